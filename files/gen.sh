@@ -23,4 +23,41 @@ openssl req \
     -extensions SAN \
     -config <(cat $sslConfig <(printf '[SAN]\nsubjectAltName=DNS:localhost')) \
     -sha256 \
-    -days ${days}
\ No newline at end of file
+    -days ${days}
+
+# CA and client certificate for mutual TLS.
+# kvsd server --tls-client-ca ca.pem verifies client.pem.
+caKeyName="ca.key"
+caCertName="ca.pem"
+clientKeyName="client.key"
+clientCertName="client.pem"
+
+openssl req \
+    -newkey rsa:${keyLength} \
+    -x509 \
+    -nodes \
+    -keyout ${caKeyName} \
+    -new \
+    -out ${caCertName} \
+    -subj "/CN=kvsd dev CA" \
+    -addext basicConstraints=critical,CA:TRUE \
+    -addext keyUsage=critical,keyCertSign,cRLSign \
+    -sha256 \
+    -days ${days}
+
+openssl req \
+    -newkey rsa:${keyLength} \
+    -nodes \
+    -keyout ${clientKeyName} \
+    -new \
+    -subj /CN=kvsduser \
+    -sha256 |
+openssl x509 \
+    -req \
+    -CA ${caCertName} \
+    -CAkey ${caKeyName} \
+    -CAcreateserial \
+    -out ${clientCertName} \
+    -extfile <(printf 'basicConstraints=CA:FALSE\nextendedKeyUsage=clientAuth\nsubjectAltName=DNS:kvsduser.client.local,URI:spiffe://kvsd.local/kvsduser') \
+    -sha256 \
+    -days ${days}
@@ -28,10 +28,12 @@ fn init_tracing() {
 async fn main() {
     init_tracing();
 
-    let KvsadminCommand { command } = cli::admin::parse();
+    let KvsadminCommand { client, command } = cli::admin::parse();
 
     let result = match command {
-        cli::admin::Command::Table(table) => table.run().await,
+        cli::admin::Command::Table(table) => table.run(client).await,
+        cli::admin::Command::Namespace(namespace) => namespace.run(client).await,
+        cli::admin::Command::User(user) => user.run().await,
     };
 
     if let Err(err) = result {
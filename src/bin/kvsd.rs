@@ -52,6 +52,7 @@ async fn run_inner() -> kvsd::Result<()> {
         Command::Delete(delete) => delete.run(authenticate(client).await?).await,
         Command::Get(get) => get.run(authenticate(client).await?).await,
         Command::Set(set) => set.run(authenticate(client).await?).await,
+        Command::Scan(scan) => scan.run(authenticate(client).await?).await,
         Command::Server(server) => server.run(client.disable_tls).await,
     }
 }
@@ -1,12 +1,18 @@
+use crate::cli::ClientOptions;
 use crate::Result;
 use clap::{Parser, Subcommand};
 
+mod namespace;
 mod table;
+mod user;
 
 /// Kvsadmin command
 #[derive(Parser, Debug)]
 #[command(version, propagate_version = true, subcommand_required = true)]
 pub struct KvsadminCommand {
+    /// Client options
+    #[command(flatten)]
+    pub client: ClientOptions,
     #[command(subcommand)]
     pub command: Command,
 }
@@ -14,14 +20,18 @@ pub struct KvsadminCommand {
 #[derive(Subcommand, Debug)]
 pub enum Command {
     Table(table::TableCommand),
+    Namespace(namespace::NamespaceCommand),
+    User(user::UserCommand),
 }
 
 impl KvsadminCommand {
     pub async fn run(self) -> Result<()> {
-        let KvsadminCommand { command } = self;
+        let KvsadminCommand { client, command } = self;
 
         match command {
-            Command::Table(table) => table.run().await,
+            Command::Table(table) => table.run(client).await,
+            Command::Namespace(namespace) => namespace.run(client).await,
+            Command::User(user) => user.run().await,
         }
     }
 }
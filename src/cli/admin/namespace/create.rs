@@ -0,0 +1,23 @@
+use clap::Args;
+
+use crate::client::Api;
+use crate::Result;
+
+/// Create namespace
+#[derive(Args, Debug)]
+pub struct CreateCommand {
+    /// Namespace name
+    #[arg(value_name = "NAMESPACE")]
+    namespace: String,
+}
+
+impl CreateCommand {
+    pub async fn run(self, mut client: Box<dyn Api>) -> Result<()> {
+        let CreateCommand { namespace } = self;
+
+        client.create_namespace(namespace).await?;
+        println!("OK");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,23 @@
+use clap::Args;
+
+use crate::client::Api;
+use crate::Result;
+
+/// Drop empty namespace
+#[derive(Args, Debug)]
+pub struct DropCommand {
+    /// Namespace name
+    #[arg(value_name = "NAMESPACE")]
+    namespace: String,
+}
+
+impl DropCommand {
+    pub async fn run(self, mut client: Box<dyn Api>) -> Result<()> {
+        let DropCommand { namespace } = self;
+
+        client.drop_namespace(namespace).await?;
+        println!("OK");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+mod create;
+mod drop;
+
+use crate::cli::{authenticate, ClientOptions};
+use crate::Result;
+use clap::{Args, Subcommand};
+
+#[derive(Args, Debug)]
+pub struct NamespaceCommand {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    Create(create::CreateCommand),
+    Drop(drop::DropCommand),
+}
+
+impl NamespaceCommand {
+    pub async fn run(self, client: ClientOptions) -> Result<()> {
+        let NamespaceCommand { command } = self;
+
+        match command {
+            Command::Create(create) => create.run(authenticate(client).await?).await,
+            Command::Drop(drop) => drop.run(authenticate(client).await?).await,
+        }
+    }
+}
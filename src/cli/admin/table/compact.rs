@@ -0,0 +1,26 @@
+use clap::Args;
+
+use crate::client::Api;
+use crate::Result;
+
+/// Compact table file on the running server
+#[derive(Args, Debug)]
+pub struct CompactCommand {
+    /// Namespace the table belongs to
+    #[arg(value_name = "NAMESPACE")]
+    namespace: String,
+    /// Table name
+    #[arg(value_name = "TABLE")]
+    table: String,
+}
+
+impl CompactCommand {
+    pub async fn run(self, mut client: Box<dyn Api>) -> Result<()> {
+        let CompactCommand { namespace, table } = self;
+
+        client.compact_table(namespace, table).await?;
+        println!("OK");
+
+        Ok(())
+    }
+}
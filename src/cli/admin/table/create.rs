@@ -0,0 +1,26 @@
+use clap::Args;
+
+use crate::client::Api;
+use crate::Result;
+
+/// Create table
+#[derive(Args, Debug)]
+pub struct CreateCommand {
+    /// Namespace the table belongs to
+    #[arg(value_name = "NAMESPACE")]
+    namespace: String,
+    /// Table name
+    #[arg(value_name = "TABLE")]
+    table: String,
+}
+
+impl CreateCommand {
+    pub async fn run(self, mut client: Box<dyn Api>) -> Result<()> {
+        let CreateCommand { namespace, table } = self;
+
+        client.create_table(namespace, table).await?;
+        println!("OK");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,26 @@
+use clap::Args;
+
+use crate::client::Api;
+use crate::Result;
+
+/// Drop table and delete its data
+#[derive(Args, Debug)]
+pub struct DropCommand {
+    /// Namespace the table belongs to
+    #[arg(value_name = "NAMESPACE")]
+    namespace: String,
+    /// Table name
+    #[arg(value_name = "TABLE")]
+    table: String,
+}
+
+impl DropCommand {
+    pub async fn run(self, mut client: Box<dyn Api>) -> Result<()> {
+        let DropCommand { namespace, table } = self;
+
+        client.drop_table(namespace, table).await?;
+        println!("OK");
+
+        Ok(())
+    }
+}
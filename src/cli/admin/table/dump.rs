@@ -5,7 +5,7 @@ use clap::Args;
 use serde_json::json;
 
 use crate::{
-    core::{EntryDump, Table},
+    core::{dump_file, EntryDump},
     Result,
 };
 
@@ -27,15 +27,13 @@ impl DumpCommand {
 
         tracing::debug!("Dump {}", path.display());
 
-        let mut table = Table::from_path(path).await?;
         let mut stdout = std::io::stdout();
         println!(r#"{{"entries": ["#);
-        table
-            .dump(|entry| {
-                let v = dump(entry);
-                serde_json::to_writer_pretty(&mut stdout, &v).unwrap();
-            })
-            .await?;
+        dump_file(&path, |entry| {
+            let v = dump(entry);
+            serde_json::to_writer_pretty(&mut stdout, &v).unwrap();
+        })
+        .await?;
         println!(r#"]}}"#);
 
         Ok(())
@@ -48,9 +46,15 @@ fn dump(entry: EntryDump) -> serde_json::Value {
         .unwrap()
         .to_rfc3339();
     let value = String::from_utf8_lossy(&entry.value);
+    let expires_at = entry
+        .expires_at_ms
+        .and_then(|ms| Utc.timestamp_millis_opt(ms).single())
+        .map(|t| t.to_rfc3339());
 
     json!({
         "time": time,
+        "expires_at": expires_at,
+        "version": entry.version,
         "is_deleted": entry.is_deleted,
         "key": entry.key,
         "value": value,
@@ -0,0 +1,18 @@
+use clap::Args;
+
+use crate::client::Api;
+use crate::Result;
+
+/// List tables
+#[derive(Args, Debug)]
+pub struct ListCommand {}
+
+impl ListCommand {
+    pub async fn run(self, mut client: Box<dyn Api>) -> Result<()> {
+        for (namespace, table) in client.list_tables().await? {
+            println!("{}/{}", namespace, table);
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,10 @@
+mod compact;
+mod create;
+mod drop;
 mod dump;
+mod list;
 
+use crate::cli::{authenticate, ClientOptions};
 use crate::Result;
 use clap::{Args, Subcommand};
 
@@ -12,14 +17,22 @@ pub struct TableCommand {
 #[derive(Subcommand, Debug)]
 pub enum Command {
     Dump(dump::DumpCommand),
+    Compact(compact::CompactCommand),
+    Create(create::CreateCommand),
+    Drop(drop::DropCommand),
+    List(list::ListCommand),
 }
 
 impl TableCommand {
-    pub async fn run(self) -> Result<()> {
+    pub async fn run(self, client: ClientOptions) -> Result<()> {
         let TableCommand { command } = self;
 
         match command {
             Command::Dump(dump) => dump.run().await,
+            Command::Compact(compact) => compact.run(authenticate(client).await?).await,
+            Command::Create(create) => create.run(authenticate(client).await?).await,
+            Command::Drop(drop) => drop.run(authenticate(client).await?).await,
+            Command::List(list) => list.run(authenticate(client).await?).await,
         }
     }
 }
@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use serde_yaml::{Mapping, Value};
+
+use crate::core::password;
+use crate::{KvsdError, Result};
+
+/// Add user with hashed password to the config file
+///
+/// The config file is rewritten, so its comments are not preserved.
+#[derive(Args, Debug)]
+pub struct AddUserCommand {
+    /// Config file path
+    #[arg(long, default_value = "./files/config.yaml")]
+    config: PathBuf,
+    /// Username
+    #[arg(value_name = "USERNAME")]
+    username: String,
+    /// Password of the user. read from stdin if omitted
+    #[arg(value_name = "PASSWORD")]
+    password: Option<String>,
+}
+
+impl AddUserCommand {
+    pub async fn run(self) -> Result<()> {
+        let AddUserCommand {
+            config,
+            username,
+            password,
+        } = self;
+
+        let password_hash = password::hash(&super::read_password(password)?)?;
+
+        let invalid_config = |err: serde_yaml::Error| {
+            KvsdError::InvalidArgument(format!("config {}: {}", config.display(), err))
+        };
+        let mut root: Value = serde_yaml::from_str(&tokio::fs::read_to_string(&config).await?)
+            .map_err(invalid_config)?;
+
+        let users = Self::users(&mut root).ok_or_else(|| {
+            KvsdError::InvalidArgument(format!(
+                "config {}: kvsd.users is not a list",
+                config.display()
+            ))
+        })?;
+        let exists = users
+            .iter()
+            .any(|user| user.get("username").and_then(Value::as_str) == Some(username.as_str()));
+        if exists {
+            return Err(KvsdError::AlreadyExists(format!("user {}", username)));
+        }
+
+        let mut user = Mapping::new();
+        user.insert("username".into(), username.as_str().into());
+        user.insert("password_hash".into(), password_hash.into());
+        users.push(Value::Mapping(user));
+
+        tokio::fs::write(
+            &config,
+            serde_yaml::to_string(&root).map_err(invalid_config)?,
+        )
+        .await?;
+        println!("OK");
+
+        Ok(())
+    }
+
+    // Get kvsd.users creating the missing entries.
+    fn users(root: &mut Value) -> Option<&mut Vec<Value>> {
+        if root.is_null() {
+            *root = Value::Mapping(Mapping::new());
+        }
+        let kvsd = root
+            .as_mapping_mut()?
+            .entry("kvsd".into())
+            .or_insert(Value::Null);
+        if kvsd.is_null() {
+            *kvsd = Value::Mapping(Mapping::new());
+        }
+        let users = kvsd
+            .as_mapping_mut()?
+            .entry("users".into())
+            .or_insert(Value::Null);
+        if users.is_null() {
+            *users = Value::Sequence(Vec::new());
+        }
+        users.as_sequence_mut()
+    }
+}
@@ -0,0 +1,23 @@
+use clap::Args;
+
+use crate::core::password;
+use crate::Result;
+
+/// Print argon2id hash of the password for password_hash of the users
+#[derive(Args, Debug)]
+pub struct HashPasswordCommand {
+    /// Password to hash. read from stdin if omitted
+    #[arg(value_name = "PASSWORD")]
+    password: Option<String>,
+}
+
+impl HashPasswordCommand {
+    pub async fn run(self) -> Result<()> {
+        let HashPasswordCommand { password } = self;
+
+        let password = super::read_password(password)?;
+        println!("{}", password::hash(&password)?);
+
+        Ok(())
+    }
+}
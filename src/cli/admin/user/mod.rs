@@ -0,0 +1,51 @@
+mod add_user;
+mod hash_password;
+
+use std::io::BufRead;
+
+use crate::Result;
+use clap::{Args, Subcommand};
+
+#[derive(Args, Debug)]
+pub struct UserCommand {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    HashPassword(hash_password::HashPasswordCommand),
+    AddUser(add_user::AddUserCommand),
+}
+
+impl UserCommand {
+    pub async fn run(self) -> Result<()> {
+        let UserCommand { command } = self;
+
+        match command {
+            Command::HashPassword(hash_password) => hash_password.run().await,
+            Command::AddUser(add_user) => add_user.run().await,
+        }
+    }
+}
+
+// Take the password from the argument, or read the first line of stdin
+// so that the password does not remain in the shell history.
+fn read_password(password: Option<String>) -> Result<String> {
+    let password = match password {
+        Some(password) => password,
+        None => {
+            let mut line = String::new();
+            std::io::stdin().lock().read_line(&mut line)?;
+            line.trim_end_matches(['\r', '\n']).to_owned()
+        }
+    };
+
+    if password.is_empty() {
+        return Err(crate::KvsdError::InvalidArgument(
+            "password is empty".into(),
+        ));
+    }
+
+    Ok(password)
+}
@@ -1,7 +1,7 @@
 use clap::Args;
 
 use crate::client::Api;
-use crate::protocol::Key;
+use crate::protocol::{Key, Previous};
 use crate::Result;
 
 #[derive(Args, Debug)]
@@ -16,9 +16,12 @@ impl DeleteCommand {
         let key = Key::new(key)?;
 
         match client.delete(key).await? {
-            Some(value) => {
+            Some(Previous::Value(value)) => {
                 println!("OK old value: {:?}", value);
             }
+            Some(Previous::TooLarge(bytes)) => {
+                println!("OK old value: {} bytes", bytes);
+            }
             None => {
                 println!("OK");
             }
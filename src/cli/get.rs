@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::Args;
 
 use crate::client::Api;
@@ -8,14 +10,29 @@ use crate::Result;
 pub struct GetCommand {
     #[arg(value_name = "KEY")]
     key: String,
+    /// Write the value to the file by stream. the value can be larger than the maximum value bytes.
+    #[arg(long = "output", value_name = "PATH")]
+    output: Option<PathBuf>,
 }
 
 impl GetCommand {
     pub async fn run(self, mut client: Box<dyn Api>) -> Result<()> {
-        let GetCommand { key } = self;
+        let GetCommand { key, output } = self;
 
         let key = Key::new(key)?;
 
+        if let Some(output) = output {
+            let mut file = tokio::fs::File::create(&output).await?;
+            match client.get_stream(key, &mut file).await? {
+                Some(bytes) => println!("OK bytes: {}", bytes),
+                None => {
+                    tokio::fs::remove_file(&output).await?;
+                    println!("Not Found");
+                }
+            }
+            return Ok(());
+        }
+
         match client.get(key).await? {
             Some(value) => {
                 println!("{:?}", value);
@@ -1,11 +1,12 @@
 //! Define the features to support as a cli.
 
 mod root;
-pub use root::{authenticate, parse, Command, KvsdCommand};
+pub use root::{authenticate, parse, ClientOptions, Command, KvsdCommand};
 
 pub mod admin;
 mod delete;
 mod get;
 mod ping;
+mod scan;
 mod server;
 mod set;
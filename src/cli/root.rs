@@ -1,9 +1,12 @@
+use std::path::PathBuf;
+
 use clap::{ArgAction, Args, Parser, Subcommand};
 
-use crate::cli::{delete, get, ping, server, set};
-use crate::client::tcp::UnauthenticatedClient;
+use crate::cli::{delete, get, ping, scan, server, set};
+use crate::client::tcp::{TlsOptions, UnauthenticatedClient};
 use crate::client::Api;
 use crate::server::DEFAULT_PORT;
+use crate::KvsdError;
 
 /// Kvsd command
 #[derive(Parser, Debug)]
@@ -32,9 +35,46 @@ pub struct ClientOptions {
     /// Password
     #[arg(long, env = "KVSD_PASSWORD", default_value = "secret", global = true)]
     pub password: String,
+    /// Connect to the unix domain socket instead of the host and port
+    #[arg(long, env = "KVSD_UNIX_SOCKET", global = true)]
+    pub unix_socket: Option<PathBuf>,
+    /// Skip the password authentication.
+    /// the server authenticates the unix socket connection by the socket file permission,
+    /// or the tls connection by the client certificate
+    #[arg(long, action = ArgAction::SetTrue, global = true)]
+    pub peer_auth: bool,
+    /// Tls client certificate file path presented to the server
+    #[arg(
+        long,
+        env = "KVSD_TLS_CLIENT_CERT",
+        requires = "tls_client_key",
+        global = true
+    )]
+    pub tls_client_cert: Option<PathBuf>,
+    /// Tls client private key file path
+    #[arg(
+        long,
+        env = "KVSD_TLS_CLIENT_KEY",
+        requires = "tls_client_cert",
+        global = true
+    )]
+    pub tls_client_key: Option<PathBuf>,
     /// Disable tls connections
     #[arg(long,env = "KVSD_DISABLE_TLS", action = ArgAction::SetTrue, global = true)]
     pub disable_tls: bool,
+    /// CA certificates file to verify the server certificate.
+    /// the system root certificates are used if not specified
+    #[arg(long, env = "KVSD_TLS_CA_FILE", global = true)]
+    pub ca_file: Option<PathBuf>,
+    /// Skip the server certificate verification. for development only
+    #[arg(long, env = "KVSD_INSECURE", action = ArgAction::SetTrue, conflicts_with = "ca_file", global = true)]
+    pub insecure: bool,
+    /// Namespace of the table to operate
+    #[arg(long, env = "KVSD_NAMESPACE", default_value = "default", global = true)]
+    pub namespace: String,
+    /// Table to operate
+    #[arg(long, env = "KVSD_TABLE", default_value = "default", global = true)]
+    pub table: String,
 }
 
 /// Subcommands
@@ -48,8 +88,10 @@ pub enum Command {
     Get(get::GetCommand),
     /// Set
     Set(set::SetCommand),
+    /// Scan
+    Scan(scan::ScanCommand),
     /// Server
-    Server(server::ServerCommand),
+    Server(Box<server::ServerCommand>),
 }
 
 /// Parse command line args
@@ -64,21 +106,60 @@ pub async fn authenticate(options: ClientOptions) -> crate::Result<Box<dyn Api>>
         port,
         username,
         password,
+        unix_socket,
+        peer_auth,
+        tls_client_cert,
+        tls_client_key,
         disable_tls,
+        ca_file,
+        insecure,
+        namespace,
+        table,
     } = options;
 
-    let client: Box<dyn Api> = if disable_tls {
+    if peer_auth && unix_socket.is_none() && tls_client_cert.is_none() {
+        return Err(KvsdError::InvalidArgument(
+            "--peer-auth requires --unix-socket or --tls-client-cert".into(),
+        ));
+    }
+
+    let mut client: Box<dyn Api> = if let Some(unix_socket) = unix_socket {
+        let client = UnauthenticatedClient::from_unix_socket(unix_socket).await?;
+        if peer_auth {
+            Box::new(client.authenticated_by_peer())
+        } else {
+            client
+                .authenticate(username, password)
+                .await
+                .map(Box::new)?
+        }
+    } else if disable_tls {
         UnauthenticatedClient::insecure_from_addr(host, port)
             .await?
             .authenticate(username, password)
             .await
             .map(Box::new)?
     } else {
-        UnauthenticatedClient::from_addr(host, port)
-            .await?
-            .authenticate(username, password)
-            .await
-            .map(Box::new)?
+        let tls_options = TlsOptions {
+            ca_file,
+            client_certificate: tls_client_cert.zip(tls_client_key),
+            insecure,
+        };
+        let client =
+            UnauthenticatedClient::from_addr_with_options(host, port, &tls_options).await?;
+        if peer_auth {
+            Box::new(client.authenticated_by_peer())
+        } else {
+            client
+                .authenticate(username, password)
+                .await
+                .map(Box::new)?
+        }
     };
+
+    if namespace != "default" || table != "default" {
+        client.use_table(namespace, table).await?;
+    }
+
     Ok(client)
 }
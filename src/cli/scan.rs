@@ -0,0 +1,46 @@
+use clap::Args;
+use tokio_stream::StreamExt;
+
+use crate::client::{Api, ScanOptions};
+use crate::Result;
+
+#[derive(Args, Debug)]
+pub struct ScanCommand {
+    /// Only keys starting with the prefix
+    #[arg(long)]
+    prefix: Option<String>,
+    /// Inclusive lower bound of the keys
+    #[arg(long)]
+    start: Option<String>,
+    /// Exclusive upper bound of the keys
+    #[arg(long)]
+    end: Option<String>,
+    /// Maximum entries fetched per request
+    #[arg(long)]
+    limit: Option<u64>,
+}
+
+impl ScanCommand {
+    pub async fn run(self, mut client: Box<dyn Api>) -> Result<()> {
+        let ScanCommand {
+            prefix,
+            start,
+            end,
+            limit,
+        } = self;
+
+        let mut entries = client.scan(ScanOptions {
+            prefix,
+            start,
+            end,
+            limit,
+            cursor: None,
+        });
+
+        while let Some((key, value)) = entries.next().await.transpose()? {
+            println!("{} {:?}", key, value);
+        }
+
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use clap::Args;
+use clap::{ArgAction, Args};
 
 use crate::common::debug;
 use crate::config::Initializer;
@@ -33,6 +33,24 @@ pub struct ServerCommand {
     /// Tcp binding address port
     #[arg(long, env = "KVSD_SERVER_PORT")]
     bind_port: Option<String>,
+    /// Tcp binding address port of the redis compatible listener.
+    /// the listener is disabled if not specified
+    #[arg(long, env = "KVSD_SERVER_RESP_PORT")]
+    resp_port: Option<String>,
+    /// Tcp binding address port of the http gateway.
+    /// the gateway is disabled if not specified. requires the http feature
+    #[arg(long, env = "KVSD_SERVER_HTTP_PORT")]
+    http_port: Option<String>,
+    /// Unix domain socket path to listen in addition to tcp
+    #[arg(long, env = "KVSD_SERVER_UNIX_SOCKET")]
+    bind_unix_socket: Option<PathBuf>,
+    /// Permission of the unix socket file in octal(e.g. 660)
+    #[arg(long, env = "KVSD_SERVER_UNIX_SOCKET_MODE", value_parser = parse_mode)]
+    unix_socket_mode: Option<u32>,
+    /// User as whom the unix socket connections are authenticated without password.
+    /// access is controlled by the permission of the socket file
+    #[arg(long, env = "KVSD_SERVER_UNIX_SOCKET_USER")]
+    unix_socket_user: Option<String>,
     /// Root directory where kvsd store it's data
     #[arg(long, env = "KVSD_DIR", default_value = ".kvsd")]
     kvsd_dir: PathBuf,
@@ -42,6 +60,16 @@ pub struct ServerCommand {
     /// Tls server private key file path
     #[arg(long, env = "KVSD_TLS_KEY", default_value = "./files/localhost.key")]
     key: PathBuf,
+    /// CA bundle file path to verify the tls client certificates.
+    /// clients presenting the certificate mapped to a user skip the password authentication
+    #[arg(long, env = "KVSD_TLS_CLIENT_CA")]
+    tls_client_ca: Option<PathBuf>,
+    /// Reject the tls clients without certificate
+    #[arg(long, env = "KVSD_TLS_CLIENT_CERT_REQUIRED", action = ArgAction::SetTrue, requires = "tls_client_ca")]
+    tls_client_cert_required: bool,
+    /// Do not negotiate the compression of the values with the clients
+    #[arg(long, env = "KVSD_SERVER_DISABLE_COMPRESSION", action = ArgAction::SetTrue)]
+    disable_compression: bool,
 }
 
 impl ServerCommand {
@@ -53,9 +81,17 @@ impl ServerCommand {
             config,
             mut bind_host,
             mut bind_port,
+            mut resp_port,
+            mut http_port,
+            mut bind_unix_socket,
+            unix_socket_mode,
+            mut unix_socket_user,
             kvsd_dir,
             cert,
             key,
+            mut tls_client_ca,
+            tls_client_cert_required,
+            disable_compression,
         } = self;
 
         tokio::fs::create_dir_all(&kvsd_dir).await?;
@@ -71,9 +107,17 @@ impl ServerCommand {
             config.set_authenticate_timeout_milliseconds(authenticate_timeout_milliseconds);
             config.set_listen_host(&mut bind_host);
             config.set_listen_port(&mut bind_port);
+            config.set_resp_listen_port(&mut resp_port);
+            config.set_http_listen_port(&mut http_port);
+            config.set_unix_socket_path(&mut bind_unix_socket);
+            config.set_unix_socket_mode(unix_socket_mode);
+            config.set_unix_socket_user(&mut unix_socket_user);
             config.set_disable_tls(&mut Some(disable_tls));
             config.set_tls_certificate(&mut Some(cert));
             config.set_tls_key(&mut Some(key));
+            config.set_tls_client_ca(&mut tls_client_ca);
+            config.set_tls_client_cert_required(&mut tls_client_cert_required.then_some(true));
+            config.set_disable_compression(&mut disable_compression.then_some(true));
             config
         };
 
@@ -91,3 +135,7 @@ impl ServerCommand {
             .map_err(KvsdError::from)
     }
 }
+
+fn parse_mode(s: &str) -> std::result::Result<u32, String> {
+    u32::from_str_radix(s.trim_start_matches("0o"), 8).map_err(|err| err.to_string())
+}
@@ -1,28 +1,92 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
 use clap::Args;
 
 use crate::client::Api;
-use crate::protocol::{Key, Value};
+use crate::protocol::{Condition, Key, Value};
 use crate::Result;
 
 #[derive(Args, Debug)]
 pub struct SetCommand {
     #[arg(value_name = "KEY", index = 1)]
     key: String,
-    #[arg(value_name = "VALUE", index = 2)]
-    value: String,
+    #[arg(value_name = "VALUE", index = 2, required_unless_present = "file")]
+    value: Option<String>,
+    /// Set the content of the file by stream. the file can be larger than the maximum value bytes.
+    #[arg(long = "file", value_name = "PATH", conflicts_with_all = ["value", "condition"])]
+    file: Option<PathBuf>,
+    /// Expire the key after the milliseconds.
+    #[arg(long = "ttl-ms", value_name = "MILLISECONDS", group = "condition")]
+    ttl_ms: Option<u64>,
+    /// Set only if the key does not exist.
+    #[arg(long = "if-absent", group = "condition")]
+    if_absent: bool,
+    /// Set only if the key exists.
+    #[arg(long = "if-present", group = "condition")]
+    if_present: bool,
+    /// Set only if the current value equals to the value.
+    #[arg(long = "if-value", value_name = "VALUE", group = "condition")]
+    if_value: Option<String>,
+    /// Set only if the current version equals to the version.
+    #[arg(long = "if-version", value_name = "VERSION", group = "condition")]
+    if_version: Option<u64>,
 }
 
 impl SetCommand {
     pub async fn run(self, mut client: Box<dyn Api>) -> Result<()> {
-        let SetCommand { key, value } = self;
+        let condition = self.condition()?;
+        let SetCommand {
+            key,
+            value,
+            file,
+            ttl_ms,
+            ..
+        } = self;
 
         let key = Key::new(key)?;
-        let value = Value::new(value.as_bytes())?;
+        if let Some(file) = file {
+            let mut file = tokio::fs::File::open(file).await?;
+            let version = client.set_stream(key, &mut file).await?;
+            println!("OK version: {}", version);
+            return Ok(());
+        }
+        let value = Value::new(value.unwrap_or_default().as_bytes())?;
+
+        if let Some(condition) = condition {
+            match client.set_if(key, value, condition).await? {
+                Some(version) => println!("OK version: {}", version),
+                None => println!("condition not satisfied"),
+            }
+            return Ok(());
+        }
 
-        if client.set(key, value).await.is_ok() {
+        let result = match ttl_ms {
+            Some(ttl_ms) => {
+                client
+                    .set_with_ttl(key, value, Duration::from_millis(ttl_ms))
+                    .await
+            }
+            None => client.set(key, value).await,
+        };
+
+        if result.is_ok() {
             println!("OK");
         }
 
         Ok(())
     }
+
+    fn condition(&self) -> Result<Option<Condition>> {
+        let condition = if self.if_absent {
+            Some(Condition::Absent)
+        } else if self.if_present {
+            Some(Condition::Present)
+        } else if let Some(value) = &self.if_value {
+            Some(Condition::Value(Value::new(value.as_bytes())?))
+        } else {
+            self.if_version.map(Condition::Version)
+        };
+        Ok(condition)
+    }
 }
@@ -1,8 +1,13 @@
 //! Provides an implementation of kvsd protocol communication with the kvsd server.
 
+use std::pin::Pin;
+use std::time::Duration;
+
 use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_stream::Stream;
 
-use crate::{Key, Result, Value};
+use crate::{Condition, Expiry, Key, Previous, Result, Value};
 
 /// tcp client implementation.
 pub mod tcp;
@@ -13,13 +18,173 @@ pub trait Api {
     /// Ping to server.
     async fn ping(&mut self) -> Result<chrono::Duration>;
 
+    /// Switch the table to which subsequent key value operations are applied.
+    /// "default/default" table is used until this is called.
+    async fn use_table(&mut self, namespace: String, table: String) -> Result<()>;
+
     /// Set given key value to remote kvsd.
     async fn set(&mut self, key: Key, value: Value) -> Result<()>;
 
+    /// Set given key value only if the condition is satisfied.
+    /// return the version of the written value, or None if the condition is not satisfied.
+    async fn set_if(&mut self, key: Key, value: Value, condition: Condition)
+        -> Result<Option<u64>>;
+
     /// Get the value corresponding to the key.
     async fn get(&mut self, key: Key) -> Result<Option<Value>>;
 
+    /// Get the value corresponding to the key with its version.
+    /// the version can be used for [`Condition::Version`].
+    async fn get_versioned(&mut self, key: Key) -> Result<Option<(Value, u64)>>;
+
     /// Delete the value corresponding to the key.
     /// if the key exists, return the deleted value.
-    async fn delete(&mut self, key: Key) -> Result<Option<Value>>;
+    /// value set by stream which is too large to be returned is [`Previous::TooLarge`].
+    async fn delete(&mut self, key: Key) -> Result<Option<Previous>>;
+
+    /// Set the value read from the reader to the end.
+    /// the value is sent by chunks, so values larger than
+    /// [`MAX_VALUE_BYTES`](crate::protocol::MAX_VALUE_BYTES) can be set.
+    /// return the version of the written value.
+    async fn set_stream(
+        &mut self,
+        key: Key,
+        value: &mut (dyn AsyncRead + Unpin + Send),
+    ) -> Result<u64>;
+
+    /// Write the value corresponding to the key to the writer.
+    /// the value is received by chunks, so values set by [`Api::set_stream`] can be read.
+    /// return the number of written bytes, or None if the key does not exist.
+    async fn get_stream(
+        &mut self,
+        key: Key,
+        out: &mut (dyn AsyncWrite + Unpin + Send),
+    ) -> Result<Option<u64>>;
+
+    /// Set given key values atomically.
+    async fn mset(&mut self, entries: Vec<(Key, Value)>) -> Result<()>;
+
+    /// Get the values corresponding to the keys in the order of the keys.
+    async fn mget(&mut self, keys: Vec<Key>) -> Result<Vec<Option<Value>>>;
+
+    /// Delete the values corresponding to the keys atomically.
+    /// return the deleted values in the order of the keys.
+    async fn mdelete(&mut self, keys: Vec<Key>) -> Result<Vec<Option<Previous>>>;
+
+    /// Set given key value which expires after the ttl.
+    async fn set_with_ttl(&mut self, key: Key, value: Value, ttl: Duration) -> Result<()>;
+
+    /// Set the ttl of the key.
+    /// return false if the key does not exist.
+    async fn expire(&mut self, key: Key, ttl: Duration) -> Result<bool>;
+
+    /// Remove the ttl of the key so that it never expires.
+    /// return false if the key does not exist.
+    async fn persist(&mut self, key: Key) -> Result<bool>;
+
+    /// Get the remaining ttl of the key.
+    /// return None if the key does not exist.
+    async fn ttl(&mut self, key: Key) -> Result<Option<Expiry>>;
+
+    /// Begin the transaction.
+    /// subsequent get, set and delete are buffered until commit or abort.
+    /// they may touch keys in several tables by switching table with `use_table`.
+    async fn begin(&mut self) -> Result<()>;
+
+    /// Commit the transaction atomically.
+    /// return [`KvsdError::Conflict`](crate::KvsdError::Conflict)
+    /// if the keys read in the transaction have been changed.
+    async fn commit(&mut self) -> Result<()>;
+
+    /// Abort the transaction and discard the buffered writes.
+    async fn abort(&mut self) -> Result<()>;
+
+    /// Send the requests without waiting for each response.
+    /// return the result of each request in the order of the requests.
+    /// previous value which is too large to be returned fails the result with
+    /// [`KvsdError::MaxValueBytes`](crate::KvsdError::MaxValueBytes), though the write is applied.
+    async fn pipeline(
+        &mut self,
+        requests: Vec<Request>,
+        mode: PipelineMode,
+    ) -> Result<Vec<Result<Option<Value>>>>;
+
+    /// Scan the key values in key order.
+    /// pages are requested from the server as the stream is consumed.
+    fn scan(&mut self, options: ScanOptions) -> ScanStream<'_>;
+
+    /// Create the namespace.
+    async fn create_namespace(&mut self, namespace: String) -> Result<()>;
+
+    /// Drop the namespace.
+    /// namespace must not have any tables.
+    async fn drop_namespace(&mut self, namespace: String) -> Result<()>;
+
+    /// Create the table in the namespace.
+    async fn create_table(&mut self, namespace: String, table: String) -> Result<()>;
+
+    /// Drop the table and delete its data.
+    async fn drop_table(&mut self, namespace: String, table: String) -> Result<()>;
+
+    /// Compact the table file and wait for the compaction to complete.
+    async fn compact_table(&mut self, namespace: String, table: String) -> Result<()>;
+
+    /// List the (namespace, table) pairs.
+    async fn list_tables(&mut self) -> Result<Vec<(String, String)>>;
+}
+
+/// Request sent by [`Api::pipeline`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+    /// Respond the value.
+    Get(Key),
+    /// Respond the previous value.
+    Set(Key, Value),
+    /// Respond the deleted value.
+    Delete(Key),
+}
+
+/// How the server handles the pipelined requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipelineMode {
+    /// Requests are handled one by one in order, as if they were sent one at a time.
+    Ordered,
+    /// Requests are handled concurrently and may be applied in any order,
+    /// so they should not depend on each other.
+    /// responses are matched with the requests by request id.
+    Concurrent,
+}
+
+/// Stream of the key values returned by [`Api::scan`].
+pub type ScanStream<'a> = Pin<Box<dyn Stream<Item = Result<(Key, Value)>> + Send + 'a>>;
+
+/// Scan options.
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// Only keys starting with the prefix.
+    pub prefix: Option<String>,
+    /// Inclusive lower bound of the keys.
+    pub start: Option<String>,
+    /// Exclusive upper bound of the keys.
+    pub end: Option<String>,
+    /// Maximum entries per page. server caps it, and ends the page early by bytes.
+    pub limit: Option<u64>,
+    /// Resume the scan after this key.
+    pub cursor: Option<String>,
+}
+
+impl ScanOptions {
+    const DEFAULT_LIMIT: u64 = 100;
+
+    /// Construct options scanning the keys starting with the prefix.
+    pub fn with_prefix(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: Some(prefix.into()),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn limit(&self) -> u64 {
+        self.limit.unwrap_or(ScanOptions::DEFAULT_LIMIT)
+    }
 }
@@ -1,10 +1,12 @@
 use std::net::ToSocketAddrs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use std::{convert::TryFrom, io};
 
 use async_trait::async_trait;
-use tokio::io::{AsyncRead, AsyncWrite};
-use tokio::net::TcpStream;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpStream, UnixStream};
 use tokio_rustls::{
     client::TlsStream,
     rustls::{
@@ -14,16 +16,24 @@ use tokio_rustls::{
 };
 use tokio_rustls::{rustls, TlsConnector};
 
-use crate::client::Api;
+use crate::client::{Api, PipelineMode, Request, ScanOptions, ScanStream};
 use crate::common::info;
+use crate::protocol::compression::Compression;
 use crate::protocol::connection::Connection;
-use crate::protocol::message::{Authenticate, Delete, Get, Message, Ping, Set};
-use crate::protocol::{Key, Value};
+use crate::protocol::message::{
+    Abort, Authenticate, Begin, Chunk, Commit, CompactTable, CreateNamespace, CreateTable, Delete,
+    DropNamespace, DropTable, Expire, Fail, FailCode, FrameLimits, Get, GetStream, Hello,
+    ListTables, MDelete, MGet, MSet, Message, MessageFrames, MessageType, Persist, Ping, Scan, Set,
+    SetStream, Ttl, UseTable,
+};
+use crate::protocol::{Condition, Expiry, Key, Previous, Value, MAX_CHUNK_BYTES, MAX_VALUE_BYTES};
 use crate::{KvsdError, Result};
 
 /// Implementation of client api by tcp.
 pub struct Client<T> {
     connection: Connection<T>,
+    // protocol negotiated with the server by Hello.
+    protocol: Hello,
 }
 
 /// A client that is not authenticated by the server.
@@ -37,10 +47,12 @@ where
     T: AsyncWrite + AsyncRead + Unpin,
 {
     /// Construct Client by given stream.
-    pub fn new(stream: T) -> Self {
-        Self {
-            client: Client::new(stream),
-        }
+    /// the protocol version and features are negotiated with the server before returning.
+    pub async fn new(stream: T) -> Result<Self> {
+        let mut client = Client::new(stream);
+        client.hello().await?;
+
+        Ok(Self { client })
     }
 
     /// Try authenticate by given credential.
@@ -63,6 +75,24 @@ where
             )))),
         }
     }
+
+    /// Return the client without authentication.
+    /// the server authenticates the unix socket connection if the unix socket user is configured,
+    /// and the tls connection if the client certificate is mapped to a user.
+    pub fn authenticated_by_peer(self) -> Client<T> {
+        self.client
+    }
+}
+
+impl UnauthenticatedClient<UnixStream> {
+    /// Return the client connected to the unix domain socket.
+    pub async fn from_unix_socket(path: impl AsRef<Path>) -> Result<Self> {
+        info!(path = %path.as_ref().display(), "Connecting");
+
+        let stream = UnixStream::connect(path).await?;
+
+        UnauthenticatedClient::new(stream).await
+    }
 }
 
 impl UnauthenticatedClient<TcpStream> {
@@ -77,37 +107,109 @@ impl UnauthenticatedClient<TcpStream> {
 
         let stream = tokio::net::TcpStream::connect(addr).await?;
 
-        Ok(UnauthenticatedClient::new(stream))
+        UnauthenticatedClient::new(stream).await
+    }
+}
+
+/// Options of the TLS connection to the server.
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// CA certificates file to verify the server certificate.
+    /// the system root certificates are used if not set.
+    pub ca_file: Option<PathBuf>,
+    /// Client certificate file and private key file presented to the server.
+    pub client_certificate: Option<(PathBuf, PathBuf)>,
+    /// Accept any server certificate. for development only.
+    pub insecure: bool,
+}
+
+impl TlsOptions {
+    fn client_config(&self) -> Result<rustls::ClientConfig> {
+        let builder = rustls::ClientConfig::builder();
+        let builder = if self.insecure {
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(DangerousServerCertVerifier::new()))
+        } else {
+            builder.with_root_certificates(self.root_certificates()?)
+        };
+
+        match &self.client_certificate {
+            Some((certificate, key)) => {
+                let certs = rustls_pemfile::certs(&mut io::BufReader::new(std::fs::File::open(
+                    certificate,
+                )?))
+                .collect::<io::Result<Vec<_>>>()?;
+                let key = rustls_pemfile::private_key(&mut io::BufReader::new(
+                    std::fs::File::open(key)?,
+                ))?
+                .ok_or_else(|| KvsdError::InvalidArgument("private key not found".into()))?;
+
+                builder
+                    .with_client_auth_cert(certs, key)
+                    .map_err(|err| KvsdError::InvalidArgument(err.to_string()))
+            }
+            None => Ok(builder.with_no_client_auth()),
+        }
+    }
+
+    fn root_certificates(&self) -> Result<rustls::RootCertStore> {
+        let certs = match &self.ca_file {
+            Some(ca_file) => {
+                rustls_pemfile::certs(&mut io::BufReader::new(std::fs::File::open(ca_file)?))
+                    .collect::<io::Result<Vec<_>>>()?
+            }
+            None => rustls_native_certs::load_native_certs()?,
+        };
+
+        let mut roots = rustls::RootCertStore::empty();
+        let (added, ignored) = roots.add_parsable_certificates(certs);
+        if ignored > 0 {
+            info!(ignored, "Ignore unparsable root certificates");
+        }
+        if added == 0 {
+            return Err(KvsdError::InvalidArgument(
+                "no root certificates to verify the server".into(),
+            ));
+        }
+
+        Ok(roots)
     }
 }
 
 impl UnauthenticatedClient<TlsStream<TcpStream>> {
     /// Return the client with a TLS connection to the given address.
+    /// the server certificate is verified with the system root certificates.
     pub async fn from_addr(host: impl Into<String>, port: u16) -> Result<Self> {
+        UnauthenticatedClient::from_addr_with_options(host, port, &TlsOptions::default()).await
+    }
+
+    /// Return the client with a TLS connection configured by the options.
+    /// the host is used as the server name to verify the certificate and for SNI.
+    ///
+    /// if the client certificate mapped to a user is presented, the server authenticates the connection,
+    /// so [`UnauthenticatedClient::authenticated_by_peer`] can skip the password authentication.
+    pub async fn from_addr_with_options(
+        host: impl Into<String>,
+        port: u16,
+        options: &TlsOptions,
+    ) -> Result<Self> {
         let host = host.into();
         let addr = (host.as_str(), port)
             .to_socket_addrs()?
             .next()
             .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
-        let tls_config = rustls::ClientConfig::builder()
-            .dangerous()
-            .with_custom_certificate_verifier(Arc::new(DangerousServerCertVerifier::new()))
-            .with_no_client_auth();
 
-        let connector = TlsConnector::from(Arc::new(tls_config));
+        let connector = TlsConnector::from(Arc::new(options.client_config()?));
 
-        // TODO: remove hard code
-        let domain = pki_types::ServerName::try_from("localhost")
-            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid host"))?
-            .to_owned();
+        let domain = pki_types::ServerName::try_from(host)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid host"))?;
 
         info!(%addr,?domain, "Connecting");
 
         let stream = tokio::net::TcpStream::connect(addr).await?;
 
-        Ok(UnauthenticatedClient::new(
-            connector.connect(domain, stream).await?,
-        ))
+        UnauthenticatedClient::new(connector.connect(domain, stream).await?).await
     }
 }
 
@@ -116,8 +218,113 @@ where
     T: AsyncWrite + AsyncRead + Unpin,
 {
     fn new(stream: T) -> Self {
+        let mut connection = Connection::new(stream, Some(1024 * 4));
+        connection.set_frame_limits(FrameLimits::CLIENT);
+
         Self {
-            connection: Connection::new(stream, Some(1024 * 4)),
+            connection,
+            protocol: Hello::new(),
+        }
+    }
+
+    /// Change the maximum bytes of the response read from the server. (default 64 MiB)
+    /// the response exceeding it fails with the framing error, so raise it to read the batch of
+    /// many large values at once.
+    pub fn set_max_response_bytes(&mut self, max_bytes: usize) {
+        let limits = self.connection.frame_limits();
+        self.connection.set_frame_limits(FrameLimits {
+            max_bytes,
+            ..limits
+        });
+    }
+
+    /// Change the maximum number of frames of the response read from the server. (default 65536)
+    /// each key and value of the batch and the scan page is a frame.
+    pub fn set_max_response_frames(&mut self, max_frames: u64) {
+        let limits = self.connection.frame_limits();
+        self.connection.set_frame_limits(FrameLimits {
+            max_frames,
+            ..limits
+        });
+    }
+
+    // Advertise the protocol this build speaks and keep the one negotiated by the server.
+    async fn hello(&mut self) -> Result<()> {
+        self.connection.write_message(Hello::new()).await?;
+        match self.connection.read_message().await? {
+            Some(Message::Hello(hello)) if hello.is_compatible() => {
+                self.connection
+                    .set_compression(Compression::negotiated(&hello));
+                self.protocol = hello;
+                Ok(())
+            }
+            Some(Message::Hello(hello)) => Err(KvsdError::IncompatibleProtocol(format!(
+                "server protocol version {} is not supported",
+                hello.version
+            ))),
+            Some(Message::Fail(fail)) => Err(fail.into()),
+            msg => Err(format!("unexpected message {:?}", msg).into()),
+        }
+    }
+
+    // Fail if the server does not support the message.
+    fn require(&self, message_type: MessageType) -> Result<()> {
+        if self.protocol.message_types.contains(&message_type) {
+            Ok(())
+        } else {
+            Err(KvsdError::IncompatibleProtocol(format!(
+                "server does not support {:?}",
+                message_type
+            )))
+        }
+    }
+
+    // Read the next chunk of the value from the reader.
+    // the chunk is the last one if the reader reached the end before the chunk is filled.
+    async fn read_chunk(
+        value: &mut (dyn AsyncRead + Unpin + Send),
+        buf: &mut [u8],
+    ) -> io::Result<Chunk> {
+        let mut n = 0;
+        while n < buf.len() {
+            match value.read(&mut buf[n..]).await? {
+                0 => return Ok(Chunk::new(buf[..n].to_vec(), true)),
+                read => n += read,
+            }
+        }
+        Ok(Chunk::new(buf.to_vec(), false))
+    }
+
+    // Write the request that the server responds with Success or Fail.
+    async fn request(&mut self, message: impl Into<MessageFrames>) -> Result<()> {
+        self.connection.write_message(message).await?;
+        match self.connection.read_message().await? {
+            Some(Message::Success(_)) => Ok(()),
+            Some(Message::Fail(fail)) => Err(fail.into()),
+            msg => Err(format!("unexpected message {:?}", msg).into()),
+        }
+    }
+
+    // Send the message which is responded with Values.
+    async fn request_values(
+        &mut self,
+        message: impl Into<MessageFrames>,
+    ) -> Result<Vec<Option<Previous>>> {
+        self.connection.write_message(message).await?;
+        match self.connection.read_message().await? {
+            Some(Message::Values(values)) => Ok(values.values),
+            Some(Message::Fail(fail)) => Err(fail.into()),
+            msg => Err(format!("unexpected message {:?}", msg).into()),
+        }
+    }
+
+    // Send the message which is responded with TtlResult.
+    async fn request_ttl(&mut self, message: impl Into<MessageFrames>) -> Result<Option<Expiry>> {
+        self.connection.write_message(message).await?;
+        match self.connection.read_message().await? {
+            Some(Message::TtlResult(result)) => Ok(result.expiry),
+            Some(Message::Fail(fail)) => Err(fail.into()),
+            msg => Err(format!("unexpected message {:?}", msg).into()),
         }
     }
 }
@@ -132,16 +339,24 @@ where
         let ping = Ping::new().record_client_time();
         self.connection.write_message(ping).await?;
         match self.connection.read_message().await? {
-            Some(Message::Ping(ping)) => Ok(ping.latency().unwrap()),
+            Some(Message::Ping(ping)) => ping
+                .latency()
+                .ok_or_else(|| "ping without server time".into()),
+            Some(Message::Fail(fail)) => Err(fail.into()),
             msg => Err(format!("unexpected message {:?}", msg).into()),
         }
     }
 
+    async fn use_table(&mut self, namespace: String, table: String) -> Result<()> {
+        self.request(UseTable::new(namespace, table)).await
+    }
+
     async fn set(&mut self, key: Key, value: Value) -> Result<()> {
         let set = Set::new(key, value);
         self.connection.write_message(set).await?;
         match self.connection.read_message().await? {
             Some(Message::Success(_)) => Ok(()),
+            Some(Message::Fail(fail)) => Err(fail.into()),
             msg => Err(KvsdError::Internal(Box::<
                 dyn std::error::Error + Send + Sync,
             >::from(format!(
@@ -151,25 +366,316 @@ where
         }
     }
 
+    async fn set_if(
+        &mut self,
+        key: Key,
+        value: Value,
+        condition: Condition,
+    ) -> Result<Option<u64>> {
+        let set = Set::new(key, value).with_condition(condition);
+        self.connection.write_message(set).await?;
+        match self.connection.read_message().await? {
+            Some(Message::Success(success)) => Ok(success.version()),
+            Some(Message::Fail(fail)) if fail.code() == FailCode::ConditionFailed => Ok(None),
+            Some(Message::Fail(fail)) => Err(fail.into()),
+            msg => Err(format!("unexpected message {:?}", msg).into()),
+        }
+    }
+
     async fn get(&mut self, key: Key) -> Result<Option<Value>> {
         let get = Get::new(key);
         self.connection.write_message(get).await?;
         match self.connection.read_message().await? {
             Some(Message::Success(success)) => Ok(success.value()),
-            _ => unreachable!(),
+            Some(Message::Fail(fail)) => Err(fail.into()),
+            msg => Err(format!("unexpected message {:?}", msg).into()),
         }
     }
 
-    async fn delete(&mut self, key: Key) -> Result<Option<Value>> {
+    async fn get_versioned(&mut self, key: Key) -> Result<Option<(Value, u64)>> {
+        let get = Get::new(key);
+        self.connection.write_message(get).await?;
+        match self.connection.read_message().await? {
+            Some(Message::Success(success)) => {
+                let version = success.version().unwrap_or(0);
+                Ok(success.value().map(|value| (value, version)))
+            }
+            Some(Message::Fail(fail)) => Err(fail.into()),
+            msg => Err(format!("unexpected message {:?}", msg).into()),
+        }
+    }
+
+    async fn delete(&mut self, key: Key) -> Result<Option<Previous>> {
         let delete = Delete::new(key);
         self.connection.write_message(delete).await?;
         match self.connection.read_message().await? {
-            Some(Message::Success(success)) => Ok(success.value()),
-            _ => unreachable!(),
+            Some(Message::Success(success)) => Ok(success.previous()),
+            Some(Message::Fail(fail)) => Err(fail.into()),
+            msg => Err(format!("unexpected message {:?}", msg).into()),
+        }
+    }
+
+    async fn set_stream(
+        &mut self,
+        key: Key,
+        value: &mut (dyn AsyncRead + Unpin + Send),
+    ) -> Result<u64> {
+        self.require(MessageType::SetStream)?;
+
+        self.connection
+            .feed_message(None, SetStream::new(key))
+            .await?;
+        let mut buf = vec![0; MAX_CHUNK_BYTES];
+        let mut read_error = None;
+        loop {
+            let chunk = match Client::<T>::read_chunk(value, &mut buf).await {
+                Ok(chunk) => chunk,
+                Err(err) => {
+                    // Abort the upload so that the partial value is not set.
+                    self.connection
+                        .write_message(Fail::new(FailCode::InvalidArgument))
+                        .await?;
+                    read_error = Some(err);
+                    break;
+                }
+            };
+            let last = chunk.last;
+            self.connection.write_message(chunk).await?;
+            if last {
+                break;
+            }
+        }
+
+        let response = self.connection.read_message().await?;
+        if let Some(err) = read_error {
+            return Err(err.into());
+        }
+        match response {
+            Some(Message::Success(success)) => success
+                .version()
+                .ok_or_else(|| "stream is responded without version".into()),
+            Some(Message::Fail(fail)) => Err(fail.into()),
+            msg => Err(format!("unexpected message {:?}", msg).into()),
+        }
+    }
+
+    async fn get_stream(
+        &mut self,
+        key: Key,
+        out: &mut (dyn AsyncWrite + Unpin + Send),
+    ) -> Result<Option<u64>> {
+        self.require(MessageType::GetStream)?;
+
+        self.connection.write_message(GetStream::new(key)).await?;
+        match self.connection.read_message().await? {
+            Some(Message::Success(success)) if success.version().is_some() => (),
+            Some(Message::Success(_)) => return Ok(None),
+            Some(Message::Fail(fail)) => return Err(fail.into()),
+            msg => return Err(format!("unexpected message {:?}", msg).into()),
         }
+
+        let mut written = 0;
+        let mut write_error = None;
+        loop {
+            let chunk = match self.connection.read_message().await? {
+                Some(Message::Chunk(chunk)) => chunk,
+                Some(Message::Fail(fail)) => return Err(fail.into()),
+                msg => return Err(format!("unexpected message {:?}", msg).into()),
+            };
+            // Chunks are read to the last one even if the write failed,
+            // so that the connection can be used for the next request.
+            if write_error.is_none() {
+                match out.write_all(&chunk.data).await {
+                    Ok(()) => written += chunk.data.len() as u64,
+                    Err(err) => write_error = Some(err),
+                }
+            }
+            if chunk.last {
+                break;
+            }
+        }
+
+        match write_error {
+            Some(err) => Err(err.into()),
+            None => {
+                out.flush().await?;
+                Ok(Some(written))
+            }
+        }
+    }
+
+    async fn mset(&mut self, entries: Vec<(Key, Value)>) -> Result<()> {
+        self.request(MSet::new(entries)).await
+    }
+
+    async fn mget(&mut self, keys: Vec<Key>) -> Result<Vec<Option<Value>>> {
+        self.request_values(MGet::new(keys))
+            .await?
+            .into_iter()
+            .map(inline_value)
+            .collect()
+    }
+
+    async fn mdelete(&mut self, keys: Vec<Key>) -> Result<Vec<Option<Previous>>> {
+        self.request_values(MDelete::new(keys)).await
+    }
+
+    async fn set_with_ttl(&mut self, key: Key, value: Value, ttl: Duration) -> Result<()> {
+        self.request(Set::new(key, value).with_ttl(ttl)).await
+    }
+
+    async fn expire(&mut self, key: Key, ttl: Duration) -> Result<bool> {
+        self.request_ttl(Expire::new(key, ttl))
+            .await
+            .map(|expiry| expiry.is_some())
+    }
+
+    async fn persist(&mut self, key: Key) -> Result<bool> {
+        self.request_ttl(Persist::new(key))
+            .await
+            .map(|expiry| expiry.is_some())
+    }
+
+    async fn ttl(&mut self, key: Key) -> Result<Option<Expiry>> {
+        self.request_ttl(Ttl::new(key)).await
+    }
+
+    async fn begin(&mut self) -> Result<()> {
+        self.request(Begin::new()).await
+    }
+
+    async fn commit(&mut self) -> Result<()> {
+        self.request(Commit::new()).await
+    }
+
+    async fn abort(&mut self) -> Result<()> {
+        self.request(Abort::new()).await
+    }
+
+    async fn pipeline(
+        &mut self,
+        requests: Vec<Request>,
+        mode: PipelineMode,
+    ) -> Result<Vec<Result<Option<Value>>>> {
+        // Servers not supporting pipelining handle the requests in order.
+        let mode = if self.protocol.supports(Hello::PIPELINING) {
+            mode
+        } else {
+            PipelineMode::Ordered
+        };
+        let n = requests.len();
+        for (i, request) in requests.into_iter().enumerate() {
+            // Server handles the requests with id concurrently.
+            let request_id = match mode {
+                PipelineMode::Ordered => None,
+                PipelineMode::Concurrent => Some(i as u64),
+            };
+            let message: MessageFrames = match request {
+                Request::Get(key) => Get::new(key).into(),
+                Request::Set(key, value) => Set::new(key, value).into(),
+                Request::Delete(key) => Delete::new(key).into(),
+            };
+            self.connection.feed_message(request_id, message).await?;
+        }
+        self.connection.flush().await?;
+
+        let mut responses = (0..n).map(|_| None).collect::<Vec<_>>();
+        for i in 0..n {
+            let (request_id, message) = match self.connection.read_message_with_id().await? {
+                Some(response) => response,
+                None => return Err("connection closed".into()),
+            };
+            let index = match mode {
+                PipelineMode::Ordered => i,
+                PipelineMode::Concurrent => request_id
+                    .map(|request_id| request_id as usize)
+                    .filter(|index| *index < n && responses[*index].is_none())
+                    .ok_or_else(|| format!("unexpected request id {:?}", request_id))?,
+            };
+            responses[index] = Some(match message {
+                Message::Success(success) => inline_value(success.previous()),
+                Message::Fail(fail) => Err(fail.into()),
+                msg => return Err(format!("unexpected message {:?}", msg).into()),
+            });
+        }
+
+        // Every request is responded once.
+        Ok(responses.into_iter().map(Option::unwrap).collect())
+    }
+
+    fn scan(&mut self, options: ScanOptions) -> ScanStream<'_> {
+        Box::pin(async_stream::try_stream! {
+            let mut cursor = options.cursor.clone();
+            loop {
+                let scan = Scan {
+                    prefix: options.prefix.clone(),
+                    start: options.start.clone(),
+                    end: options.end.clone(),
+                    cursor: cursor.take(),
+                    limit: options.limit(),
+                };
+                self.connection.write_message(scan).await?;
+
+                let page = match self.connection.read_message().await? {
+                    Some(Message::ScanPage(page)) => page,
+                    Some(Message::Fail(fail)) => Err(KvsdError::from(fail))?,
+                    msg => Err(KvsdError::from(format!("unexpected message {:?}", msg)))?,
+                };
+
+                for entry in page.entries {
+                    yield entry;
+                }
+
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => break,
+                }
+            }
+        })
+    }
+
+    async fn create_namespace(&mut self, namespace: String) -> Result<()> {
+        self.request(CreateNamespace::new(namespace)).await
+    }
+
+    async fn drop_namespace(&mut self, namespace: String) -> Result<()> {
+        self.request(DropNamespace::new(namespace)).await
+    }
+
+    async fn create_table(&mut self, namespace: String, table: String) -> Result<()> {
+        self.request(CreateTable::new(namespace, table)).await
+    }
+
+    async fn drop_table(&mut self, namespace: String, table: String) -> Result<()> {
+        self.request(DropTable::new(namespace, table)).await
+    }
+
+    async fn compact_table(&mut self, namespace: String, table: String) -> Result<()> {
+        self.request(CompactTable::new(namespace, table)).await
+    }
+
+    async fn list_tables(&mut self) -> Result<Vec<(String, String)>> {
+        self.connection.write_message(ListTables::new()).await?;
+        match self.connection.read_message().await? {
+            Some(Message::Tables(tables)) => Ok(tables.tables),
+            Some(Message::Fail(fail)) => Err(fail.into()),
+            msg => Err(format!("unexpected message {:?}", msg).into()),
+        }
+    }
+}
+
+// Return the value responded inline. value too large to be responded fails with MaxValueBytes.
+fn inline_value(previous: Option<Previous>) -> Result<Option<Value>> {
+    match previous {
+        Some(Previous::Value(value)) => Ok(Some(value)),
+        Some(Previous::TooLarge(_)) => Err(KvsdError::MaxValueBytes {
+            max_bytes: MAX_VALUE_BYTES,
+        }),
+        None => Ok(None),
     }
 }
 
+// Verifier accepting any server certificate.
 #[derive(Debug)]
 struct DangerousServerCertVerifier {}
 
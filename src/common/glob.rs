@@ -0,0 +1,115 @@
+//! Glob style pattern matching.
+
+// Leading part of the glob pattern without special characters.
+// keys matching the pattern start with it.
+pub(crate) fn literal_prefix(pattern: &[u8]) -> &[u8] {
+    let end = pattern
+        .iter()
+        .position(|b| matches!(b, b'*' | b'?' | b'[' | b'\\'))
+        .unwrap_or(pattern.len());
+    &pattern[..end]
+}
+
+// Match the string with the glob style pattern as redis does.
+// used by SCAN MATCH of the resp listener and the role scopes.
+// * matches any sequence, ? matches any single byte, [abc], [^a] and [a-z] match byte classes,
+// and \ escapes the following byte.
+// only the last * is backtracked, so that the matching does not take exponential time.
+pub(crate) fn glob_match(pattern: &[u8], s: &[u8]) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // pattern position after the last * and the string position matched from there.
+    let mut backtrack = None;
+
+    while i < s.len() {
+        let next = match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                backtrack = Some((p, i));
+                continue;
+            }
+            Some(b'?') => Some(p + 1),
+            Some(b'[') => match match_class(&pattern[p + 1..], s[i]) {
+                Some((true, rest)) => Some(pattern.len() - rest.len()),
+                _ => None,
+            },
+            Some(b'\\') if p + 1 < pattern.len() => (pattern[p + 1] == s[i]).then_some(p + 2),
+            Some(b) => (*b == s[i]).then_some(p + 1),
+            None => None,
+        };
+
+        match (next, backtrack) {
+            (Some(next), _) => {
+                p = next;
+                i += 1;
+            }
+            // Let the last * consume one more byte.
+            (None, Some((star, matched))) => {
+                p = star;
+                i = matched + 1;
+                backtrack = Some((star, i));
+            }
+            (None, None) => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|b| *b == b'*')
+}
+
+// Match the byte with the class following '['.
+// returns whether it matched and the pattern after the class, None if the class is not closed.
+fn match_class(mut pattern: &[u8], c: u8) -> Option<(bool, &[u8])> {
+    let negate = matches!(pattern.first(), Some(b'^' | b'!'));
+    if negate {
+        pattern = &pattern[1..];
+    }
+
+    let mut matched = false;
+    loop {
+        match pattern {
+            [] => return None,
+            [b']', rest @ ..] => return Some((matched != negate, rest)),
+            [b'\\', escaped, rest @ ..] => {
+                matched |= *escaped == c;
+                pattern = rest;
+            }
+            [start, b'-', end, rest @ ..] if *end != b']' => {
+                let (low, high) = if start <= end {
+                    (start, end)
+                } else {
+                    (end, start)
+                };
+                matched |= (*low..=*high).contains(&c);
+                pattern = rest;
+            }
+            [b, rest @ ..] => {
+                matched |= *b == c;
+                pattern = rest;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob() {
+        assert!(glob_match(b"*", b""));
+        assert!(glob_match(b"user:*", b"user:1"));
+        assert!(!glob_match(b"user:*", b"order:1"));
+        assert!(glob_match(b"h?llo", b"hello"));
+        assert!(!glob_match(b"h?llo", b"hllo"));
+        assert!(glob_match(b"h[ae]llo", b"hallo"));
+        assert!(!glob_match(b"h[^e]llo", b"hello"));
+        assert!(glob_match(b"key:[0-9]", b"key:7"));
+        assert!(!glob_match(b"key:[0-9]", b"key:a"));
+        assert!(glob_match(b"a\\*b", b"a*b"));
+        assert!(!glob_match(b"a\\*b", b"axb"));
+        assert!(!glob_match(b"h[ello", b"hello"));
+
+        assert_eq!(literal_prefix(b"user:*:name"), b"user:");
+        assert_eq!(literal_prefix(b"user"), b"user");
+        assert_eq!(literal_prefix(b"[ab]*"), b"");
+    }
+}
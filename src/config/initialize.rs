@@ -1,10 +1,11 @@
 use std::future::Future;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
 use std::path::{Path, PathBuf};
 
 use tokio::fs;
-use tokio::net::TcpListener;
+use tokio::net::{TcpListener, UnixListener};
 
-use crate::common::{info, Result};
+use crate::common::{info, warn, Result};
 use crate::config::{filepath, Config};
 use crate::core;
 use crate::server::tcp::Server;
@@ -15,10 +16,14 @@ use crate::KvsdError;
 ///   * create directory structure
 ///   * build core kvsd from config
 ///   * listen tcp if needed, then run tcp server
+///   * listen resp, http and unix socket if configured, then serve them along with tcp server
 #[derive(Debug)]
 pub struct Initializer {
     pub(crate) config: Config,
     listener: Option<TcpListener>,
+    resp_listener: Option<TcpListener>,
+    http_listener: Option<TcpListener>,
+    unix_listener: Option<UnixListener>,
 }
 
 impl Initializer {
@@ -27,6 +32,9 @@ impl Initializer {
         Self {
             config,
             listener: None,
+            resp_listener: None,
+            http_listener: None,
+            unix_listener: None,
         }
     }
 
@@ -41,6 +49,25 @@ impl Initializer {
         self.listener = Some(listener);
     }
 
+    /// Set resp tcp listener.
+    /// the initializer binds the resp listen port if it is configured and the listener is not set.
+    pub fn set_resp_listener(&mut self, listener: TcpListener) {
+        self.resp_listener = Some(listener);
+    }
+
+    /// Set http tcp listener.
+    /// the initializer binds the http listen port if it is configured and the listener is not set.
+    #[cfg(feature = "http")]
+    pub fn set_http_listener(&mut self, listener: TcpListener) {
+        self.http_listener = Some(listener);
+    }
+
+    /// Set unix domain socket listener.
+    /// the initializer binds the unix socket path if it is configured and the listener is not set.
+    pub fn set_unix_listener(&mut self, listener: UnixListener) {
+        self.unix_listener = Some(listener);
+    }
+
     pub(crate) async fn load_config_file(path: impl AsRef<Path>) -> Result<Self> {
         let f = fs::File::open(path).await?;
         let config = serde_yaml::from_reader::<_, Config>(f.into_std().await)?;
@@ -66,13 +93,68 @@ impl Initializer {
             }
         };
 
+        let resp_listener = match (self.resp_listener, self.config.server.resp_listen_addr()) {
+            (Some(listener), _) => Some(listener),
+            (None, Some(addr)) => {
+                info!(%addr, "Listening resp");
+                Some(TcpListener::bind(addr).await?)
+            }
+            (None, None) => None,
+        };
+        let http_listener = match (self.http_listener, self.config.server.http_listen_addr()) {
+            (Some(listener), _) => Some(listener),
+            (None, Some(addr)) if cfg!(feature = "http") => {
+                info!(%addr, "Listening http");
+                Some(TcpListener::bind(addr).await?)
+            }
+            (None, Some(addr)) => {
+                warn!(%addr, "Http listener requires the http feature");
+                None
+            }
+            (None, None) => None,
+        };
+        let unix_listener = match (self.unix_listener, self.config.server.unix_socket_path()) {
+            (Some(listener), _) => Some(listener),
+            (None, Some(path)) => {
+                info!(path = %path.display(), "Listening unix socket");
+                Some(
+                    Initializer::bind_unix_socket(path, self.config.server.unix_socket_mode())
+                        .await?,
+                )
+            }
+            (None, None) => None,
+        };
+
         let server = Server::new(self.config.server);
 
-        server.run(request_sender, listener, shutdown).await?;
+        server
+            .run(
+                request_sender,
+                listener,
+                resp_listener,
+                http_listener,
+                unix_listener,
+                shutdown,
+            )
+            .await?;
 
         Ok(())
     }
 
+    // Bind the unix socket and restrict the access by the permission of the socket file.
+    async fn bind_unix_socket(path: &Path, mode: u32) -> Result<UnixListener> {
+        // Remove the socket left by the previous process.
+        match fs::symlink_metadata(path).await {
+            Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path).await?,
+            _ => (),
+        }
+
+        let listener = UnixListener::bind(path)?;
+        fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).await?;
+
+        Ok(listener)
+    }
+
     /// Initialize kvsd directory structure.
     pub async fn init_dir(&mut self) -> Result<(), KvsdError> {
         let root_dir = self.config.kvsd.root_dir.clone().unwrap();
@@ -88,7 +170,7 @@ impl Initializer {
 
         let initial_namespaces = vec![
             namespaces.join(filepath::NS_SYSTEM),
-            namespaces.join("default/default"),
+            namespaces.join(filepath::NS_DEFAULT).join("default"),
         ];
 
         for ns in &initial_namespaces {
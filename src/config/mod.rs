@@ -10,6 +10,7 @@ pub(crate) mod filepath {
     pub const NAMESPACES: &str = "namespaces";
     pub const NS_SYSTEM: &str = "system";
     pub const NS_DEFAULT: &str = "default";
+    pub const TRANSACTION_LOG: &str = "transaction.log";
 }
 
 /// Environment variable config
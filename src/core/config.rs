@@ -1,21 +1,351 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use serde::Deserialize;
 
+use crate::common::glob::glob_match;
+use crate::common::{ErrorKind, Result};
+use crate::core::password;
+
 /// kvsd configuration.
-#[derive(Default, Debug, Deserialize)]
+#[derive(Default, Debug, Deserialize, Clone)]
 pub struct Config {
     /// authenticated principal users.
     pub users: Vec<UserEntry>,
+    /// accept the plaintext `password` of users. intended for development only.
+    #[serde(default)]
+    pub allow_plaintext_passwords: bool,
+    /// permissions granted to the users having the role. key is the role name.
+    /// authorization is disabled and authenticated users can do anything if no roles are defined.
+    #[serde(default)]
+    pub roles: HashMap<String, Vec<Grant>>,
     /// root directory to store kvsd data and state.
     pub root_dir: Option<PathBuf>,
+    /// configuration applied to all tables.
+    #[serde(default)]
+    pub table: TableConfig,
+    /// configuration applied to specific table. key is "{namespace}/{table}".
+    /// unspecified values fall back to `table`.
+    #[serde(default)]
+    pub tables: HashMap<String, TableConfig>,
+}
+
+impl Config {
+    /// Return the configuration of given table.
+    pub(crate) fn table_config(&self, namespace: &str, table: &str) -> TableConfig {
+        match self.tables.get(&format!("{}/{}", namespace, table)) {
+            Some(config) => config.clone().merge(&self.table),
+            None => self.table.clone(),
+        }
+    }
+
+    // Check that every user can authenticate by password as configured.
+    pub(crate) fn validate_users(&self) -> Result<()> {
+        for user in &self.users {
+            match (&user.password_hash, &user.password) {
+                (Some(hash), _) if !password::is_valid_hash(hash) => {
+                    return Err(ErrorKind::InvalidArgument(format!(
+                        "password_hash of user {} is neither argon2 nor bcrypt hash",
+                        user.username
+                    ))
+                    .into())
+                }
+                (None, Some(_)) if !self.allow_plaintext_passwords => {
+                    return Err(ErrorKind::InvalidArgument(format!(
+                        "user {} has plaintext password. use password_hash or enable allow_plaintext_passwords",
+                        user.username
+                    ))
+                    .into())
+                }
+                _ => (),
+            }
+
+            if let Some(role) = user.roles.iter().find(|r| !self.roles.contains_key(*r)) {
+                return Err(ErrorKind::InvalidArgument(format!(
+                    "role {} of user {} is not defined",
+                    role, user.username
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Authenticated users.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Deserialize, Clone, Default)]
 pub struct UserEntry {
     /// username.
     pub username: String,
-    /// password.
-    pub password: String,
+    /// argon2id or bcrypt hash of the password.
+    /// `kvsadmin user hash-password` generates the argon2id hash.
+    #[serde(default)]
+    pub password_hash: Option<String>,
+    /// plaintext password. accepted only if `allow_plaintext_passwords` is enabled.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// bearer tokens with which the user can authenticate instead of the password.
+    #[serde(default)]
+    pub tokens: Vec<String>,
+    /// subject common name or subject alternative name (dns, email or uri) of the tls client
+    /// certificate with which the user can authenticate instead of the password.
+    #[serde(default)]
+    pub certificate_names: Vec<String>,
+    /// names of the roles granted to the user.
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl fmt::Debug for UserEntry {
+    // Mask the credentials not to be logged.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UserEntry")
+            .field("username", &self.username)
+            .field("password_hash", &self.password_hash.as_ref().map(|_| "***"))
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .field("tokens", &self.tokens.len())
+            .field("certificate_names", &self.certificate_names)
+            .field("roles", &self.roles)
+            .finish()
+    }
+}
+
+/// Permissions granted on the tables matching the scope.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Grant {
+    /// "{namespace}/{table}" glob pattern. e.g. "default/*", "*".
+    pub scope: String,
+    /// granted permissions.
+    pub permissions: Vec<Permission>,
+}
+
+impl Grant {
+    /// Whether the grant permits the operation on the table.
+    pub(crate) fn permits(&self, permission: Permission, namespace: &str, table: &str) -> bool {
+        self.permissions.iter().any(|p| p.implies(permission))
+            && glob_match(
+                self.scope.as_bytes(),
+                format!("{}/{}", namespace, table).as_bytes(),
+            )
+    }
+}
+
+/// Operations permitted to the role.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// get and scan the keys.
+    Read,
+    /// set, delete and expire the keys.
+    Write,
+    /// create, drop and list namespaces and tables. implies read and write.
+    Admin,
+}
+
+impl Permission {
+    fn implies(self, other: Permission) -> bool {
+        self == other || self == Permission::Admin
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Permission::Read => write!(f, "read"),
+            Permission::Write => write!(f, "write"),
+            Permission::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+/// Table configuration.
+#[derive(Default, Debug, Deserialize, Clone)]
+pub struct TableConfig {
+    /// ratio of garbage bytes (overwritten values and tombstones) to the table file size
+    /// at which compaction is started automatically.
+    pub compaction_garbage_ratio: Option<f64>,
+    /// table file size in bytes required before automatic compaction is considered.
+    pub compaction_min_bytes: Option<u64>,
+    /// how to handle invalid entries at the end of table file on startup.
+    pub recovery: Option<RecoveryMode>,
+    /// when written entries are synced to the disk.
+    pub durability: Option<Durability>,
+}
+
+/// Recovery mode applied when table file ends with a torn or corrupted entry.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryMode {
+    /// Truncate the invalid entries and continue.
+    Truncate,
+    /// Refuse to start.
+    Strict,
+}
+
+/// Durability policy of table writes.
+///
+/// Represented as "always", "never" or "every {N}ms" in configuration file.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum Durability {
+    /// Sync before acknowledging writes.
+    /// writes queued at the same time are synced together.
+    Always,
+    /// Sync written entries periodically.
+    Every(Duration),
+    /// Leave syncing to the operating system.
+    Never,
+}
+
+impl TryFrom<String> for Durability {
+    type Error = String;
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("invalid durability {:?}. (always|never|every {{N}}ms)", s);
+
+        match s.trim() {
+            "always" => Ok(Durability::Always),
+            "never" => Ok(Durability::Never),
+            every => {
+                let ms = every
+                    .strip_prefix("every")
+                    .and_then(|ms| ms.trim().strip_suffix("ms"))
+                    .and_then(|ms| ms.trim().parse::<u64>().ok())
+                    .filter(|ms| *ms > 0)
+                    .ok_or_else(invalid)?;
+                Ok(Durability::Every(Duration::from_millis(ms)))
+            }
+        }
+    }
+}
+
+impl TableConfig {
+    const DEFAULT_COMPACTION_GARBAGE_RATIO: f64 = 0.5;
+    const DEFAULT_COMPACTION_MIN_BYTES: u64 = 1024 * 1024 * 16;
+
+    pub(crate) fn compaction_garbage_ratio(&self) -> f64 {
+        self.compaction_garbage_ratio
+            .unwrap_or(TableConfig::DEFAULT_COMPACTION_GARBAGE_RATIO)
+    }
+
+    pub(crate) fn compaction_min_bytes(&self) -> u64 {
+        self.compaction_min_bytes
+            .unwrap_or(TableConfig::DEFAULT_COMPACTION_MIN_BYTES)
+    }
+
+    pub(crate) fn recovery(&self) -> RecoveryMode {
+        self.recovery.unwrap_or(RecoveryMode::Truncate)
+    }
+
+    pub(crate) fn durability(&self) -> Durability {
+        self.durability.unwrap_or(Durability::Never)
+    }
+
+    // Fill unspecified values with other.
+    fn merge(self, other: &TableConfig) -> TableConfig {
+        TableConfig {
+            compaction_garbage_ratio: self
+                .compaction_garbage_ratio
+                .or(other.compaction_garbage_ratio),
+            compaction_min_bytes: self.compaction_min_bytes.or(other.compaction_min_bytes),
+            recovery: self.recovery.or(other.recovery),
+            durability: self.durability.or(other.durability),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durability_from_string() {
+        let parse = |s: &str| Durability::try_from(s.to_owned());
+
+        assert_eq!(parse("always"), Ok(Durability::Always));
+        assert_eq!(parse("never"), Ok(Durability::Never));
+        assert_eq!(
+            parse("every 100ms"),
+            Ok(Durability::Every(Duration::from_millis(100)))
+        );
+        assert!(parse("every 0ms").is_err());
+        assert!(parse("sometimes").is_err());
+    }
+
+    #[test]
+    fn table_config_override() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+users: []
+table:
+  durability: never
+  recovery: strict
+tables:
+  default/orders:
+    durability: always
+"#,
+        )
+        .unwrap();
+
+        let orders = config.table_config("default", "orders");
+        assert_eq!(orders.durability(), Durability::Always);
+        assert_eq!(orders.recovery(), RecoveryMode::Strict);
+
+        let default = config.table_config("default", "default");
+        assert_eq!(default.durability(), Durability::Never);
+    }
+
+    #[test]
+    fn validate_users() {
+        let parse = |s: &str| serde_yaml::from_str::<Config>(s).unwrap();
+
+        let plaintext = parse(
+            r#"
+users:
+  - username: "dev"
+    password: "secret"
+"#,
+        );
+        assert!(plaintext.validate_users().is_err());
+
+        let allowed = Config {
+            allow_plaintext_passwords: true,
+            ..plaintext
+        };
+        assert!(allowed.validate_users().is_ok());
+
+        let invalid_hash = parse(
+            r#"
+users:
+  - username: "dev"
+    password_hash: "secret"
+"#,
+        );
+        assert!(invalid_hash.validate_users().is_err());
+
+        let hashed = parse(
+            r#"
+users:
+  - username: "dev"
+    password_hash: "$2b$04$0Cb4NNmy4WnAmXTFe2Kk7.mpSVGUtZ5WtxTzXnFAAV4aaNqmXxWCu"
+"#,
+        );
+        assert!(hashed.validate_users().is_ok());
+
+        let undefined_role = parse(
+            r#"
+users:
+  - username: "dev"
+    password_hash: "$2b$04$0Cb4NNmy4WnAmXTFe2Kk7.mpSVGUtZ5WtxTzXnFAAV4aaNqmXxWCu"
+    roles: ["reader"]
+roles:
+  writer:
+    - scope: "*"
+      permissions: [write]
+"#,
+        );
+        assert!(undefined_role.validate_users().is_err());
+    }
 }
@@ -2,6 +2,11 @@ use std::borrow::Cow;
 
 pub(crate) enum Credential<'a> {
     Password(Password<'a>),
+    // Bearer token issued to the user. only http gateway accepts it.
+    #[cfg_attr(not(feature = "http"), allow(dead_code))]
+    Token(Cow<'a, str>),
+    // Names of the client certificate verified by the tls handshake.
+    Certificate(Cow<'a, [String]>),
 }
 
 pub(crate) trait Provider {
@@ -12,3 +17,12 @@ pub(crate) struct Password<'a> {
     pub(crate) username: Cow<'a, str>,
     pub(crate) password: Cow<'a, str>,
 }
+
+impl Password<'_> {
+    pub(crate) fn into_owned(self) -> Password<'static> {
+        Password {
+            username: Cow::Owned(self.username.into_owned()),
+            password: Cow::Owned(self.password.into_owned()),
+        }
+    }
+}
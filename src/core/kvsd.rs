@@ -1,9 +1,8 @@
 use tokio::sync::mpsc::{self, Receiver, Sender};
 
-use crate::common::{debug, error, info, Result};
+use crate::common::{error, info, Result};
 use crate::config::filepath;
 use crate::core::middleware::{Dispatcher, MiddlewareChain};
-use crate::core::table::Table;
 use crate::core::{Config, UnitOfWork};
 
 #[derive(Default)]
@@ -20,6 +19,10 @@ impl Builder {
     }
 
     pub(crate) async fn build(mut self) -> Result<Kvsd> {
+        if let Some(config) = self.config.as_ref() {
+            config.validate_users()?;
+        }
+
         let (send, recv) = mpsc::channel(self.request_channel_buffer);
 
         let dispatcher = self.build_dispatcher().await?;
@@ -34,21 +37,16 @@ impl Builder {
     }
 
     async fn build_dispatcher(&mut self) -> Result<Dispatcher> {
-        // TODO configure channel size
-        let (tx, rx) = mpsc::channel(1024);
-
-        let root_dir = self.config.as_ref().unwrap().root_dir.as_ref().unwrap();
-        let default_table = root_dir
-            .join(filepath::NAMESPACES)
-            .join(filepath::NS_DEFAULT)
-            .join("default/default.kvsd");
-        debug!("Open default table file {}", default_table.display());
-        let default_table = Table::from_path(default_table).await?;
-
-        tokio::spawn(default_table.run(rx));
-
-        let mut dispatcher = Dispatcher::new();
-        dispatcher.add_table("default", "default", tx);
+        let config = self.config.as_ref().unwrap();
+        let namespaces_dir = config.root_dir.as_ref().unwrap().join(filepath::NAMESPACES);
+
+        let transaction_log = config
+            .root_dir
+            .as_ref()
+            .unwrap()
+            .join(filepath::TRANSACTION_LOG);
+        let mut dispatcher = Dispatcher::new(namespaces_dir, transaction_log, config.clone());
+        dispatcher.discover().await?;
 
         Ok(dispatcher)
     }
@@ -1,26 +1,102 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
+use tokio::sync::oneshot;
 
 use crate::common::{info, ErrorKind, Result};
 use crate::core::middleware::Middleware;
-use crate::core::{principal, Credential, Password, Principal, UnitOfWork, UserEntry, Work};
+use crate::core::{Credential, Password, Principal, UnitOfWork, UserEntry, Work};
 
 pub(crate) struct Authenticator<MW> {
-    users: Vec<UserEntry>,
+    users: Arc<Vec<UserEntry>>,
+    // Verified for the unknown username not to reveal the user existence by the response time.
+    dummy_hash: Option<Arc<String>>,
+    allow_plaintext_passwords: bool,
     next: MW,
 }
 
 impl<MW> Authenticator<MW> {
-    pub(crate) fn new(users: Vec<UserEntry>, next: MW) -> Self {
-        Self { users, next }
+    pub(crate) fn new(users: Vec<UserEntry>, allow_plaintext_passwords: bool, next: MW) -> Self {
+        let dummy_hash = users
+            .iter()
+            .find_map(|user_entry| user_entry.password_hash.as_deref())
+            .and_then(|hash| crate::core::password::dummy_hash(hash).ok())
+            .map(Arc::new);
+        Self {
+            users: Arc::new(users),
+            dummy_hash,
+            allow_plaintext_passwords,
+            next,
+        }
+    }
+
+    // Verifying the password hash takes time intentionally,
+    // so it runs on the blocking thread not to stall the other unit of works.
+    fn authenticate_by_password(
+        &self,
+        password: Password<'static>,
+        response_sender: oneshot::Sender<Result<Option<Principal>>>,
+    ) {
+        let users = self.users.clone();
+        let dummy_hash = self.dummy_hash.clone();
+        let allow_plaintext_passwords = self.allow_plaintext_passwords;
+
+        tokio::task::spawn_blocking(move || {
+            let principal = match users
+                .iter()
+                .find(|user_entry| user_entry.username == password.username)
+            {
+                Some(user_entry) => Authenticator::<MW>::verify_password(
+                    user_entry,
+                    &password.password,
+                    allow_plaintext_passwords,
+                )
+                .then(|| Principal::user(user_entry.username.clone())),
+                None => {
+                    // Spend the same time as the known user.
+                    if let Some(dummy_hash) = dummy_hash {
+                        crate::core::password::verify(&password.password, &dummy_hash);
+                    }
+                    None
+                }
+            };
+
+            // The client may have gone.
+            let _ = response_sender.send(Ok(principal));
+        });
+    }
+
+    fn verify_password(user_entry: &UserEntry, password: &str, allow_plaintext: bool) -> bool {
+        match (&user_entry.password_hash, &user_entry.password) {
+            (Some(hash), _) => crate::core::password::verify(password, hash),
+            (None, Some(plaintext)) if allow_plaintext => {
+                crate::core::password::constant_time_eq(password, plaintext)
+            }
+            _ => false,
+        }
+    }
+
+    fn authenticate_by_token(&self, token: &str) -> Result<Option<Principal>> {
+        for user_entry in self.users.iter() {
+            if user_entry
+                .tokens
+                .iter()
+                .any(|t| crate::core::password::constant_time_eq(t, token))
+            {
+                return Ok(Some(Principal::user(user_entry.username.clone())));
+            }
+        }
+        Ok(None)
     }
 
-    fn authenticate_by_password(&self, password: Password) -> Result<Option<Principal>> {
-        for user_entry in &self.users {
-            if user_entry.username == password.username && user_entry.password == password.password
+    fn authenticate_by_certificate(&self, names: &[String]) -> Result<Option<Principal>> {
+        for user_entry in self.users.iter() {
+            if user_entry
+                .certificate_names
+                .iter()
+                .any(|name| names.contains(name))
             {
-                return Ok(Some(Principal::User(principal::User {
-                    name: user_entry.username.clone(),
-                })));
+                return Ok(Some(Principal::user(user_entry.username.clone())));
             }
         }
         Ok(None)
@@ -48,9 +124,23 @@ where
                 match credential {
                     Credential::Password(password) => {
                         info!(user=?password.username, "Try authenticate ");
+                        self.authenticate_by_password(
+                            password.into_owned(),
+                            auth.response_sender.expect("response already sent"),
+                        );
+                    }
+                    Credential::Token(token) => {
+                        info!("Try authenticate by token");
+                        auth.response_sender
+                            .expect("response already sent")
+                            .send(self.authenticate_by_token(&token))
+                            .map_err(|_| ErrorKind::Internal("send to resp channel".into()))?;
+                    }
+                    Credential::Certificate(names) => {
+                        info!(?names, "Try authenticate by client certificate");
                         auth.response_sender
                             .expect("response already sent")
-                            .send(self.authenticate_by_password(password))
+                            .send(self.authenticate_by_certificate(&names))
                             .map_err(|_| ErrorKind::Internal("send to resp channel".into()))?;
                     }
                 }
@@ -60,7 +150,24 @@ where
             UnitOfWork::Ping(Work { ref principal, .. })
             | UnitOfWork::Set(Work { ref principal, .. })
             | UnitOfWork::Get(Work { ref principal, .. })
-            | UnitOfWork::Delete(Work { ref principal, .. }) => {
+            | UnitOfWork::Delete(Work { ref principal, .. })
+            | UnitOfWork::Scan(Work { ref principal, .. })
+            | UnitOfWork::MSet(Work { ref principal, .. })
+            | UnitOfWork::MGet(Work { ref principal, .. })
+            | UnitOfWork::MDelete(Work { ref principal, .. })
+            | UnitOfWork::Expire(Work { ref principal, .. })
+            | UnitOfWork::Ttl(Work { ref principal, .. })
+            | UnitOfWork::WriteChunk(Work { ref principal, .. })
+            | UnitOfWork::ReadChunk(Work { ref principal, .. })
+            | UnitOfWork::Commit(Work { ref principal, .. })
+            | UnitOfWork::Prepare(Work { ref principal, .. })
+            | UnitOfWork::CreateNamespace(Work { ref principal, .. })
+            | UnitOfWork::DropNamespace(Work { ref principal, .. })
+            | UnitOfWork::CreateTable(Work { ref principal, .. })
+            | UnitOfWork::DropTable(Work { ref principal, .. })
+            | UnitOfWork::CompactTable(Work { ref principal, .. })
+            | UnitOfWork::ListTables(Work { ref principal, .. })
+            | UnitOfWork::LookupTable(Work { ref principal, .. }) => {
                 let r = self.check_principal(principal.as_ref());
 
                 match r {
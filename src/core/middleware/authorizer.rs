@@ -1,16 +1,151 @@
+use std::collections::HashMap;
+
 use async_trait::async_trait;
 
-use crate::common::Result;
+use crate::common::{ErrorKind, Result};
 use crate::core::middleware::Middleware;
-use crate::core::UnitOfWork;
+use crate::core::{Grant, Permission, Principal, UnitOfWork, UserEntry, Work};
+
+// Table name used to check the permission on the whole namespace.
+const ALL_TABLES: &str = "*";
 
 pub(crate) struct Authorizer<MW> {
+    // Grants of the roles each user has. None if no roles are defined.
+    grants: Option<HashMap<String, Vec<Grant>>>,
     next: MW,
 }
 
 impl<MW> Authorizer<MW> {
-    pub(crate) fn new(next: MW) -> Self {
-        Self { next }
+    pub(crate) fn new(roles: &HashMap<String, Vec<Grant>>, users: &[UserEntry], next: MW) -> Self {
+        let grants = (!roles.is_empty()).then(|| {
+            users
+                .iter()
+                .map(|user| {
+                    let grants = user
+                        .roles
+                        .iter()
+                        .filter_map(|role| roles.get(role))
+                        .flatten()
+                        .cloned()
+                        .collect();
+                    (user.username.clone(), grants)
+                })
+                .collect()
+        });
+
+        Self { grants, next }
+    }
+
+    fn authorize(
+        &self,
+        principal: &Principal,
+        permission: Permission,
+        namespace: &str,
+        table: &str,
+    ) -> Result<()> {
+        let grants = match &self.grants {
+            Some(grants) => grants,
+            None => return Ok(()),
+        };
+
+        let permitted = match principal {
+            Principal::User(user) => grants
+                .get(&user.name)
+                .map(|grants| {
+                    grants
+                        .iter()
+                        .any(|grant| grant.permits(permission, namespace, table))
+                })
+                .unwrap_or(false),
+            Principal::AnonymousUser => false,
+        };
+
+        if permitted {
+            Ok(())
+        } else {
+            Err(ErrorKind::Unauthorized(format!(
+                "{} permission on {}/{} is required",
+                permission, namespace, table
+            ))
+            .into())
+        }
+    }
+
+    // Check the permissions required by the unit of work.
+    fn authorize_uow(&self, uow: &UnitOfWork) -> Result<()> {
+        use Permission::{Admin, Read, Write};
+
+        match uow {
+            // Authenticate has no principal yet, Prepare is sent by the dispatcher,
+            // and Ping requires only the authentication.
+            UnitOfWork::Authenticate(_) | UnitOfWork::Prepare(_) | UnitOfWork::Ping(_) => Ok(()),
+            UnitOfWork::Get(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Read, &request.namespace, &request.table),
+            UnitOfWork::Scan(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Read, &request.namespace, &request.table),
+            UnitOfWork::MGet(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Read, &request.namespace, &request.table),
+            UnitOfWork::Ttl(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Read, &request.namespace, &request.table),
+            UnitOfWork::ReadChunk(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Read, &request.namespace, &request.table),
+            UnitOfWork::LookupTable(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Read, &request.namespace, &request.table),
+            UnitOfWork::Set(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Write, &request.namespace, &request.table),
+            UnitOfWork::Delete(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Write, &request.namespace, &request.table),
+            UnitOfWork::MSet(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Write, &request.namespace, &request.table),
+            UnitOfWork::MDelete(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Write, &request.namespace, &request.table),
+            UnitOfWork::Expire(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Write, &request.namespace, &request.table),
+            UnitOfWork::WriteChunk(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Write, &request.namespace, &request.table),
+            UnitOfWork::Commit(Work {
+                principal, request, ..
+            }) => {
+                for key in request.reads.keys() {
+                    self.authorize(principal, Read, &key.namespace, &key.table)?;
+                }
+                for key in request.writes.keys() {
+                    self.authorize(principal, Write, &key.namespace, &key.table)?;
+                }
+                Ok(())
+            }
+            UnitOfWork::CreateNamespace(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Admin, &request.namespace, ALL_TABLES),
+            UnitOfWork::DropNamespace(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Admin, &request.namespace, ALL_TABLES),
+            UnitOfWork::CreateTable(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Admin, &request.namespace, &request.table),
+            UnitOfWork::DropTable(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Admin, &request.namespace, &request.table),
+            UnitOfWork::CompactTable(Work {
+                principal, request, ..
+            }) => self.authorize(principal, Admin, &request.namespace, &request.table),
+            // Listing shows the tables of all namespaces.
+            UnitOfWork::ListTables(Work { principal, .. }) => {
+                self.authorize(principal, Admin, ALL_TABLES, ALL_TABLES)
+            }
+        }
     }
 }
 
@@ -20,6 +155,85 @@ where
     MW: Middleware + Send + 'static,
 {
     async fn apply(&mut self, uow: UnitOfWork) -> Result<()> {
-        self.next.apply(uow).await
+        match self.authorize_uow(&uow) {
+            Ok(()) => self.next.apply(uow).await,
+            Err(err) => uow.send_error(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorizer() -> Authorizer<()> {
+        let roles: HashMap<String, Vec<Grant>> = serde_yaml::from_str(
+            r#"
+reader:
+  - scope: "default/*"
+    permissions: [read]
+writer:
+  - scope: "app/orders"
+    permissions: [read, write]
+admin:
+  - scope: "*"
+    permissions: [admin]
+"#,
+        )
+        .unwrap();
+        let user = |name: &str, roles: &[&str]| UserEntry {
+            username: name.into(),
+            roles: roles.iter().map(|r| r.to_string()).collect(),
+            ..Default::default()
+        };
+        let users = vec![
+            user("alice", &["reader", "writer"]),
+            user("root", &["admin"]),
+            user("nobody", &[]),
+        ];
+
+        Authorizer::new(&roles, &users, ())
+    }
+
+    #[test]
+    fn authorize() {
+        use Permission::{Admin, Read, Write};
+        let authorizer = authorizer();
+        let alice = Principal::user("alice");
+
+        assert!(authorizer
+            .authorize(&alice, Read, "default", "default")
+            .is_ok());
+        assert!(authorizer
+            .authorize(&alice, Write, "default", "default")
+            .is_err());
+        assert!(authorizer.authorize(&alice, Write, "app", "orders").is_ok());
+        assert!(authorizer.authorize(&alice, Read, "app", "users").is_err());
+        assert!(authorizer
+            .authorize(&alice, Admin, "default", ALL_TABLES)
+            .is_err());
+
+        let root = Principal::user("root");
+        assert!(authorizer.authorize(&root, Write, "app", "users").is_ok());
+        assert!(authorizer
+            .authorize(&root, Admin, ALL_TABLES, ALL_TABLES)
+            .is_ok());
+
+        let err = authorizer
+            .authorize(&Principal::user("nobody"), Read, "default", "default")
+            .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized(_)));
+        assert!(authorizer
+            .authorize(&Principal::AnonymousUser, Read, "default", "default")
+            .is_err());
+    }
+
+    #[test]
+    fn disabled_without_roles() {
+        let authorizer = Authorizer::new(&HashMap::new(), &[], ());
+
+        assert!(authorizer
+            .authorize(&Principal::user("nobody"), Permission::Admin, "a", "b")
+            .is_ok());
     }
 }
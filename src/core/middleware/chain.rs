@@ -8,9 +8,13 @@ pub(crate) struct MiddlewareChain {
 
 impl MiddlewareChain {
     pub(crate) fn new(config: &Config, dispatcher: Dispatcher) -> Self {
-        let authorizer = Authorizer::new(dispatcher);
+        let authorizer = Authorizer::new(&config.roles, &config.users, dispatcher);
 
-        let authenticator = Authenticator::new(config.users.clone(), authorizer);
+        let authenticator = Authenticator::new(
+            config.users.clone(),
+            config.allow_plaintext_passwords,
+            authorizer,
+        );
 
         let logger = Logger::new(authenticator);
 
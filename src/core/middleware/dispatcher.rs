@@ -1,40 +1,378 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use chrono::Utc;
-use tokio::sync::mpsc;
+use tokio::fs;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
 
-use crate::common::{ErrorKind, Result};
+use crate::common::{debug, error, info, warn, ErrorKind, Result};
+use crate::config::filepath;
 use crate::core::middleware::Middleware;
-use crate::core::UnitOfWork;
+use crate::core::transaction::{Coordinator, Participant, TransactionLog};
+use crate::core::uow::{Commit, DropTable, Prepare, TableNames, Work};
+use crate::core::{Config, Table, UnitOfWork};
 
 pub(crate) struct Dispatcher {
-    table: HashMap<String, HashMap<String, mpsc::Sender<UnitOfWork>>>,
+    // directory where namespaces are stored.
+    namespaces_dir: PathBuf,
+    config: Config,
+    table: HashMap<String, HashMap<String, TableHandle>>,
+    // tables waiting for the task to stop to remove the directory.
+    dropping: HashMap<(String, String), JoinHandle<()>>,
+    // shared with the coordinators of the transactions being committed.
+    transaction_log: Arc<Mutex<TransactionLog>>,
+}
+
+// Handle of the running table task.
+struct TableHandle {
+    sender: mpsc::Sender<UnitOfWork>,
+    task: JoinHandle<()>,
+    // held by the transaction being committed on the table.
+    lock: Arc<Mutex<()>>,
 }
 
 impl Dispatcher {
-    pub(crate) fn new() -> Self {
+    // Maximum bytes of namespace and table name.
+    const MAX_NAME_BYTES: usize = 128;
+
+    pub(crate) fn new(
+        namespaces_dir: impl Into<PathBuf>,
+        transaction_log: impl Into<PathBuf>,
+        config: Config,
+    ) -> Self {
         Self {
+            namespaces_dir: namespaces_dir.into(),
+            config,
             table: HashMap::new(),
+            dropping: HashMap::new(),
+            transaction_log: Arc::new(Mutex::new(TransactionLog::new(transaction_log))),
         }
     }
 
-    pub(crate) fn add_table<S>(&mut self, namespace: S, table: S, sender: mpsc::Sender<UnitOfWork>)
-    where
-        S: Into<String>,
-    {
-        self.table
-            .entry(namespace.into())
-            .or_default()
-            .insert(table.into(), sender);
+    // Open the namespaces and tables found in the directory tree.
+    // namespaces/{namespace}/{table}/{table}.kvsd
+    // the transaction committed but not cleared from the log is applied before tables start.
+    pub(crate) async fn discover(&mut self) -> Result<()> {
+        let mut opened = Vec::new();
+
+        let mut namespaces = fs::read_dir(&self.namespaces_dir).await?;
+
+        while let Some(namespace_dir) = namespaces.next_entry().await? {
+            if !namespace_dir.file_type().await?.is_dir() {
+                continue;
+            }
+            let namespace = match namespace_dir.file_name().into_string() {
+                Ok(name) if Dispatcher::validate_name(&name).is_ok() => name,
+                name => {
+                    warn!(?name, "Ignore invalid namespace directory");
+                    continue;
+                }
+            };
+            self.table.entry(namespace.clone()).or_default();
+
+            let mut tables = fs::read_dir(namespace_dir.path()).await?;
+            while let Some(table_dir) = tables.next_entry().await? {
+                if !table_dir.file_type().await?.is_dir() {
+                    continue;
+                }
+                match table_dir.file_name().into_string() {
+                    Ok(table) if Dispatcher::validate_name(&table).is_ok() => {
+                        let t = self.open_table(&namespace, &table).await?;
+                        opened.push((namespace.clone(), table, t));
+                    }
+                    name => {
+                        warn!(%namespace, ?name, "Ignore invalid table directory");
+                    }
+                }
+            }
+        }
+
+        self.redo_transaction(&mut opened).await?;
+
+        for (namespace, table, t) in opened {
+            self.spawn_table(&namespace, &table, t);
+        }
+
+        Ok(())
+    }
+
+    async fn redo_transaction(&self, opened: &mut [(String, String, Table)]) -> Result<()> {
+        let transaction_log = self.transaction_log.lock().await;
+        let committed = transaction_log.load().await?;
+        if committed.is_empty() {
+            return Ok(());
+        }
+
+        info!(tables = committed.len(), "Redo committed transaction");
+
+        for writes in committed {
+            match opened.iter_mut().find(|(namespace, table, _)| {
+                *namespace == writes.namespace && *table == writes.table
+            }) {
+                Some((_, _, t)) => t.apply_committed(writes.writes).await?,
+                None => warn!(
+                    namespace = %writes.namespace,
+                    table = %writes.table,
+                    "Ignore committed writes of missing table"
+                ),
+            }
+        }
+
+        transaction_log.clear().await
+    }
+
+    async fn open_table(&self, namespace: &str, table: &str) -> Result<Table> {
+        let path = self
+            .table_dir(namespace, table)
+            .join(format!("{}.kvsd", table));
+        debug!("Open table file {}", path.display());
+
+        Table::from_path(path, self.config.table_config(namespace, table)).await
+    }
+
+    fn spawn_table(&mut self, namespace: &str, table: &str, t: Table) {
+        // TODO configure channel size
+        let (tx, rx) = mpsc::channel(1024);
+        let task = tokio::spawn(t.run(rx));
+
+        self.table.entry(namespace.to_owned()).or_default().insert(
+            table.to_owned(),
+            TableHandle {
+                sender: tx,
+                task,
+                lock: Arc::new(Mutex::new(())),
+            },
+        );
     }
 
     fn lookup_table(&self, namespace: &str, table: &str) -> Result<&mpsc::Sender<UnitOfWork>> {
+        self.lookup_handle(namespace, table)
+            .map(|handle| &handle.sender)
+    }
+
+    fn lookup_handle(&self, namespace: &str, table: &str) -> Result<&TableHandle> {
         self.table
             .get(namespace)
             .and_then(|tables| tables.get(table))
             .ok_or_else(|| ErrorKind::TableNotFound(format!("{}/{}", namespace, table)).into())
     }
+
+    async fn create_namespace(&mut self, namespace: &str) -> Result<()> {
+        Dispatcher::validate_name(namespace)?;
+        Dispatcher::ensure_not_reserved(namespace)?;
+
+        if self.table.contains_key(namespace) {
+            return Err(ErrorKind::AlreadyExists(format!("namespace {}", namespace)).into());
+        }
+
+        fs::create_dir_all(self.namespaces_dir.join(namespace)).await?;
+        self.table.insert(namespace.to_owned(), HashMap::new());
+
+        info!(%namespace, "Namespace created");
+
+        Ok(())
+    }
+
+    async fn drop_namespace(&mut self, namespace: &str) -> Result<()> {
+        Dispatcher::ensure_not_reserved(namespace)?;
+
+        match self.table.get(namespace) {
+            Some(tables) if !tables.is_empty() => {
+                return Err(ErrorKind::InvalidArgument(format!(
+                    "namespace {} is not empty",
+                    namespace
+                ))
+                .into())
+            }
+            Some(_) => (),
+            None => return Err(ErrorKind::NamespaceNotFound(namespace.to_owned()).into()),
+        }
+        if self.is_dropping(|(ns, _)| ns == namespace) {
+            return Err(ErrorKind::InvalidArgument(format!(
+                "namespace {} has the table being dropped",
+                namespace
+            ))
+            .into());
+        }
+
+        fs::remove_dir_all(self.namespaces_dir.join(namespace)).await?;
+        self.table.remove(namespace);
+
+        info!(%namespace, "Namespace dropped");
+
+        Ok(())
+    }
+
+    async fn create_table(&mut self, namespace: &str, table: &str) -> Result<()> {
+        Dispatcher::validate_name(table)?;
+        Dispatcher::ensure_not_reserved(namespace)?;
+
+        match self.table.get(namespace) {
+            Some(tables) if tables.contains_key(table) => {
+                return Err(
+                    ErrorKind::AlreadyExists(format!("table {}/{}", namespace, table)).into(),
+                )
+            }
+            Some(_) => (),
+            None => return Err(ErrorKind::NamespaceNotFound(namespace.to_owned()).into()),
+        }
+        if self.is_dropping(|(ns, t)| ns == namespace && t == table) {
+            return Err(ErrorKind::InvalidArgument(format!(
+                "table {}/{} is being dropped",
+                namespace, table
+            ))
+            .into());
+        }
+
+        fs::create_dir_all(self.table_dir(namespace, table)).await?;
+        let t = self.open_table(namespace, table).await?;
+        self.spawn_table(namespace, table, t);
+
+        info!(%namespace, %table, "Table created");
+
+        Ok(())
+    }
+
+    // The table is removed from the dispatcher at once, but waiting for the queued unit of works
+    // can take long, so the directory is removed and responded by the spawned task.
+    fn drop_table(&mut self, mut work: Work<DropTable, ()>) -> Result<()> {
+        let namespace = work.request.namespace.clone();
+        let table = work.request.table.clone();
+        let handle = match self
+            .table
+            .get_mut(&namespace)
+            .and_then(|tables| tables.remove(&table))
+        {
+            Some(handle) => handle,
+            None => {
+                return work.send_response(Err(ErrorKind::TableNotFound(format!(
+                    "{}/{}",
+                    namespace, table
+                ))
+                .into()))
+            }
+        };
+
+        // Closing the channel lets the table handle queued unit of works then stop.
+        let TableHandle { sender, task, .. } = handle;
+        drop(sender);
+        let table_dir = self.table_dir(&namespace, &table);
+        let dropping = tokio::spawn({
+            let namespace = namespace.clone();
+            let table = table.clone();
+            async move {
+                let result = async {
+                    task.await
+                        .map_err(|err| ErrorKind::Internal(format!("table task {}", err)))?;
+                    fs::remove_dir_all(table_dir).await?;
+                    info!(%namespace, %table, "Table dropped");
+                    Ok(())
+                }
+                .await;
+                if let Err(err) = work.send_response(result) {
+                    error!("respond drop table {}", err);
+                }
+            }
+        });
+        self.dropping.insert((namespace, table), dropping);
+
+        Ok(())
+    }
+
+    // Whether any table matching the predicate is still being dropped.
+    fn is_dropping(&mut self, predicate: impl FnMut(&(String, String)) -> bool) -> bool {
+        self.dropping.retain(|_, task| !task.is_finished());
+        self.dropping.keys().any(predicate)
+    }
+
+    // Split the transaction into the tables taking part in it.
+    fn participants(&self, commit: Commit) -> Result<Vec<Participant>> {
+        let mut prepares: BTreeMap<(String, String), Prepare> = BTreeMap::new();
+        fn prepare_of(
+            prepares: &mut BTreeMap<(String, String), Prepare>,
+            namespace: String,
+            table: String,
+        ) -> &mut Prepare {
+            prepares
+                .entry((namespace.clone(), table.clone()))
+                .or_insert_with(|| Prepare {
+                    namespace,
+                    table,
+                    reads: Vec::new(),
+                    writes: Vec::new(),
+                })
+        }
+        for (key, version) in commit.reads {
+            prepare_of(&mut prepares, key.namespace, key.table)
+                .reads
+                .push((key.key, version));
+        }
+        for (key, value) in commit.writes {
+            prepare_of(&mut prepares, key.namespace, key.table)
+                .writes
+                .push((key.key, value));
+        }
+
+        prepares
+            .into_values()
+            .map(|prepare| {
+                let handle = self.lookup_handle(&prepare.namespace, &prepare.table)?;
+                Ok(Participant {
+                    sender: handle.sender.clone(),
+                    lock: Arc::clone(&handle.lock),
+                    prepare,
+                })
+            })
+            .collect()
+    }
+
+    fn list_tables(&self) -> TableNames {
+        let mut tables = self
+            .table
+            .iter()
+            .flat_map(|(namespace, tables)| {
+                tables
+                    .keys()
+                    .map(move |table| (namespace.clone(), table.clone()))
+            })
+            .collect::<Vec<_>>();
+        tables.sort();
+        tables
+    }
+
+    fn table_dir(&self, namespace: &str, table: &str) -> PathBuf {
+        self.namespaces_dir.join(namespace).join(table)
+    }
+
+    // Name is used as directory name, so restrict it to safe characters.
+    fn validate_name(name: &str) -> Result<()> {
+        let valid = !name.is_empty()
+            && name.len() <= Dispatcher::MAX_NAME_BYTES
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+        if valid {
+            Ok(())
+        } else {
+            Err(ErrorKind::InvalidArgument(format!(
+                "name {:?} must consist of alphanumeric, '_' or '-'",
+                name
+            ))
+            .into())
+        }
+    }
+
+    fn ensure_not_reserved(namespace: &str) -> Result<()> {
+        if namespace == filepath::NS_SYSTEM {
+            Err(ErrorKind::InvalidArgument(format!("namespace {} is reserved", namespace)).into())
+        } else {
+            Ok(())
+        }
+    }
 }
 
 #[async_trait]
@@ -68,6 +406,101 @@ impl Middleware for Dispatcher {
                     Err(err) => delete.send_response(Err(err)),
                 }
             }
+            UnitOfWork::Scan(ref mut scan) => {
+                match self.lookup_table(&scan.request.namespace, &scan.request.table) {
+                    Ok(sender) => Ok(sender.send(uow).await?),
+                    Err(err) => scan.send_response(Err(err)),
+                }
+            }
+            UnitOfWork::MSet(ref mut mset) => {
+                match self.lookup_table(&mset.request.namespace, &mset.request.table) {
+                    Ok(sender) => Ok(sender.send(uow).await?),
+                    Err(err) => mset.send_response(Err(err)),
+                }
+            }
+            UnitOfWork::MGet(ref mut mget) => {
+                match self.lookup_table(&mget.request.namespace, &mget.request.table) {
+                    Ok(sender) => Ok(sender.send(uow).await?),
+                    Err(err) => mget.send_response(Err(err)),
+                }
+            }
+            UnitOfWork::MDelete(ref mut mdelete) => {
+                match self.lookup_table(&mdelete.request.namespace, &mdelete.request.table) {
+                    Ok(sender) => Ok(sender.send(uow).await?),
+                    Err(err) => mdelete.send_response(Err(err)),
+                }
+            }
+            UnitOfWork::Expire(ref mut expire) => {
+                match self.lookup_table(&expire.request.namespace, &expire.request.table) {
+                    Ok(sender) => Ok(sender.send(uow).await?),
+                    Err(err) => expire.send_response(Err(err)),
+                }
+            }
+            UnitOfWork::Ttl(ref mut ttl) => {
+                match self.lookup_table(&ttl.request.namespace, &ttl.request.table) {
+                    Ok(sender) => Ok(sender.send(uow).await?),
+                    Err(err) => ttl.send_response(Err(err)),
+                }
+            }
+            UnitOfWork::WriteChunk(ref mut write) => {
+                match self.lookup_table(&write.request.namespace, &write.request.table) {
+                    Ok(sender) => Ok(sender.send(uow).await?),
+                    Err(err) => write.send_response(Err(err)),
+                }
+            }
+            UnitOfWork::ReadChunk(ref mut read) => {
+                match self.lookup_table(&read.request.namespace, &read.request.table) {
+                    Ok(sender) => Ok(sender.send(uow).await?),
+                    Err(err) => read.send_response(Err(err)),
+                }
+            }
+            UnitOfWork::CompactTable(ref mut compact) => {
+                match self.lookup_table(&compact.request.namespace, &compact.request.table) {
+                    Ok(sender) => Ok(sender.send(uow).await?),
+                    Err(err) => compact.send_response(Err(err)),
+                }
+            }
+            UnitOfWork::Commit(mut commit) => {
+                let request = std::mem::take(&mut commit.request);
+                let participants = match self.participants(request) {
+                    Ok(participants) => participants,
+                    Err(err) => return commit.send_response(Err(err)),
+                };
+                let coordinator = Coordinator::new(
+                    Arc::clone(&commit.principal),
+                    participants,
+                    Arc::clone(&self.transaction_log),
+                );
+                tokio::spawn(async move {
+                    let result = coordinator.commit().await;
+                    if let Err(err) = commit.send_response(result) {
+                        error!("respond commit {}", err);
+                    }
+                });
+                Ok(())
+            }
+            UnitOfWork::CreateNamespace(ref mut create) => {
+                let result = self.create_namespace(&create.request.namespace).await;
+                create.send_response(result)
+            }
+            UnitOfWork::DropNamespace(ref mut drop) => {
+                let result = self.drop_namespace(&drop.request.namespace).await;
+                drop.send_response(result)
+            }
+            UnitOfWork::CreateTable(ref mut create) => {
+                let result = self
+                    .create_table(&create.request.namespace, &create.request.table)
+                    .await;
+                create.send_response(result)
+            }
+            UnitOfWork::DropTable(drop) => self.drop_table(drop),
+            UnitOfWork::ListTables(ref mut list) => list.send_response(Ok(self.list_tables())),
+            UnitOfWork::LookupTable(ref mut lookup) => {
+                let result = self
+                    .lookup_table(&lookup.request.namespace, &lookup.request.table)
+                    .map(|_| ());
+                lookup.send_response(result)
+            }
             _ => unreachable!(),
         }
     }
@@ -1,6 +1,6 @@
 use async_trait::async_trait;
 
-use crate::common::{info, Result};
+use crate::common::{debug, info, Result};
 use crate::core::middleware::Middleware;
 use crate::core::UnitOfWork;
 
@@ -22,10 +22,16 @@ where
     async fn apply(&mut self, uow: UnitOfWork) -> Result<()> {
         let start = tokio::time::Instant::now();
         let log = format!("{:?}", uow);
+        // Streams are made of many chunks, so they are logged only for debugging.
+        let is_chunk = matches!(uow, UnitOfWork::WriteChunk(_) | UnitOfWork::ReadChunk(_));
 
         let result = self.next.apply(uow).await;
 
-        info!(uow=%log, elapsed=?start.elapsed(),?result ,"Uow done");
+        if is_chunk {
+            debug!(uow=%log, elapsed=?start.elapsed(),?result ,"Uow done");
+        } else {
+            info!(uow=%log, elapsed=?start.elapsed(),?result ,"Uow done");
+        }
 
         result
     }
@@ -6,10 +6,14 @@ mod kvsd;
 pub(crate) use self::kvsd::Builder;
 
 mod config;
-pub use self::config::{Config, UserEntry};
+pub use self::config::{
+    Config, Durability, Grant, Permission, RecoveryMode, TableConfig, UserEntry,
+};
 
 mod table;
-pub(crate) use table::{EntryDump, Table};
+pub(crate) use table::{dump_file, EntryDump, Table};
+
+mod transaction;
 
 mod principal;
 pub(crate) use self::principal::Principal;
@@ -17,6 +21,8 @@ pub(crate) use self::principal::Principal;
 pub(crate) mod uow;
 pub(crate) use self::uow::{UnitOfWork, Work};
 
+pub(crate) mod password;
+
 mod credential;
 pub(crate) use self::credential::{Credential, Password, Provider as CredentialProvider};
 
@@ -0,0 +1,141 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Argon2, Params};
+use subtle::ConstantTimeEq;
+
+use crate::common::{ErrorKind, Result};
+
+// Hash the password with argon2id.
+// the hash is in PHC string format, e.g. $argon2id$v=19$m=19456,t=2,p=1$...
+pub(crate) fn hash(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| ErrorKind::Internal(format!("hash password {}", err)).into())
+}
+
+// Verify the password with the argon2id or bcrypt hash.
+// both verifications compare the hashes in constant time.
+pub(crate) fn verify(password: &str, hash: &str) -> bool {
+    if is_bcrypt(hash) {
+        return bcrypt::verify(password, hash).unwrap_or(false);
+    }
+
+    match PasswordHash::new(hash) {
+        Ok(hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+// Hash of the random password with the same algorithm and cost as the given hash.
+// verifying the unknown user against it takes as long as the known user.
+pub(crate) fn dummy_hash(like: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let password = salt.as_str().to_owned();
+    if is_bcrypt(like) {
+        let cost = like
+            .parse::<bcrypt::HashParts>()
+            .map_err(|err| ErrorKind::Internal(format!("parse hash {}", err)))?
+            .get_cost();
+        return bcrypt::hash(password, cost)
+            .map_err(|err| ErrorKind::Internal(format!("hash password {}", err)).into());
+    }
+
+    let like = PasswordHash::new(like)
+        .map_err(|err| ErrorKind::Internal(format!("parse hash {}", err)))?;
+    let params = Params::try_from(&like)
+        .map_err(|err| ErrorKind::Internal(format!("parse hash {}", err)))?;
+    Argon2::default()
+        .hash_password_customized(
+            password.as_bytes(),
+            Some(like.algorithm),
+            like.version,
+            params,
+            &salt,
+        )
+        .map(|hash| hash.to_string())
+        .map_err(|err| ErrorKind::Internal(format!("hash password {}", err)).into())
+}
+
+// Whether the hash is in the format verify accepts.
+pub(crate) fn is_valid_hash(hash: &str) -> bool {
+    is_bcrypt(hash) || PasswordHash::new(hash).is_ok()
+}
+
+pub(crate) fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
+
+fn is_bcrypt(hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn argon2id() {
+        let hashed = hash("secret").unwrap();
+        assert!(hashed.starts_with("$argon2id$"));
+        assert!(is_valid_hash(&hashed));
+
+        assert!(verify("secret", &hashed));
+        assert!(!verify("secret2", &hashed));
+        // Salt differs per hash.
+        assert_ne!(hashed, hash("secret").unwrap());
+    }
+
+    #[test]
+    fn bcrypt() {
+        let hashed = bcrypt::hash("secret", 4).unwrap();
+        assert!(is_valid_hash(&hashed));
+
+        assert!(verify("secret", &hashed));
+        assert!(!verify("secret2", &hashed));
+    }
+
+    #[test]
+    fn dummy() {
+        let argon2 = Argon2::new(
+            argon2::Algorithm::Argon2i,
+            argon2::Version::V0x13,
+            Params::new(8192, 3, 1, None).unwrap(),
+        )
+        .hash_password(b"secret", &SaltString::generate(&mut OsRng))
+        .unwrap()
+        .to_string();
+        let dummy = dummy_hash(&argon2).unwrap();
+        assert!(
+            dummy.starts_with("$argon2i$v=19$m=8192,t=3,p=1$"),
+            "{}",
+            dummy
+        );
+        assert!(!verify("secret", &dummy));
+
+        let bcrypt = bcrypt::hash("secret", 5).unwrap();
+        let dummy = dummy_hash(&bcrypt).unwrap();
+        assert!(dummy.starts_with("$2b$05$"), "{}", dummy);
+        assert!(!verify("secret", &dummy));
+
+        assert!(dummy_hash("secret").is_err());
+    }
+
+    #[test]
+    fn invalid_hash() {
+        assert!(!is_valid_hash("secret"));
+        assert!(!verify("secret", "secret"));
+        assert!(!verify("secret", "$2b$invalid"));
+    }
+
+    #[test]
+    fn plaintext() {
+        assert!(constant_time_eq("secret", "secret"));
+        assert!(!constant_time_eq("secret", "secret2"));
+    }
+}
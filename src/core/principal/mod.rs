@@ -4,11 +4,14 @@ pub(crate) use user::User;
 #[derive(Debug, Clone)]
 pub(crate) enum Principal {
     AnonymousUser,
-    #[allow(dead_code)]
     User(User),
 }
 
 impl Principal {
+    pub(crate) fn user(name: impl Into<String>) -> Self {
+        Principal::User(User { name: name.into() })
+    }
+
     pub(crate) fn is_authenticated(&self) -> bool {
         matches!(self, Principal::User(_))
     }
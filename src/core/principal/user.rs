@@ -1,5 +1,4 @@
 #[derive(Debug, Clone)]
 pub(crate) struct User {
-    #[allow(dead_code)]
     pub(crate) name: String,
 }
@@ -0,0 +1,270 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, SeekFrom};
+
+use crate::common::{debug, warn, ErrorKind, Result};
+use crate::core::table::suffixed_path;
+use crate::protocol::Key;
+
+// Values uploaded by stream are stored in the blob files in the directory next to the table file,
+// so that the table file does not hold the large values and the upload does not block the other
+// writes to the table. the table file has the entry referencing the blob.
+//
+// Blob files are immutable once the upload is finished. the files no longer referenced are removed
+// after compaction, as the entry referencing them may be still in the table file until then.
+pub(super) struct Blobs {
+    dir: PathBuf,
+    next_id: u64,
+    uploads: HashMap<u64, Upload>,
+}
+
+// Reference to the blob file stored as the value of the entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct BlobRef {
+    pub(super) id: u64,
+    pub(super) len: u64,
+    crc_checksum: u32,
+}
+
+// Upload in progress.
+struct Upload {
+    file: fs::File,
+    key: Key,
+    len: u64,
+    hasher: crc32fast::Hasher,
+    updated_at: Instant,
+}
+
+impl BlobRef {
+    // | id(8) | len(8) | crc_checksum(4) |
+    pub(super) const ENCODED_BYTES: usize = 8 + 8 + 4;
+
+    pub(super) fn encode(&self) -> Box<[u8]> {
+        [
+            self.id.to_be_bytes().as_ref(),
+            self.len.to_be_bytes().as_ref(),
+            self.crc_checksum.to_be_bytes().as_ref(),
+        ]
+        .concat()
+        .into_boxed_slice()
+    }
+
+    pub(super) fn decode(b: &[u8]) -> Option<Self> {
+        if b.len() != BlobRef::ENCODED_BYTES {
+            return None;
+        }
+        Some(BlobRef {
+            id: u64::from_be_bytes(<[u8; 8]>::try_from(&b[0..8]).ok()?),
+            len: u64::from_be_bytes(<[u8; 8]>::try_from(&b[8..16]).ok()?),
+            crc_checksum: u32::from_be_bytes(<[u8; 4]>::try_from(&b[16..20]).ok()?),
+        })
+    }
+}
+
+impl Blobs {
+    // Open the blob directory of the table file.
+    // new uploads are numbered after the existing blob files.
+    pub(super) async fn open(table_path: &Path) -> Result<Self> {
+        let dir = suffixed_path(table_path, ".blobs");
+        fs::create_dir_all(&dir).await?;
+
+        let mut next_id = 0;
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(id) = Blobs::id_of(&entry.path()) {
+                next_id = next_id.max(id + 1);
+            }
+        }
+
+        Ok(Self {
+            dir,
+            next_id,
+            uploads: HashMap::new(),
+        })
+    }
+
+    // Append the chunk to the upload. upload is started if not given.
+    // return the id of the upload.
+    pub(super) async fn write(
+        &mut self,
+        upload: Option<u64>,
+        key: &Key,
+        data: &[u8],
+    ) -> Result<u64> {
+        let id = match upload {
+            Some(id) => id,
+            None => self.start(key.clone()).await?,
+        };
+        let upload = match self.uploads.get_mut(&id) {
+            Some(upload) if upload.key == *key => upload,
+            _ => {
+                return Err(ErrorKind::InvalidArgument(format!(
+                    "upload {} of {} not found",
+                    id, key
+                ))
+                .into())
+            }
+        };
+
+        upload.file.write_all(data).await?;
+        upload.hasher.update(data);
+        upload.len += data.len() as u64;
+        upload.updated_at = Instant::now();
+
+        Ok(id)
+    }
+
+    // Make the upload durable and return the reference to it.
+    pub(super) async fn finish(&mut self, id: u64) -> Result<BlobRef> {
+        let mut upload = self
+            .uploads
+            .remove(&id)
+            .ok_or_else(|| ErrorKind::InvalidArgument(format!("upload {} not found", id)))?;
+
+        upload.file.flush().await?;
+        upload.file.sync_data().await?;
+
+        Ok(BlobRef {
+            id,
+            len: upload.len,
+            crc_checksum: upload.hasher.finalize(),
+        })
+    }
+
+    // Discard the upload and its file.
+    pub(super) async fn abort(&mut self, id: u64) {
+        if self.uploads.remove(&id).is_some() {
+            if let Err(err) = fs::remove_file(self.path(id)).await {
+                warn!(id, "Remove aborted upload {}", err);
+            }
+        }
+    }
+
+    // Discard the uploads which have not been written for the duration.
+    // client may have gone in the middle of the upload.
+    pub(super) async fn abort_idle(&mut self, idle: Duration) {
+        let idle = self
+            .uploads
+            .iter()
+            .filter(|(_, upload)| upload.updated_at.elapsed() >= idle)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in idle {
+            debug!(id, "Abort idle upload");
+            self.abort(id).await;
+        }
+    }
+
+    // Read the part of the blob.
+    pub(super) async fn read(&self, blob: &BlobRef, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let len = blob.len.saturating_sub(offset).min(len as u64) as usize;
+        let mut buf = vec![0; len];
+
+        let mut file = fs::File::open(self.path(blob.id)).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        file.read_exact(&mut buf).await?;
+
+        Ok(buf)
+    }
+
+    // Read the whole blob and verify its checksum.
+    pub(super) async fn read_all(&self, blob: &BlobRef) -> Result<Vec<u8>> {
+        let buf = self.read(blob, 0, blob.len as usize).await?;
+        if crc32fast::hash(&buf) != blob.crc_checksum {
+            return Err(
+                ErrorKind::Corruption(format!("blob {} checksum mismatch", blob.id)).into(),
+            );
+        }
+        Ok(buf)
+    }
+
+    // Remove the blob files neither referenced nor being uploaded.
+    pub(super) async fn remove_unreferenced(&mut self, referenced: &HashSet<u64>) -> Result<()> {
+        let mut entries = fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            match Blobs::id_of(&path) {
+                Some(id) if !referenced.contains(&id) && !self.uploads.contains_key(&id) => {
+                    debug!(path = %path.display(), "Remove unreferenced blob");
+                    fs::remove_file(&path).await?;
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    async fn start(&mut self, key: Key) -> Result<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(self.path(id))
+            .await?;
+        self.uploads.insert(
+            id,
+            Upload {
+                file,
+                key,
+                len: 0,
+                hasher: crc32fast::Hasher::new(),
+                updated_at: Instant::now(),
+            },
+        );
+
+        Ok(id)
+    }
+
+    fn path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{:016x}.blob", id))
+    }
+
+    fn id_of(path: &Path) -> Option<u64> {
+        let name = path.file_name()?.to_str()?.strip_suffix(".blob")?;
+        u64::from_str_radix(name, 16).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn upload() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut blobs = Blobs::open(&dir.path().join("t.kvsd")).await.unwrap();
+        let key = Key::new("key").unwrap();
+
+        let id = blobs.write(None, &key, b"hello ").await.unwrap();
+        assert_eq!(blobs.write(Some(id), &key, b"world").await.unwrap(), id);
+        assert!(blobs
+            .write(Some(id), &Key::new("other").unwrap(), b"!")
+            .await
+            .is_err());
+        let blob = blobs.finish(id).await.unwrap();
+        assert_eq!(BlobRef::decode(&blob.encode()), Some(blob));
+
+        assert_eq!(blobs.read_all(&blob).await.unwrap(), b"hello world");
+        assert_eq!(blobs.read(&blob, 6, 100).await.unwrap(), b"world");
+        assert!(blobs.read(&blob, 11, 100).await.unwrap().is_empty());
+
+        // Aborted and unreferenced blobs are removed.
+        let aborted = blobs.write(None, &key, b"x").await.unwrap();
+        blobs.abort(aborted).await;
+        let uploading = blobs.write(None, &key, b"x").await.unwrap();
+        blobs.remove_unreferenced(&HashSet::new()).await.unwrap();
+        assert!(blobs.read(&blob, 0, 1).await.is_err());
+        assert!(blobs.path(uploading).exists());
+        assert!(!blobs.path(aborted).exists());
+
+        // Ids are not reused after reopen.
+        let reopened = Blobs::open(&dir.path().join("t.kvsd")).await.unwrap();
+        assert_eq!(reopened.next_id, uploading + 1);
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufWriter, SeekFrom};
+use tokio::task::JoinHandle;
+
+use crate::common::{debug, ErrorKind, Result};
+use crate::core::table::entry::Entry;
+use crate::core::table::index::{Index, Location};
+use crate::core::table::suffixed_path;
+
+// Compaction rewrites the entries referenced by the index into a new file.
+// overwritten values and tombstones are not copied, so the space they occupy is reclaimed.
+//
+// Compaction runs on a separate task against a snapshot of the index.
+// entries appended to the table while compaction is running are copied by the table
+// when the compacted file is swapped in.
+pub(super) struct Compaction {
+    handle: JoinHandle<Result<Compacted>>,
+}
+
+// Result of compaction.
+pub(super) struct Compacted {
+    // compacted file path.
+    pub(super) path: PathBuf,
+    // index of the compacted file.
+    pub(super) index: Index,
+    // table file length at the time of snapshot.
+    pub(super) source_len: u64,
+    // compacted file length.
+    pub(super) len: u64,
+    // ids of the blobs referenced by the compacted file.
+    pub(super) blobs: HashSet<u64>,
+}
+
+impl Compaction {
+    pub(super) fn start(
+        source: PathBuf,
+        snapshot: Vec<(String, Location)>,
+        max_version: u64,
+        source_len: u64,
+    ) -> Self {
+        let handle = tokio::spawn(Compaction::compact(
+            source,
+            snapshot,
+            max_version,
+            source_len,
+        ));
+
+        Self { handle }
+    }
+
+    // Wait for running compaction to complete.
+    // if compaction is not running, wait forever.
+    pub(super) async fn wait(compaction: &mut Option<Compaction>) -> Result<Compacted> {
+        match compaction {
+            Some(compaction) => (&mut compaction.handle)
+                .await
+                .map_err(|err| ErrorKind::Internal(format!("compaction task {}", err)))?,
+            None => std::future::pending().await,
+        }
+    }
+
+    async fn compact(
+        source: PathBuf,
+        snapshot: Vec<(String, Location)>,
+        max_version: u64,
+        source_len: u64,
+    ) -> Result<Compacted> {
+        let path = compacted_path(&source);
+        debug!("Compact {} into {}", source.display(), path.display());
+
+        let mut src = fs::File::open(&source).await?;
+        let mut dest = BufWriter::new(fs::File::create(&path).await?);
+
+        let mut index = Index::new();
+        // Versions of the reclaimed tombstones are kept by the hint written with the index.
+        index.raise_max_version(max_version);
+        let mut blobs = HashSet::new();
+        let mut buf = Vec::new();
+        let mut pos: usize = 0;
+
+        // Snapshot is ordered by offset, so source file is read from front to back.
+        for (key, location) in snapshot {
+            buf.resize(location.len, 0);
+            src.seek(SeekFrom::Start(location.offset as u64)).await?;
+            src.read_exact(&mut buf).await?;
+            dest.write_all(&buf).await?;
+            if let Some(blob) = Entry::blob_of_encoded(&buf) {
+                blobs.insert(blob.id);
+            }
+
+            index.add(
+                key,
+                Location {
+                    offset: pos,
+                    ..location
+                },
+            );
+            pos += location.len;
+        }
+
+        dest.flush().await?;
+        dest.get_mut().sync_all().await?;
+
+        Ok(Compacted {
+            path,
+            index,
+            source_len,
+            len: pos as u64,
+            blobs,
+        })
+    }
+}
+
+// Return the path where compacted file is written. (e.g. default.kvsd.compact)
+fn compacted_path(source: &Path) -> PathBuf {
+    suffixed_path(source, ".compact")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::table::testing::{delete, get, mdelete, set};
+    use crate::core::table::Table;
+    use crate::core::TableConfig;
+    use crate::protocol::Value;
+
+    async fn value(table: &mut Table, key: &str) -> Option<Value> {
+        get(table, key).await.map(|versioned| versioned.value)
+    }
+
+    #[tokio::test]
+    async fn compact() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("default.kvsd");
+
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        set(&mut table, "key1", "value1").await;
+        set(&mut table, "key1", "value2").await;
+        set(&mut table, "key2", "value1").await;
+        delete(&mut table, "key2").await.unwrap();
+        set(&mut table, "key3", "value1").await;
+
+        let before = std::fs::metadata(&path).unwrap().len();
+        table.compact().await.unwrap();
+        let after = std::fs::metadata(&path).unwrap().len();
+        assert!(after < before);
+
+        assert_eq!(value(&mut table, "key1").await.unwrap().as_ref(), b"value2");
+        assert_eq!(value(&mut table, "key2").await, None);
+        assert_eq!(value(&mut table, "key3").await.unwrap().as_ref(), b"value1");
+
+        // Make sure that write after compaction is reflected on reopen.
+        set(&mut table, "key4", "value1").await;
+        drop(table);
+
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(value(&mut table, "key1").await.unwrap().as_ref(), b"value2");
+        assert_eq!(value(&mut table, "key2").await, None);
+        assert_eq!(value(&mut table, "key4").await.unwrap().as_ref(), b"value1");
+    }
+
+    #[tokio::test]
+    async fn compact_deleted() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("default.kvsd");
+
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        set(&mut table, "key1", "value1").await;
+        set(&mut table, "key1", "value2").await;
+        set(&mut table, "key2", "value1").await;
+        delete(&mut table, "key1").await.unwrap();
+        mdelete(&mut table, &["key2"]).await;
+
+        // Tombstones are reclaimed as well.
+        table.compact().await.unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+        drop(table);
+
+        // Versions of the deleted keys are not reused. key2 was deleted at version 3.
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(value(&mut table, "key1").await, None);
+        set(&mut table, "key2", "value2").await;
+        assert_eq!(get(&mut table, "key2").await.unwrap().version, 4);
+    }
+}
@@ -1,7 +1,120 @@
+use std::path::Path;
+
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncSeek, AsyncSeekExt, BufReader, SeekFrom};
+
+use crate::common::{error, Result};
+use crate::core::table::entry::Entry;
+
 #[derive(Debug)]
 pub(crate) struct EntryDump {
     pub(crate) timestamp_ns: i64,
+    pub(crate) expires_at_ms: Option<i64>,
+    pub(crate) version: u64,
     pub(crate) is_deleted: bool,
     pub(crate) key: String,
     pub(crate) value: Vec<u8>,
 }
+
+// Read the entries of the table file.
+// the file is opened read only, so it can be dumped while the server is running.
+pub(crate) async fn dump_file<F>(path: &Path, callback: F) -> Result<()>
+where
+    F: FnMut(EntryDump),
+{
+    let file = fs::File::open(path).await?;
+    dump(BufReader::new(file), callback).await
+}
+
+// Read the entries from the current position of reader to the end.
+async fn dump<R, F>(mut reader: R, mut callback: F) -> Result<()>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    F: FnMut(EntryDump),
+{
+    let mut offset = reader.stream_position().await?;
+
+    loop {
+        match Entry::decode_from(&mut reader).await {
+            Ok((n, entry)) => {
+                offset += n as u64;
+                callback(entry.into());
+            }
+            Err(err) if err.is_eof() => break,
+            // Continue with the next entry only if the corrupted entry has been read to the end.
+            // otherwise the lengths in its header can not be trusted to find the next entry.
+            Err(err) if err.is_corruption() => {
+                error!(offset, "{err}");
+                match corrupted_entry_end(&mut reader, offset).await? {
+                    Some(end) => offset = end,
+                    None => break,
+                }
+            }
+            Err(err) => {
+                error!(offset, "{err}");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Return the end of the corrupted entry at offset if it has been read to the end,
+// which is the case the lengths passed the check and the checksum did not match.
+async fn corrupted_entry_end<R>(reader: &mut R, offset: u64) -> Result<Option<u64>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let read = reader.stream_position().await?;
+
+    reader.seek(SeekFrom::Start(offset)).await?;
+    let end = Entry::read_encoded_len(&mut *reader)
+        .await
+        .ok()
+        .map(|len| offset.saturating_add(len))
+        .filter(|end| *end == read);
+    reader.seek(SeekFrom::Start(read)).await?;
+
+    Ok(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::protocol::{Key, Value};
+
+    async fn dumped_keys(buf: Vec<u8>) -> Vec<String> {
+        let mut keys = Vec::new();
+        dump(Cursor::new(buf), |entry| keys.push(entry.key))
+            .await
+            .unwrap();
+        keys
+    }
+
+    #[tokio::test]
+    async fn corrupted_entry() {
+        let mut buf = Vec::new();
+        let mut offsets = Vec::new();
+        for key in ["key1", "key2", "key3"] {
+            offsets.push(buf.len());
+            Entry::new(Key::new(key).unwrap(), Value::new(key.as_bytes()).unwrap())
+                .unwrap()
+                .encode_to(&mut buf)
+                .await
+                .unwrap();
+        }
+
+        // Entry whose checksum does not match is skipped.
+        let mut flipped = buf.clone();
+        flipped[offsets[2] - 1] ^= 0xff;
+        assert_eq!(dumped_keys(flipped).await, vec!["key1", "key3"]);
+
+        // Dump stops at the entry whose length is invalid.
+        let mut flipped = buf;
+        flipped[offsets[1] + 8] ^= 0xff;
+        assert_eq!(dumped_keys(flipped).await, vec!["key1"]);
+    }
+}
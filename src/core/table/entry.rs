@@ -3,7 +3,8 @@ use std::convert::TryFrom;
 use chrono::Utc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-use crate::protocol::{Key, KeyValue, Value};
+use crate::core::table::blob::BlobRef;
+use crate::protocol::{Key, KeyValue, Value, MAX_KYE_BYTES, MAX_VALUE_BYTES};
 use crate::{
     common::{Error, ErrorKind, Result},
     core::EntryDump,
@@ -22,6 +23,10 @@ enum State {
     Invalid = 0,
     Active = 1,
     Deleted = 2,
+    // marks the start of the batch. value is the number of entries in the batch.
+    Batch = 3,
+    // value is stored in the blob file. value is the reference to the blob.
+    Blob = 4,
 }
 
 // store mata value for entry.
@@ -36,10 +41,36 @@ struct Header {
     timestamp_ms: i64,
     // entry state. for support delete operation.
     state: State,
+    // entry expiration timestamp.
+    // milliseconds since January 1,1970 UTC
+    expires_at_ms: Option<i64>,
+    // per key version incremented on every write. 0 means unversioned.
+    // the key created again continues from the greatest version of the table.
+    version: u64,
     // check data integrity.
     crc_checksum: Option<u32>,
 }
 
+// On-disk format of the entry.
+// format is stored in the upper 4 bits of the state byte,
+// so that entries written before the format was introduced are read as V1.
+//
+// V1
+// | key_bytes(8) | value_bytes(8) | timestamp_ms(8) | state(1) | crc_checksum(4) | key | value |
+//
+// V2
+// | key_bytes(8) | value_bytes(8) | timestamp_ms(8) | state(1) | expires_at_ms(8) | crc_checksum(4) | key | value |
+//
+// V3 (expires_at_ms 0 means no expiration)
+// | key_bytes(8) | value_bytes(8) | timestamp_ms(8) | state(1) | expires_at_ms(8) | version(8) | crc_checksum(4) | key | value |
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    V1 = 0,
+    V2 = 1,
+    V3 = 2,
+}
+
 // actual data provided by user.
 #[derive(PartialEq, Debug)]
 struct Body {
@@ -61,6 +92,12 @@ impl Entry {
         + 1 // state
         + 4 // crc_checksum
     ;
+    // Length of the largest entry of the current format.
+    pub(super) const MAX_ENCODED_BYTES: usize = Entry::HEADER_BYTES
+        + 8 // expires_at_ms
+        + 8 // version
+        + MAX_KYE_BYTES
+        + MAX_VALUE_BYTES;
 
     pub(super) fn new(key: Key, value: Value) -> Result<Self> {
         let header = Header {
@@ -68,6 +105,8 @@ impl Entry {
             value_bytes: value.len(),
             timestamp_ms: Utc::now().timestamp_millis(),
             state: State::Active,
+            expires_at_ms: None,
+            version: 0,
             crc_checksum: None,
         };
 
@@ -82,12 +121,118 @@ impl Entry {
         Ok(entry)
     }
 
+    // Construct the entry which deletes the key.
+    // version is the one of the deleted entry, so that the greatest version is rebuilt from the table file.
+    pub(super) fn tombstone(key: String, version: u64) -> Self {
+        let header = Header {
+            key_bytes: key.len(),
+            value_bytes: 0,
+            timestamp_ms: Utc::now().timestamp_millis(),
+            state: State::Deleted,
+            expires_at_ms: None,
+            version,
+            crc_checksum: None,
+        };
+
+        let mut entry = Self {
+            header,
+            body: Body { key, value: None },
+        };
+        entry.header.crc_checksum = Some(entry.calc_crc_checksum());
+
+        entry
+    }
+
+    // Construct the entry whose value is stored in the blob.
+    pub(super) fn blob_reference(key: Key, blob: &BlobRef) -> Self {
+        let value = blob.encode();
+        let header = Header {
+            key_bytes: key.len(),
+            value_bytes: value.len(),
+            timestamp_ms: Utc::now().timestamp_millis(),
+            state: State::Blob,
+            expires_at_ms: None,
+            version: 0,
+            crc_checksum: None,
+        };
+
+        let mut entry = Self {
+            header,
+            body: Body {
+                key: key.into_string(),
+                value: Some(value),
+            },
+        };
+        entry.header.crc_checksum = Some(entry.calc_crc_checksum());
+
+        entry
+    }
+
+    // Renew the timestamp to write the entry again.
+    pub(super) fn touch(mut self) -> Self {
+        self.header.timestamp_ms = Utc::now().timestamp_millis();
+        self.header.crc_checksum = Some(self.calc_crc_checksum());
+        self
+    }
+
+    // Set the expiration timestamp in milliseconds since epoch.
+    pub(super) fn with_expires_at(mut self, expires_at_ms: Option<i64>) -> Self {
+        self.header.expires_at_ms = expires_at_ms;
+        self.header.crc_checksum = Some(self.calc_crc_checksum());
+        self
+    }
+
+    pub(super) fn with_version(mut self, version: u64) -> Self {
+        self.header.version = version;
+        self.header.crc_checksum = Some(self.calc_crc_checksum());
+        self
+    }
+
+    // Construct the marker entry followed by the entries of the batch.
+    // the batch is effective only if all of its entries are read.
+    pub(super) fn batch_marker(entries: usize) -> Self {
+        let value = (entries as u64).to_be_bytes();
+        let header = Header {
+            key_bytes: 0,
+            value_bytes: value.len(),
+            timestamp_ms: Utc::now().timestamp_millis(),
+            state: State::Batch,
+            expires_at_ms: None,
+            version: 0,
+            crc_checksum: None,
+        };
+
+        let mut entry = Self {
+            header,
+            body: Body {
+                key: String::new(),
+                value: Some(Box::new(value)),
+            },
+        };
+        entry.header.crc_checksum = Some(entry.calc_crc_checksum());
+
+        entry
+    }
+
+    // Return the number of entries in the batch if the entry is the batch marker.
+    pub(super) fn batch_len(&self) -> Option<usize> {
+        if self.header.state != State::Batch {
+            return None;
+        }
+        let value = self.body.value.as_deref()?;
+        let entries = <[u8; 8]>::try_from(value).ok()?;
+        Some(u64::from_be_bytes(entries) as usize)
+    }
+
+    #[cfg(test)]
     pub(super) fn mark_deleted(&mut self) -> Option<Box<[u8]>> {
         let value = self.body.value.take();
 
         self.header.value_bytes = 0;
         self.header.timestamp_ms = Utc::now().timestamp_millis();
         self.header.state = State::Deleted;
+        self.header.expires_at_ms = None;
+        self.header.version = 0;
         self.header.crc_checksum = Some(self.calc_crc_checksum());
 
         value
@@ -100,12 +245,22 @@ impl Entry {
         // Assuming that the validation is done at the timeout entry construction.
         debug_assert!(self.assert());
 
-        let mut n: usize = Entry::HEADER_BYTES;
+        let mut n: usize = self.header.len();
         // Header
         writer.write_u64(self.header.key_bytes as u64).await?;
         writer.write_u64(self.header.value_bytes as u64).await?;
         writer.write_i64(self.header.timestamp_ms).await?;
-        writer.write_u8(self.header.state as u8).await?;
+        writer.write_u8(self.header.state_byte()).await?;
+        match self.header.format() {
+            Format::V1 => (),
+            Format::V2 => writer.write_i64(self.header.expires_at_ms.unwrap()).await?,
+            Format::V3 => {
+                writer
+                    .write_i64(self.header.expires_at_ms.unwrap_or(0))
+                    .await?;
+                writer.write_u64(self.header.version).await?;
+            }
+        }
         writer
             .write_u32(self.header.crc_checksum.unwrap_or(0))
             .await?;
@@ -135,25 +290,42 @@ impl Entry {
         let key_bytes = reader.read_u64().await? as usize;
         let value_bytes = reader.read_u64().await? as usize;
         let timestamp_ms = reader.read_i64().await?;
-        let state = State::from(reader.read_u8().await?);
+        let (format, state) = Header::parse_state_byte(reader.read_u8().await?)?;
+        let (expires_at_ms, version) = match format {
+            Format::V1 => (None, 0),
+            Format::V2 => (Some(reader.read_i64().await?), 0),
+            Format::V3 => {
+                let expires_at_ms = reader.read_i64().await?;
+                let version = reader.read_u64().await?;
+                (Some(expires_at_ms).filter(|ms| *ms != 0), version)
+            }
+        };
         let crc_checksum = reader
             .read_u32()
             .await
             .map(|n| if n == 0 { None } else { Some(n) })?;
 
+        // Bit flipped length must not cause huge allocation.
+        if key_bytes > MAX_KYE_BYTES || value_bytes > MAX_VALUE_BYTES {
+            return Err(ErrorKind::Corruption(format!(
+                "invalid entry length. key: {} value: {}",
+                key_bytes, value_bytes
+            ))
+            .into());
+        }
+
         let header = Header {
             key_bytes,
             value_bytes,
             timestamp_ms,
             state,
+            expires_at_ms,
+            version,
             crc_checksum,
         };
 
-        let mut buf = Vec::with_capacity(header.body_len());
-        reader
-            .take(header.body_len() as u64)
-            .read_to_end(buf.as_mut())
-            .await?;
+        let mut buf = vec![0; header.body_len()];
+        reader.read_exact(&mut buf).await?;
 
         let value = buf.split_off(header.key_bytes);
 
@@ -172,11 +344,60 @@ impl Entry {
             body: Body { key, value },
         };
 
+        entry.verify_crc_checksum()?;
+
         Ok((entry.encoded_len(), entry))
     }
 
+    // Read the header from reader and return the encoded length of the entry.
+    pub(super) async fn read_encoded_len<R: AsyncReadExt + Unpin>(mut reader: R) -> Result<u64> {
+        let key_bytes = reader.read_u64().await?;
+        let value_bytes = reader.read_u64().await?;
+        let _timestamp_ms = reader.read_i64().await?;
+        let (format, _) = Header::parse_state_byte(reader.read_u8().await?)?;
+
+        Ok((Header::len_of(format) as u64)
+            .saturating_add(key_bytes)
+            .saturating_add(value_bytes))
+    }
+
     pub(super) fn is_active(&self) -> bool {
-        self.header.state == State::Active
+        matches!(self.header.state, State::Active | State::Blob)
+    }
+
+    // Return the reference to the blob if the value is stored in the blob.
+    pub(super) fn blob(&self) -> Option<BlobRef> {
+        if self.header.state != State::Blob {
+            return None;
+        }
+        BlobRef::decode(self.body.value.as_deref()?)
+    }
+
+    // Return the reference to the blob from the encoded entry without decoding it.
+    // used by compaction which copies the encoded entries as is.
+    pub(super) fn blob_of_encoded(buf: &[u8]) -> Option<BlobRef> {
+        // state byte follows key_bytes, value_bytes and timestamp_ms.
+        let state = *buf.get(24)?;
+        if State::from(state & 0x0f) != State::Blob {
+            return None;
+        }
+        BlobRef::decode(buf.get(buf.len().checked_sub(BlobRef::ENCODED_BYTES)?..)?)
+    }
+
+    pub(super) fn expires_at_ms(&self) -> Option<i64> {
+        self.header.expires_at_ms
+    }
+
+    pub(super) fn version(&self) -> u64 {
+        self.header.version
+    }
+
+    // Value stored in the blob is not returned.
+    pub(super) fn value(&self) -> Option<&[u8]> {
+        match self.header.state {
+            State::Blob => None,
+            _ => self.body.value.as_deref(),
+        }
     }
 
     pub(super) fn take_key(self) -> String {
@@ -199,7 +420,21 @@ impl Entry {
             .as_ref(),
         );
 
-        h.update((self.header.state as u8).to_be_bytes().as_ref());
+        h.update(self.header.state_byte().to_be_bytes().as_ref());
+        match self.header.format() {
+            Format::V1 => (),
+            Format::V2 => h.update(self.header.expires_at_ms.unwrap().to_be_bytes().as_ref()),
+            Format::V3 => {
+                h.update(
+                    self.header
+                        .expires_at_ms
+                        .unwrap_or(0)
+                        .to_be_bytes()
+                        .as_ref(),
+                );
+                h.update(self.header.version.to_be_bytes().as_ref());
+            }
+        }
         h.update(self.body.key.as_bytes());
         if let Some(value) = &self.body.value {
             h.update(value);
@@ -207,6 +442,23 @@ impl Entry {
         h.finalize()
     }
 
+    // Make sure that decoded entry matches its checksum.
+    // entry without checksum is not verified.
+    fn verify_crc_checksum(&self) -> Result<()> {
+        match self.header.crc_checksum {
+            Some(crc_checksum) if crc_checksum != self.calc_crc_checksum() => Err(
+                ErrorKind::Corruption(format!("crc checksum mismatch. key: {}", self.body.key))
+                    .into(),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    // Whether the entry has the checksum, which is verified on decode.
+    pub(super) fn has_checksum(&self) -> bool {
+        self.header.crc_checksum.is_some()
+    }
+
     // Assert entry data consistency.
     fn assert(&self) -> bool {
         self.header.key_bytes == self.body.key.len()
@@ -215,8 +467,8 @@ impl Entry {
     }
 
     // Return assuming encoded bytes length.
-    fn encoded_len(&self) -> usize {
-        Entry::HEADER_BYTES + self.body.len()
+    pub(super) fn encoded_len(&self) -> usize {
+        self.header.len() + self.body.len()
     }
 }
 
@@ -225,6 +477,8 @@ impl From<u8> for State {
         match n {
             1 => State::Active,
             2 => State::Deleted,
+            3 => State::Batch,
+            4 => State::Blob,
             _ => State::Invalid,
         }
     }
@@ -234,6 +488,45 @@ impl Header {
     fn body_len(&self) -> usize {
         self.key_bytes + self.value_bytes
     }
+
+    // Entry is written in the oldest format which can represent it.
+    fn format(&self) -> Format {
+        if self.version != 0 {
+            Format::V3
+        } else if self.expires_at_ms.is_some() {
+            Format::V2
+        } else {
+            Format::V1
+        }
+    }
+
+    fn len(&self) -> usize {
+        Header::len_of(self.format())
+    }
+
+    fn len_of(format: Format) -> usize {
+        match format {
+            Format::V1 => Entry::HEADER_BYTES,
+            Format::V2 => Entry::HEADER_BYTES + 8, // expires_at_ms
+            Format::V3 => Entry::HEADER_BYTES + 8 + 8, // expires_at_ms, version
+        }
+    }
+
+    fn state_byte(&self) -> u8 {
+        (self.format() as u8) << 4 | self.state as u8
+    }
+
+    fn parse_state_byte(b: u8) -> Result<(Format, State)> {
+        let format = match b >> 4 {
+            0 => Format::V1,
+            1 => Format::V2,
+            2 => Format::V3,
+            n => {
+                return Err(ErrorKind::Corruption(format!("unsupported entry format {}", n)).into())
+            }
+        };
+        Ok((format, State::from(b & 0x0f)))
+    }
 }
 
 impl Body {
@@ -246,6 +539,29 @@ impl Body {
     }
 }
 
+impl From<Entry> for EntryDump {
+    fn from(e: Entry) -> EntryDump {
+        let timestamp_ns = e.header.timestamp_ms;
+        let expires_at_ms = e.header.expires_at_ms;
+        let version = e.header.version;
+        let is_deleted = matches!(e.header.state, State::Deleted);
+        let key = e.body.key;
+        let value = match e.body.value {
+            Some(v) => v.into_vec(),
+            None => Vec::new(),
+        };
+
+        EntryDump {
+            timestamp_ns,
+            expires_at_ms,
+            version,
+            is_deleted,
+            key,
+            value,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -306,6 +622,134 @@ mod tests {
         })
     }
 
+    #[test]
+    fn encode_decode_with_expiry() {
+        tokio_test::block_on(async move {
+            let entry = try_from_key_value(("key", "hello"))
+                .unwrap()
+                .with_expires_at(Some(1000));
+
+            let mut buf = Cursor::new(Vec::new());
+            let written = entry.encode_to(&mut buf).await.unwrap();
+            assert_eq!(written, entry.encoded_len());
+            assert_eq!(
+                written,
+                try_from_key_value(("key", "hello")).unwrap().encoded_len() + 8
+            );
+
+            buf.set_position(0);
+            assert_eq!(
+                Entry::read_encoded_len(&mut buf).await.unwrap(),
+                written as u64
+            );
+
+            buf.set_position(0);
+            let (n, decoded) = Entry::decode_from(&mut buf).await.unwrap();
+
+            assert_eq!(n, written);
+            assert_eq!(entry, decoded);
+            assert!(decoded.assert());
+            assert_eq!(Some(1000), decoded.expires_at_ms());
+
+            // Tombstone does not expire.
+            let mut decoded = decoded;
+            decoded.mark_deleted();
+            assert_eq!(decoded.expires_at_ms(), None);
+        })
+    }
+
+    #[test]
+    fn encode_decode_with_version() {
+        tokio_test::block_on(async move {
+            for expires_at_ms in [None, Some(1000)] {
+                let entry = try_from_key_value(("key", "hello"))
+                    .unwrap()
+                    .with_expires_at(expires_at_ms)
+                    .with_version(3);
+
+                let mut buf = Cursor::new(Vec::new());
+                let written = entry.encode_to(&mut buf).await.unwrap();
+                assert_eq!(written, entry.encoded_len());
+
+                buf.set_position(0);
+                assert_eq!(
+                    Entry::read_encoded_len(&mut buf).await.unwrap(),
+                    written as u64
+                );
+
+                buf.set_position(0);
+                let (_, decoded) = Entry::decode_from(&mut buf).await.unwrap();
+                assert_eq!(entry, decoded);
+                assert_eq!(decoded.version(), 3);
+                assert_eq!(decoded.expires_at_ms(), expires_at_ms);
+            }
+        })
+    }
+
+    #[test]
+    fn batch_marker() {
+        tokio_test::block_on(async move {
+            let marker = Entry::batch_marker(3);
+            assert_eq!(marker.batch_len(), Some(3));
+            assert!(!marker.is_active());
+
+            let mut buf = Cursor::new(Vec::new());
+            marker.encode_to(&mut buf).await.unwrap();
+
+            buf.set_position(0);
+            let (_, decoded) = Entry::decode_from(&mut buf).await.unwrap();
+            assert_eq!(decoded.batch_len(), Some(3));
+
+            let entry = try_from_key_value(("key", "hello")).unwrap();
+            assert_eq!(entry.batch_len(), None);
+        })
+    }
+
+    #[test]
+    fn blob() {
+        tokio_test::block_on(async move {
+            let blob = BlobRef::decode(&[1; BlobRef::ENCODED_BYTES]).unwrap();
+            let entry = Entry::blob_reference(Key::new("key").unwrap(), &blob)
+                .with_expires_at(Some(1000))
+                .with_version(2);
+            assert!(entry.is_active());
+            assert_eq!(entry.blob(), Some(blob));
+            assert_eq!(entry.value(), None);
+
+            let mut buf = Cursor::new(Vec::new());
+            entry.encode_to(&mut buf).await.unwrap();
+            assert_eq!(Entry::blob_of_encoded(buf.get_ref()), Some(blob));
+
+            buf.set_position(0);
+            let (_, decoded) = Entry::decode_from(&mut buf).await.unwrap();
+            assert_eq!(entry, decoded);
+
+            let entry = try_from_key_value(("key", "hello")).unwrap();
+            let mut buf = Cursor::new(Vec::new());
+            entry.encode_to(&mut buf).await.unwrap();
+            assert_eq!(entry.blob(), None);
+            assert_eq!(Entry::blob_of_encoded(buf.get_ref()), None);
+        })
+    }
+
+    #[test]
+    fn decode_corrupted() {
+        tokio_test::block_on(async move {
+            let entry = try_from_key_value(("key", "hello")).unwrap();
+
+            let mut buf = Cursor::new(Vec::new());
+            entry.encode_to(&mut buf).await.unwrap();
+
+            // Flip the last byte of value.
+            let last = buf.get_ref().len() - 1;
+            buf.get_mut()[last] ^= 0x01;
+
+            buf.set_position(0);
+            let err = Entry::decode_from(&mut buf).await.unwrap_err();
+            assert!(err.is_corruption());
+        })
+    }
+
     #[test]
     fn construct_index() {
         tokio_test::block_on(async move {
@@ -332,22 +776,3 @@ mod tests {
         })
     }
 }
-
-impl From<Entry> for EntryDump {
-    fn from(e: Entry) -> EntryDump {
-        let timestamp_ns = e.header.timestamp_ms;
-        let is_deleted = matches!(e.header.state, State::Deleted);
-        let key = e.body.key;
-        let value = match e.body.value {
-            Some(v) => v.into_vec(),
-            None => Vec::new(),
-        };
-
-        EntryDump {
-            timestamp_ns,
-            is_deleted,
-            key,
-            value,
-        }
-    }
-}
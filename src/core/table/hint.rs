@@ -0,0 +1,358 @@
+use std::io::Cursor;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use bytes::{Buf, BufMut};
+use tokio::fs;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt, SeekFrom};
+
+use crate::common::{debug, warn, ErrorKind, Result};
+use crate::core::table::entry::Entry;
+use crate::core::table::index::{Index, Location};
+use crate::core::table::suffixed_path;
+
+// Hint file holds the index of the table file so that the index can be rebuilt
+// without decoding every entry at startup.
+//
+// Format
+// | magic(8) | version(1) | data_len(8) | file_id(8) | max_version(8) | entries(8) | entry... | crc_checksum(4) |
+//
+// entry
+// | key_bytes(8) | offset(8) | len(8) | state(1) | expires_at_ms(8) | version(8) | key |
+//
+// expires_at_ms is 0 if the entry does not expire.
+//
+// max_version is the greatest version the table has assigned.
+// it outlives the tombstones reclaimed by compaction, so that the versions of the deleted keys
+// are not reused when they are created again.
+//
+// data_len is the table file length covered by the hint.
+// entries appended after data_len are read from table file.
+//
+// file_id is the inode of the table file the hint was written for.
+// compaction swaps the table file by rename, so the hint of the replaced file is not applied.
+// the entry at the last hinted offset is decoded at load as well, so that the hint is not applied
+// to the file rewritten in place either, without reading the whole file.
+pub(super) struct Hint {
+    pub(super) index: Index,
+    pub(super) data_len: u64,
+}
+
+impl Hint {
+    const MAGIC: &'static [u8; 8] = b"KVSDHINT";
+    const VERSION: u8 = 6;
+    const HEADER_BYTES: usize = 8 // magic
+        + 1 // version
+        + 8 // data_len
+        + 8 // file_id
+        + 8 // max_version
+        + 8 // entries
+    ;
+    const ENTRY_HEADER_BYTES: usize = 8 // key_bytes
+        + 8 // offset
+        + 8 // len
+        + 1 // state
+        + 8 // expires_at_ms
+        + 8 // version
+    ;
+    // Only entries referenced by index are written, so state is always active.
+    const STATE_ACTIVE: u8 = 1;
+
+    // Return hint file path of given table file. (e.g. default.kvsd.hint)
+    pub(super) fn path(table_path: &Path) -> PathBuf {
+        suffixed_path(table_path, ".hint")
+    }
+
+    // Load hint of table file.
+    // return None if hint is missing or is not valid for current table file.
+    pub(super) async fn load(table_path: &Path, table_len: u64) -> Option<Hint> {
+        let path = Hint::path(table_path);
+        let buf = match fs::read(&path).await {
+            Ok(buf) => buf,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                warn!(path = %path.display(), "Read hint {}", err);
+                return None;
+            }
+        };
+
+        let (hint, file_id) = match Hint::decode(&buf) {
+            Ok((hint, _)) if hint.data_len > table_len => {
+                warn!(
+                    path = %path.display(),
+                    data_len = hint.data_len,
+                    table_len,
+                    "Stale hint"
+                );
+                return None;
+            }
+            Ok(decoded) => decoded,
+            Err(err) => {
+                warn!(path = %path.display(), "Invalid hint {}", err);
+                return None;
+            }
+        };
+
+        match Hint::matches(table_path, &hint.index, file_id).await {
+            Ok(true) => {
+                debug!(path = %path.display(), data_len = hint.data_len, "Load hint");
+                Some(hint)
+            }
+            Ok(false) => {
+                warn!(path = %path.display(), "Hint does not match table file");
+                None
+            }
+            Err(err) => {
+                warn!(path = %path.display(), "Read table file for hint {}", err);
+                None
+            }
+        }
+    }
+
+    // Write the hint of index covering table file up to data_len.
+    // the hint is written to temporary file then renamed, so that partially written hint is never loaded.
+    pub(super) async fn write(table_path: &Path, index: &Index, data_len: u64) -> Result<()> {
+        Hint::write_for(table_path, table_path, index, data_len).await
+    }
+
+    // Write the hint of the file at data_path which is renamed to table_path afterwards.
+    // rename keeps the inode, so the hint is valid for the file once it is renamed.
+    pub(super) async fn write_for(
+        table_path: &Path,
+        data_path: &Path,
+        index: &Index,
+        data_len: u64,
+    ) -> Result<()> {
+        let path = Hint::path(table_path);
+        let tmp_path = suffixed_path(&path, ".tmp");
+
+        let file_id = Hint::file_id(data_path).await?;
+        let buf = Hint::encode(index, data_len, file_id);
+
+        let mut f = fs::File::create(&tmp_path).await?;
+        f.write_all(&buf).await?;
+        f.sync_all().await?;
+        drop(f);
+
+        fs::rename(&tmp_path, &path).await?;
+
+        debug!(path = %path.display(), data_len, "Write hint");
+
+        Ok(())
+    }
+
+    async fn file_id(table_path: &Path) -> Result<u64> {
+        Ok(fs::metadata(table_path).await?.ino())
+    }
+
+    // Check that the hint was written for the table file.
+    // the entry at the last hinted offset is expected to be the one the index refers to.
+    async fn matches(table_path: &Path, index: &Index, file_id: u64) -> Result<bool> {
+        if Hint::file_id(table_path).await? != file_id {
+            return Ok(false);
+        }
+        let (key, location) = match index.last_location() {
+            Some(last) => last,
+            None => return Ok(true),
+        };
+
+        let mut file = fs::File::open(table_path).await?;
+        file.seek(SeekFrom::Start(location.offset as u64)).await?;
+        match Entry::decode_from(&mut file).await {
+            Ok((n, entry)) => Ok(n == location.len && entry.take_key() == key),
+            // Entry which can not be decoded is not the one the hint was written for.
+            Err(_) => Ok(false),
+        }
+    }
+
+    fn encode(index: &Index, data_len: u64, file_id: u64) -> Vec<u8> {
+        let locations = index.snapshot();
+
+        let mut buf = Vec::with_capacity(
+            Hint::HEADER_BYTES
+                + locations
+                    .iter()
+                    .map(|(key, _)| Hint::ENTRY_HEADER_BYTES + key.len())
+                    .sum::<usize>()
+                + 4,
+        );
+
+        buf.put_slice(Hint::MAGIC);
+        buf.put_u8(Hint::VERSION);
+        buf.put_u64(data_len);
+        buf.put_u64(file_id);
+        buf.put_u64(index.max_version());
+        buf.put_u64(locations.len() as u64);
+
+        for (key, location) in locations {
+            buf.put_u64(key.len() as u64);
+            buf.put_u64(location.offset as u64);
+            buf.put_u64(location.len as u64);
+            buf.put_u8(Hint::STATE_ACTIVE);
+            buf.put_i64(location.expires_at_ms.unwrap_or(0));
+            buf.put_u64(location.version);
+            buf.put_slice(key.as_bytes());
+        }
+
+        let crc_checksum = crc32fast::hash(&buf);
+        buf.put_u32(crc_checksum);
+
+        buf
+    }
+
+    // Return the hint and the id of the table file it was written for.
+    fn decode(buf: &[u8]) -> Result<(Hint, u64)> {
+        let invalid = |description: &str| ErrorKind::EntryDecode {
+            description: format!("hint: {}", description),
+        };
+
+        if buf.len() < Hint::HEADER_BYTES + 4 {
+            return Err(invalid("too short").into());
+        }
+        let (body, checksum) = buf.split_at(buf.len() - 4);
+        if crc32fast::hash(body) != u32::from_be_bytes(checksum.try_into().unwrap()) {
+            return Err(invalid("crc checksum mismatch").into());
+        }
+
+        let mut src = Cursor::new(body);
+        let mut magic = [0u8; 8];
+        src.copy_to_slice(&mut magic);
+        if &magic != Hint::MAGIC {
+            return Err(invalid("magic mismatch").into());
+        }
+        let version = src.get_u8();
+        if version != Hint::VERSION {
+            return Err(invalid(&format!("unsupported version {}", version)).into());
+        }
+        let data_len = src.get_u64();
+        let file_id = src.get_u64();
+        let max_version = src.get_u64();
+        let entries = src.get_u64();
+
+        let mut index = Index::new();
+        index.raise_max_version(max_version);
+        for _ in 0..entries {
+            if src.remaining() < Hint::ENTRY_HEADER_BYTES {
+                return Err(invalid("unexpected end of entries").into());
+            }
+            let key_bytes = src.get_u64() as usize;
+            let offset = src.get_u64() as usize;
+            let len = src.get_u64() as usize;
+            let state = src.get_u8();
+            if state != Hint::STATE_ACTIVE {
+                return Err(invalid(&format!("unexpected state {}", state)).into());
+            }
+            let location = Location {
+                offset,
+                len,
+                expires_at_ms: Some(src.get_i64()).filter(|ms| *ms != 0),
+                version: src.get_u64(),
+            };
+            if (location.offset as u64).saturating_add(location.len as u64) > data_len {
+                return Err(invalid("entry exceeds data length").into());
+            }
+            if src.remaining() < key_bytes {
+                return Err(invalid("unexpected end of key").into());
+            }
+            let mut key = vec![0u8; key_bytes];
+            src.copy_to_slice(&mut key);
+            let key = String::from_utf8(key).map_err(|err| invalid(&err.to_string()))?;
+
+            index.add(key, location);
+        }
+
+        if src.has_remaining() {
+            return Err(invalid("unexpected trailing bytes").into());
+        }
+
+        Ok((Hint { index, data_len }, file_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{Key, Value};
+
+    #[test]
+    fn encode_decode() {
+        let mut index = Index::new();
+        index.add(
+            "key1".into(),
+            Location {
+                offset: 0,
+                len: 40,
+                expires_at_ms: None,
+                version: 1,
+            },
+        );
+        index.add(
+            "key2".into(),
+            Location {
+                offset: 40,
+                len: 50,
+                expires_at_ms: Some(1000),
+                version: 3,
+            },
+        );
+        // Version of the deleted key.
+        index.raise_max_version(5);
+
+        let buf = Hint::encode(&index, 100, 7);
+        let (hint, file_id) = Hint::decode(&buf).unwrap();
+
+        assert_eq!((hint.data_len, file_id), (100, 7));
+        assert_eq!(hint.index.snapshot(), index.snapshot());
+        assert_eq!(hint.index.live_bytes(), 90);
+        assert_eq!(hint.index.next_version("key2"), 4);
+        assert_eq!(hint.index.next_version("key3"), 6);
+
+        // Corrupted hint is rejected.
+        let mut corrupted = buf.clone();
+        corrupted[Hint::HEADER_BYTES] ^= 0xff;
+        assert!(Hint::decode(&corrupted).is_err());
+    }
+
+    async fn encoded(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for (key, value) in entries {
+            Entry::new(
+                Key::new(*key).unwrap(),
+                Value::new(value.as_bytes()).unwrap(),
+            )
+            .unwrap()
+            .encode_to(&mut buf)
+            .await
+            .unwrap();
+        }
+        buf
+    }
+
+    #[tokio::test]
+    async fn table_file_identity() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("default.kvsd");
+        let buf = encoded(&[("key1", "value1"), ("key2", "value2")]).await;
+        let index = Index::from_reader(buf.as_slice()).await.unwrap();
+
+        std::fs::write(&path, &buf).unwrap();
+        Hint::write(&path, &index, buf.len() as u64).await.unwrap();
+        let hint = Hint::load(&path, buf.len() as u64).await.unwrap();
+        assert_eq!(hint.data_len, buf.len() as u64);
+
+        // Hint is not applied to the file rewritten in place since it was written.
+        let rewritten = encoded(&[("key1", "value1"), ("key3", "value3")]).await;
+        std::fs::write(&path, &rewritten).unwrap();
+        assert!(Hint::load(&path, rewritten.len() as u64).await.is_none());
+        std::fs::write(&path, &buf[..buf.len() - 1]).unwrap();
+        assert!(Hint::load(&path, buf.len() as u64 - 1).await.is_none());
+
+        // Nor to the file swapped by rename, even if it has the same entries.
+        std::fs::write(&path, &buf).unwrap();
+        Hint::write(&path, &index, buf.len() as u64).await.unwrap();
+        let swapped = dir.path().join("default.kvsd.compact");
+        std::fs::write(&swapped, &buf).unwrap();
+        std::fs::rename(&swapped, &path).unwrap();
+        assert!(Hint::load(&path, buf.len() as u64).await.is_none());
+    }
+}
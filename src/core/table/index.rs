@@ -1,60 +1,253 @@
 use std::borrow::Borrow;
-use std::collections::HashMap;
-use std::hash::Hash;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
 
 use tokio::io::AsyncReadExt;
 
-use crate::common::Result;
+use crate::common::{ErrorKind, Result};
 use crate::core::table::entry::Entry;
 
 #[derive(Debug)]
 pub(super) struct Index {
-    // key to file offset mapping.
-    entry_offsets: HashMap<String, usize>,
+    // key to file location mapping ordered by key.
+    entry_locations: BTreeMap<String, Location>,
+    // greatest version of the entries applied, including the deleted ones.
+    // the key created again is versioned after it, so that its deleted versions are not reused.
+    max_version: u64,
+    // sum of encoded bytes of the entries referenced by index.
+    live_bytes: usize,
+    // keys with expiration ordered by expiration timestamp.
+    expirations: BTreeSet<(i64, String)>,
+}
+
+// Location of the encoded entry in table file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Location {
+    pub(super) offset: usize,
+    pub(super) len: usize,
+    // expiration timestamp of the entry in milliseconds since epoch.
+    pub(super) expires_at_ms: Option<i64>,
+    // version of the entry.
+    pub(super) version: u64,
+}
+
+impl Location {
+    pub(super) fn is_expired(&self, now_ms: i64) -> bool {
+        self.expires_at_ms
+            .is_some_and(|expires_at_ms| expires_at_ms <= now_ms)
+    }
+}
+
+// Batch whose entries are being read.
+struct PendingBatch {
+    // offset of the batch marker.
+    offset: usize,
+    // number of entries not read yet.
+    remaining: usize,
+    // entries read with their offset and length.
+    entries: Vec<(Entry, usize, usize)>,
 }
 
 impl Index {
-    pub(super) async fn from_reader<R: AsyncReadExt + Unpin>(mut reader: R) -> Result<Self> {
-        let mut entries = HashMap::new();
-        let mut pos: usize = 0;
+    #[cfg(test)]
+    pub(super) async fn from_reader<R: AsyncReadExt + Unpin>(reader: R) -> Result<Self> {
+        let mut index = Index::new();
+        index.read_from(reader, 0).await.1?;
+        Ok(index)
+    }
+
+    // Apply the entries read from reader until EOF.
+    // pos is the offset of the reader in table file.
+    // return the end offset of the last entry successfully read and the error that stopped reading.
+    // entries of the batch are applied only after all of them are read, so the batch which is not
+    // completely written ends the valid entries at its marker.
+    pub(super) async fn read_from<R: AsyncReadExt + Unpin>(
+        &mut self,
+        mut reader: R,
+        mut pos: usize,
+    ) -> (usize, Result<()>) {
+        let mut batch: Option<PendingBatch> = None;
+
         loop {
+            let valid_len = batch.as_ref().map_or(pos, |batch| batch.offset);
+
             match Entry::decode_from(&mut reader).await {
                 Ok((n, entry)) => {
-                    // Ignore deleted entry
-                    if entry.is_active() {
-                        entries.insert(entry.take_key(), pos);
-                    } else {
-                        // Remove as there should be entry left before deleted
-                        entries.remove(entry.take_key().as_str());
+                    match (entry.batch_len(), batch.as_mut()) {
+                        (Some(_), Some(_)) => {
+                            let err = ErrorKind::Corruption(format!(
+                                "batch marker at offset {} in the middle of batch",
+                                pos
+                            ));
+                            return (valid_len, Err(err.into()));
+                        }
+                        (Some(remaining), None) => {
+                            batch = Some(PendingBatch {
+                                offset: pos,
+                                remaining,
+                                entries: Vec::new(),
+                            })
+                        }
+                        (None, Some(batch)) => {
+                            batch.entries.push((entry, pos, n));
+                            batch.remaining -= 1;
+                        }
+                        (None, None) => self.apply(entry, pos, n),
                     }
                     pos = pos.checked_add(n).unwrap();
+
+                    if batch.as_ref().is_some_and(|batch| batch.remaining == 0) {
+                        for (entry, offset, n) in batch.take().unwrap().entries {
+                            self.apply(entry, offset, n);
+                        }
+                    }
                 }
                 Err(err) if err.is_eof() => {
-                    return Ok(Index::new(entries));
+                    return (valid_len, Ok(()));
                 }
                 Err(err) => {
-                    return Err(err);
+                    return (valid_len, Err(err));
                 }
             }
         }
     }
-    pub(super) fn add(&mut self, key: String, offset: usize) -> Option<usize> {
-        self.entry_offsets.insert(key, offset)
+
+    pub(super) fn new() -> Self {
+        Self {
+            entry_locations: BTreeMap::new(),
+            max_version: 0,
+            live_bytes: 0,
+            expirations: BTreeSet::new(),
+        }
+    }
+
+    // Reflect the entry read from the table file at given offset.
+    pub(super) fn apply(&mut self, entry: Entry, offset: usize, len: usize) {
+        self.raise_max_version(entry.version());
+        // Ignore deleted entry
+        if entry.is_active() {
+            let location = Location {
+                offset,
+                len,
+                expires_at_ms: entry.expires_at_ms(),
+                version: entry.version(),
+            };
+            self.add(entry.take_key(), location);
+        } else {
+            // Remove as there should be entry left before deleted
+            self.remove(entry.take_key().as_str());
+        }
     }
 
-    pub(super) fn remove<Q>(&mut self, k: &Q) -> Option<usize>
+    pub(super) fn add(&mut self, key: String, location: Location) -> Option<Location> {
+        self.raise_max_version(location.version);
+        self.live_bytes += location.len;
+        if let Some(expires_at_ms) = location.expires_at_ms {
+            self.expirations.insert((expires_at_ms, key.clone()));
+        }
+        let prev = self.entry_locations.insert(key.clone(), location);
+        if let Some(prev) = prev {
+            self.live_bytes -= prev.len;
+            self.remove_expiration(key, prev);
+        }
+        prev
+    }
+
+    pub(super) fn remove<Q>(&mut self, k: &Q) -> Option<Location>
     where
         String: Borrow<Q>,
-        Q: Hash + Eq + ?Sized,
+        Q: Ord + ?Sized,
     {
-        self.entry_offsets.remove(k)
+        let removed = self.entry_locations.remove_entry(k);
+        match removed {
+            Some((key, removed)) => {
+                self.live_bytes -= removed.len;
+                self.remove_expiration(key, removed);
+                Some(removed)
+            }
+            None => None,
+        }
+    }
+
+    fn remove_expiration(&mut self, key: String, location: Location) {
+        if let Some(expires_at_ms) = location.expires_at_ms {
+            self.expirations.remove(&(expires_at_ms, key));
+        }
+    }
+
+    pub(super) fn lookup(&self, key: &str) -> Option<Location> {
+        self.entry_locations.get(key).copied()
     }
 
+    // Return the version of the key, including the expired one. 0 if the key does not exist.
+    pub(super) fn version(&self, key: &str) -> u64 {
+        self.entry_locations
+            .get(key)
+            .map_or(0, |location| location.version)
+    }
+
+    // Return the version of the key written next.
+    // the absent key continues from the greatest version, as it may have been deleted.
+    pub(super) fn next_version(&self, key: &str) -> u64 {
+        match self.entry_locations.get(key) {
+            Some(location) => location.version + 1,
+            None => self.max_version + 1,
+        }
+    }
+
+    pub(super) fn max_version(&self) -> u64 {
+        self.max_version
+    }
+
+    pub(super) fn raise_max_version(&mut self, version: u64) {
+        self.max_version = self.max_version.max(version);
+    }
+
+    #[cfg(test)]
     pub(super) fn lookup_offset(&self, key: &str) -> Option<usize> {
-        self.entry_offsets.get(key).cloned()
+        self.lookup(key).map(|location| location.offset)
+    }
+
+    // Return the keys and locations from the lower bound in key order.
+    pub(super) fn range<'a>(
+        &'a self,
+        from: Bound<&'a str>,
+    ) -> impl Iterator<Item = (&'a str, Location)> + 'a {
+        self.entry_locations
+            .range::<str, _>((from, Bound::Unbounded))
+            .map(|(key, location)| (key.as_str(), *location))
+    }
+
+    // Return the keys expired at now_ms in expiration order.
+    pub(super) fn expired(&self, now_ms: i64, limit: usize) -> Vec<String> {
+        self.expirations
+            .iter()
+            .take_while(|(expires_at_ms, _)| *expires_at_ms <= now_ms)
+            .take(limit)
+            .map(|(_, key)| key.clone())
+            .collect()
+    }
+
+    pub(super) fn live_bytes(&self) -> usize {
+        self.live_bytes
+    }
+
+    // Return the location at the greatest offset.
+    pub(super) fn last_location(&self) -> Option<(String, Location)> {
+        self.entry_locations
+            .iter()
+            .max_by_key(|(_, location)| location.offset)
+            .map(|(key, location)| (key.clone(), *location))
     }
 
-    fn new(entry_offsets: HashMap<String, usize>) -> Self {
-        Self { entry_offsets }
+    // Return the key locations ordered by offset.
+    pub(super) fn snapshot(&self) -> Vec<(String, Location)> {
+        let mut locations = self
+            .entry_locations
+            .iter()
+            .map(|(key, location)| (key.clone(), *location))
+            .collect::<Vec<_>>();
+        locations.sort_by_key(|(_, location)| location.offset);
+        locations
     }
 }
@@ -1,8 +1,31 @@
+use std::path::{Path, PathBuf};
+
 mod entry;
 
 mod table;
 pub(crate) use self::table::Table;
 
 mod dump;
-pub(crate) use dump::EntryDump;
+pub(crate) use dump::{dump_file, EntryDump};
 mod index;
+
+mod compaction;
+
+mod blob;
+
+mod hint;
+
+mod recovery;
+
+#[cfg(test)]
+mod testing;
+
+// Return the path that appended suffix to the file name of given path.
+fn suffixed_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
@@ -0,0 +1,238 @@
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, BufReader, SeekFrom};
+
+use crate::common::{warn, Error, ErrorKind, Result};
+use crate::core::table::entry::Entry;
+use crate::core::RecoveryMode;
+
+// Recover the table file whose entries could not be read to the end.
+//
+// If the process dies while writing an entry, table file ends with a partial entry.
+// as the entry has not been acknowledged, it is safe to discard the bytes after the last valid entry.
+// On the other hand, an invalid entry followed by other entries is not the result of a torn write,
+// so it is reported as corruption regardless of the mode.
+pub(super) async fn recover(
+    file: &mut fs::File,
+    valid_len: u64,
+    file_len: u64,
+    cause: Option<Error>,
+    mode: RecoveryMode,
+) -> Result<()> {
+    if valid_len >= file_len && cause.is_none() {
+        return Ok(());
+    }
+
+    let cause = cause
+        .map(|err| err.to_string())
+        .unwrap_or_else(|| "partial entry".to_owned());
+
+    let is_tail = is_tail(file, valid_len, file_len).await?;
+
+    if !is_tail {
+        return Err(ErrorKind::Corruption(format!(
+            "invalid entry at offset {} is followed by other entries ({})",
+            valid_len, cause
+        ))
+        .into());
+    }
+
+    let discarded = file_len - valid_len;
+    match mode {
+        RecoveryMode::Strict => Err(ErrorKind::Corruption(format!(
+            "invalid entry at offset {} ({} bytes, {})",
+            valid_len, discarded, cause
+        ))
+        .into()),
+        RecoveryMode::Truncate => {
+            warn!(
+                offset = valid_len,
+                discarded_bytes = discarded,
+                %cause,
+                "Truncate invalid entry at the end of table file"
+            );
+            file.set_len(valid_len).await?;
+            file.sync_all().await?;
+            Ok(())
+        }
+    }
+}
+
+// Return whether the invalid bytes starting at offset are the last entry or the last batch.
+//
+// The lengths in the header of the invalid entry can not be trusted, so the bytes are judged by
+// the entries verified by their checksum instead.
+// torn write leaves at most one partial entry after the complete entries of its batch,
+// so the bytes are not the result of torn write if they are longer than any entry,
+// or a verified entry is found in them.
+async fn is_tail(file: &mut fs::File, offset: u64, file_len: u64) -> Result<bool> {
+    let offset = match skip_batch(file, offset).await? {
+        Some(offset) => offset,
+        None => return Ok(false),
+    };
+
+    let remaining = file_len.saturating_sub(offset);
+    if remaining > Entry::MAX_ENCODED_BYTES as u64 {
+        return Ok(false);
+    }
+    let mut buf = vec![0; remaining as usize];
+    file.seek(SeekFrom::Start(offset)).await?;
+    file.read_exact(&mut buf).await?;
+
+    for start in 0..buf.len() {
+        if is_verified_entry(&buf[start..]).await {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+// Return the offset after the complete entries of the batch starting at offset.
+// if the entry at offset is not a batch marker, offset is returned as is.
+// return None if the batch is broken in the way torn write does not.
+async fn skip_batch(file: &mut fs::File, mut offset: u64) -> Result<Option<u64>> {
+    file.seek(SeekFrom::Start(offset)).await?;
+    let mut reader = BufReader::new(&mut *file);
+
+    let entries = match Entry::decode_from(&mut reader).await {
+        Ok((n, entry)) => match entry.batch_len() {
+            Some(entries) => {
+                offset += n as u64;
+                entries
+            }
+            None => return Ok(Some(offset)),
+        },
+        Err(_) => return Ok(Some(offset)),
+    };
+
+    for _ in 0..entries {
+        match Entry::decode_from(&mut reader).await {
+            // Batches are not nested.
+            Ok((_, entry)) if entry.batch_len().is_some() => return Ok(None),
+            Ok((n, _)) => offset += n as u64,
+            Err(_) => break,
+        }
+    }
+    Ok(Some(offset))
+}
+
+// Whether buf starts with a complete entry which passes its checksum.
+async fn is_verified_entry(buf: &[u8]) -> bool {
+    // Check the length before decoding, so that garbage length does not cause allocation.
+    match Entry::read_encoded_len(buf).await {
+        Ok(len) if len <= buf.len() as u64 => (),
+        _ => return false,
+    }
+    match Entry::decode_from(buf).await {
+        Ok((_, entry)) => entry.has_checksum(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tokio::io::AsyncWriteExt;
+
+    use crate::core::table::entry::Entry;
+    use crate::core::table::Table;
+    use crate::core::{RecoveryMode, TableConfig};
+    use crate::protocol::{Key, Value};
+
+    fn config(mode: RecoveryMode) -> TableConfig {
+        TableConfig {
+            recovery: Some(mode),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn truncate_torn_write() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("default.kvsd");
+
+        let mut f = tokio::fs::File::create(&path).await.unwrap();
+        let entry = Entry::new(
+            Key::new("key1").unwrap(),
+            Value::new(b"value1".as_ref()).unwrap(),
+        )
+        .unwrap();
+        let valid_len = entry.encode_to(&mut f).await.unwrap() as u64;
+        f.flush().await.unwrap();
+        drop(f);
+
+        // Simulate the process died while writing header.
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&[0, 0, 0, 0, 0, 0, 0, 4, 0, 0])
+            .unwrap();
+
+        assert!(Table::from_path(&path, config(RecoveryMode::Strict))
+            .await
+            .is_err());
+
+        let _table = Table::from_path(&path, config(RecoveryMode::Truncate))
+            .await
+            .unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), valid_len);
+    }
+
+    #[tokio::test]
+    async fn truncate_torn_batch() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("default.kvsd");
+        let entry = |key: &str| {
+            Entry::new(Key::new(key).unwrap(), Value::new(key.as_bytes()).unwrap()).unwrap()
+        };
+
+        let mut buf = Vec::new();
+        let valid_len = entry("key1").encode_to(&mut buf).await.unwrap() as u64;
+        Entry::batch_marker(3).encode_to(&mut buf).await.unwrap();
+        entry("key2").encode_to(&mut buf).await.unwrap();
+        entry("key3").encode_to(&mut buf).await.unwrap();
+        // Simulate the process died while writing the last entry of the batch.
+        let mut last = Vec::new();
+        entry("key4").encode_to(&mut last).await.unwrap();
+        buf.extend_from_slice(&last[..last.len() - 1]);
+        std::fs::write(&path, &buf).unwrap();
+
+        assert!(Table::from_path(&path, config(RecoveryMode::Strict))
+            .await
+            .is_err());
+
+        // Whole batch is discarded even though its first entries are valid.
+        let _table = Table::from_path(&path, config(RecoveryMode::Truncate))
+            .await
+            .unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), valid_len);
+    }
+
+    #[tokio::test]
+    async fn keep_entries_after_corrupted_length() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("default.kvsd");
+
+        let mut buf = Vec::new();
+        let mut offsets = Vec::new();
+        for key in ["key1", "key2", "key3"] {
+            offsets.push(buf.len());
+            Entry::new(Key::new(key).unwrap(), Value::new(key.as_bytes()).unwrap())
+                .unwrap()
+                .encode_to(&mut buf)
+                .await
+                .unwrap();
+        }
+        // Flipped value length of the middle entry claims that it runs past the end of file.
+        buf[offsets[1] + 15] ^= 0xff;
+        std::fs::write(&path, &buf).unwrap();
+
+        let err = Table::from_path(&path, config(RecoveryMode::Truncate))
+            .await
+            .err()
+            .unwrap();
+        assert!(err.is_corruption());
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), buf.len() as u64);
+    }
+}
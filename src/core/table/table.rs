@@ -1,130 +1,1049 @@
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use chrono::Utc;
 use tokio::fs;
-use tokio::io::{AsyncRead, AsyncSeek, AsyncSeekExt, AsyncWrite, SeekFrom};
+use tokio::io::{
+    AsyncRead, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt, BufReader, SeekFrom,
+};
 use tokio::sync::mpsc::Receiver;
 use tokio::sync::oneshot;
 
+use crate::core::table::blob::Blobs;
+use crate::core::table::compaction::{Compacted, Compaction};
 use crate::core::table::entry::Entry;
+use crate::core::table::hint::Hint;
 use crate::core::table::index::Index;
-use crate::core::UnitOfWork;
-use crate::protocol::{Key, Value};
+use crate::core::table::recovery;
+use crate::core::transaction::CommittedWrite;
+use crate::core::uow::{
+    ChunkWritten, CompactTable, Decision, Prepare, Prepared, ReadChunk, Scan, ScanPage, SetResult,
+    ValueChunk, Versioned, WriteChunk,
+};
+use crate::core::{Durability, TableConfig, UnitOfWork, Work};
+use crate::protocol::{Condition, Expiry, Key, Previous, Value, MAX_CHUNK_BYTES, MAX_VALUE_BYTES};
 use crate::{
-    common::{debug, error, info, trace, ErrorKind, Result},
-    core::table::dump::EntryDump,
+    common::{debug, error, info, trace, warn, ErrorKind, Result},
+    KvsdError,
 };
 
 pub(crate) struct Table<File = fs::File> {
     file: File,
     index: Index,
+    // table file length.
+    len: u64,
+    config: TableConfig,
+    // table file path. required to swap compacted file.
+    path: Option<PathBuf>,
+    // running compaction.
+    compaction: Option<Compaction>,
+    // requests waiting for running compaction to complete.
+    compaction_waiters: Vec<Work<CompactTable, ()>>,
+    // writes waiting for sync to be acknowledged.
+    pending_acks: Vec<PendingAck>,
+    // whether there are written entries which are not synced.
+    dirty: bool,
+    // blob files of the values uploaded by stream. only the table backed by file has them.
+    blobs: Option<Blobs>,
+    // set when the transaction log could not be cleared after the table applied the writes.
+    // the table rejects writes and reads until restart, as the transaction is redone then.
+    stopped: bool,
 }
 
-impl Table<fs::File> {
-    pub(crate) async fn from_path(path: impl AsRef<Path>) -> Result<Self> {
-        let f = fs::OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .truncate(false)
-            .open(path.as_ref())
-            .await?;
+// Events table task waits for.
+enum Event {
+    UnitOfWork(UnitOfWork),
+    Compacted(Result<Compacted>),
+    Sync,
+    SweepExpired,
+    Closed,
+}
+
+// Response of write which is sent after sync.
+struct PendingAck {
+    send: Box<dyn FnOnce(Result<()>) + Send>,
+}
+
+impl PendingAck {
+    fn new<T>(sender: oneshot::Sender<Result<T>>, value: T) -> Self
+    where
+        T: Send + 'static,
+    {
+        Self {
+            send: Box::new(move |synced| {
+                // Client may have gone.
+                let _ = sender.send(synced.map(|()| value));
+            }),
+        }
+    }
+}
+
+// Return the expiration timestamp of the entry written now.
+fn expires_at_ms(ttl: Duration) -> i64 {
+    let ttl_ms = i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX);
+    Utc::now().timestamp_millis().saturating_add(ttl_ms)
+}
 
-        Table::new(f).await
+// Check the condition of the conditional write against the current entry.
+fn satisfies(condition: &Condition, current: Option<&Entry>) -> bool {
+    match condition {
+        Condition::Absent => current.is_none(),
+        Condition::Present => current.is_some(),
+        Condition::Value(value) => current.and_then(Entry::value) == Some(value.as_ref()),
+        Condition::Version(version) => current.map_or(0, Entry::version) == *version,
     }
 }
 
-impl<File> Table<File>
-where
-    File: AsyncWrite + AsyncRead + AsyncSeek + Unpin,
-{
-    pub(crate) async fn new(mut file: File) -> Result<Self> {
-        let pos = file.seek(SeekFrom::Current(0)).await?;
+impl Table<fs::File> {
+    // Maximum number of unit of works synced at once.
+    const MAX_GROUP_COMMIT_UOWS: usize = 128;
+    // Interval to write tombstones of expired keys.
+    const EXPIRY_SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+    // Uploads not written for this duration are aborted.
+    const UPLOAD_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+    pub(crate) async fn from_path(path: impl AsRef<Path>, config: TableConfig) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = Table::open(path).await?;
+        let file_len = file.metadata().await?.len();
+
+        // Read the entries which are not covered by the hint.
+        let hint = Hint::load(path, file_len).await;
+        let (mut index, pos) = match hint {
+            Some(hint) => (hint.index, hint.data_len),
+            None => (Index::new(), 0),
+        };
         debug!("initial pos {}", pos);
 
-        // TODO: Buffering
-        let index = Index::from_reader(&mut file).await?;
+        file.seek(SeekFrom::Start(pos)).await?;
+        let (len, result) = index
+            .read_from(BufReader::new(&mut file), pos as usize)
+            .await;
+        let len = len as u64;
+
+        recovery::recover(&mut file, len, file_len, result.err(), config.recovery()).await?;
+
+        file.seek(SeekFrom::Start(len)).await?;
         // TODO: summary
         debug!("{:?}", index);
 
-        Ok(Self {
+        let blobs = Blobs::open(path).await?;
+
+        let table = Table {
             file,
             index,
-            // receiver,
-        })
+            len,
+            config,
+            path: Some(path.to_path_buf()),
+            compaction: None,
+            compaction_waiters: Vec::new(),
+            pending_acks: Vec::new(),
+            dirty: false,
+            blobs: Some(blobs),
+            stopped: false,
+        };
+
+        // Write hint so that next startup does not need to read the entries read this time.
+        if table.len > pos {
+            if let Err(err) = Hint::write(path, &table.index, table.len).await {
+                warn!(path = %path.display(), "Write hint {}", err);
+            }
+        }
+
+        Ok(table)
     }
 
     pub(crate) async fn run(mut self, mut receiver: Receiver<UnitOfWork>) {
-        while let Some(uow) = receiver.recv().await {
-            if let Err(err) = self.handle_uow(uow).await {
-                error!("handle uow {}", err);
+        let mut sync_interval = match self.config.durability() {
+            Durability::Every(period) => Some(tokio::time::interval(period)),
+            _ => None,
+        };
+        let mut sweep_interval = tokio::time::interval(Table::EXPIRY_SWEEP_INTERVAL);
+
+        loop {
+            let event = tokio::select! {
+                uow = receiver.recv() => match uow {
+                    Some(uow) => Event::UnitOfWork(uow),
+                    None => Event::Closed,
+                },
+                compacted = Compaction::wait(&mut self.compaction), if self.compaction.is_some() => {
+                    Event::Compacted(compacted)
+                }
+                _ = tick(&mut sync_interval), if sync_interval.is_some() => Event::Sync,
+                _ = sweep_interval.tick() => Event::SweepExpired,
+            };
+
+            match event {
+                Event::UnitOfWork(uow) => {
+                    if let Err(err) = self.handle(uow).await {
+                        error!("handle uow {}", err);
+                    }
+                    // Group commit, handle queued unit of works so that one sync covers them.
+                    for _ in 1..Table::MAX_GROUP_COMMIT_UOWS {
+                        match receiver.try_recv() {
+                            Ok(uow) => {
+                                if let Err(err) = self.handle(uow).await {
+                                    error!("handle uow {}", err);
+                                }
+                            }
+                            Err(_) => break,
+                        }
+                    }
+                    self.commit().await;
+                    self.compact_if_needed().await;
+                }
+                Event::SweepExpired if self.stopped => (),
+                Event::SweepExpired => {
+                    if let Err(err) = self.sweep_expired().await {
+                        error!("sweep expired {}", err);
+                    }
+                    if let Some(blobs) = self.blobs.as_mut() {
+                        blobs.abort_idle(Table::UPLOAD_IDLE_TIMEOUT).await;
+                    }
+                    self.compact_if_needed().await;
+                }
+                Event::Compacted(compacted) => {
+                    self.compaction = None;
+                    if let Err(err) = self.finish_compaction(compacted).await {
+                        error!("compaction {}", err);
+                    }
+                }
+                Event::Sync => {
+                    if let Err(err) = self.sync().await {
+                        error!("sync {}", err);
+                    }
+                }
+                Event::Closed => break,
+            }
+        }
+
+        if let Err(err) = self.write_hint().await {
+            error!("write hint {}", err);
+        }
+    }
+
+    pub(super) async fn handle(&mut self, uow: UnitOfWork) -> Result<()> {
+        if self.stopped {
+            return uow.send_error(
+                ErrorKind::Internal(
+                    "table is stopped until the committed transaction is redone at restart"
+                        .to_owned(),
+                )
+                .into(),
+            );
+        }
+
+        match uow {
+            UnitOfWork::Prepare(prepare) => self.prepare(prepare).await,
+            UnitOfWork::CompactTable(compact) => self.request_compaction(compact).await,
+            uow => self.handle_uow(uow).await,
+        }
+    }
+
+    // Start compaction unless it is running, then respond when it completes.
+    async fn request_compaction(&mut self, mut compact: Work<CompactTable, ()>) -> Result<()> {
+        info!("{}", compact.request);
+
+        if self.compaction.is_none() {
+            if let Err(err) = self.start_compaction().await {
+                return compact.send_response(Err(err));
+            }
+        }
+        self.compaction_waiters.push(compact);
+
+        Ok(())
+    }
+
+    // Validate the versions read by the transaction then wait for the decision.
+    // the table handles nothing else until the transaction is decided,
+    // so the validated versions are not changed before the writes are applied.
+    async fn prepare(&mut self, mut prepare: Work<Prepare, Prepared>) -> Result<()> {
+        info!("{}", prepare.request);
+
+        let writes = match self.prepare_writes(&prepare.request).await {
+            Ok(writes) => writes,
+            Err(err) => return prepare.send_response(Err(err)),
+        };
+
+        let (tx, rx) = oneshot::channel();
+        prepare.send_response(Ok(Prepared {
+            writes: writes.clone(),
+            decision: tx,
+        }))?;
+
+        match rx.await {
+            Ok(Decision { ack, resume }) => {
+                let result = self.apply_committed(writes).await;
+                if let Err(err) = result.as_ref() {
+                    error!("apply committed writes {}", err);
+                }
+                // Coordinator may have gone.
+                let _ = ack.send(result);
+                // Wait until the transaction log is cleared.
+                // the log kept means the writes are redone at startup over the later writes,
+                // so the table stops until then.
+                if resume.await.is_err() {
+                    error!("stop the table until the committed transaction is redone");
+                    self.stopped = true;
+                }
+            }
+            Err(_) => debug!("Transaction aborted"),
+        }
+
+        Ok(())
+    }
+
+    // Check the read versions and assign the versions of the writes.
+    async fn prepare_writes(&mut self, prepare: &Prepare) -> Result<Vec<CommittedWrite>> {
+        for (key, version) in &prepare.reads {
+            let current = self.lookup_entry(key).await?;
+            if current.as_ref().map_or(0, Entry::version) != *version {
+                return Err(ErrorKind::Conflict(format!(
+                    "{}/{} {} has been changed",
+                    prepare.namespace, prepare.table, key
+                ))
+                .into());
+            }
+        }
+
+        let mut writes = Vec::with_capacity(prepare.writes.len());
+        for (key, value) in &prepare.writes {
+            let version = match value {
+                Some(_) => self.index.next_version(key),
+                None => 0,
+            };
+            writes.push(CommittedWrite {
+                key: key.clone(),
+                value: value.clone(),
+                version,
+            });
+        }
+
+        Ok(writes)
+    }
+
+    // Append the committed writes as a batch and sync them.
+    // also used to redo the transaction at startup, so applying the same writes again is harmless.
+    pub(crate) async fn apply_committed(&mut self, writes: Vec<CommittedWrite>) -> Result<()> {
+        let mut entries = Vec::with_capacity(writes.len());
+        for write in writes {
+            match write.value {
+                Some(value) => {
+                    entries.push(Entry::new(write.key, value)?.with_version(write.version));
+                }
+                None if self.index.lookup(&write.key).is_some() => {
+                    let version = self.index.version(&write.key);
+                    entries.push(Entry::tombstone(write.key.to_string(), version));
+                }
+                None => (),
+            }
+        }
+
+        self.append_batch(entries).await?;
+        self.sync().await
+    }
+
+    async fn write_hint(&mut self) -> Result<()> {
+        self.sync().await?;
+        if let Some(path) = self.path.as_ref() {
+            Hint::write(path, &self.index, self.len).await?;
+        }
+        Ok(())
+    }
+
+    // Sync written entries then acknowledge the writes waiting for it.
+    async fn commit(&mut self) {
+        if self.pending_acks.is_empty() {
+            return;
+        }
+
+        let result = self.sync().await;
+        if let Err(err) = result.as_ref() {
+            error!("sync {}", err);
+        }
+
+        for ack in self.pending_acks.drain(..) {
+            let synced = match result.as_ref() {
+                Ok(()) => Ok(()),
+                Err(err) => Err(ErrorKind::Internal(format!("sync table file {}", err)).into()),
+            };
+            (ack.send)(synced);
+        }
+    }
+
+    async fn sync(&mut self) -> Result<()> {
+        if self.dirty {
+            self.file.flush().await?;
+            self.file.sync_data().await?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    // Run compaction and wait for it to complete.
+    #[cfg(test)]
+    pub(crate) async fn compact(&mut self) -> Result<()> {
+        if self.compaction.is_none() {
+            self.start_compaction().await?;
+        }
+        let compacted = Compaction::wait(&mut self.compaction).await;
+        self.compaction = None;
+
+        self.finish_compaction(compacted).await
+    }
+
+    async fn compact_if_needed(&mut self) {
+        if self.should_compact() {
+            if let Err(err) = self.start_compaction().await {
+                error!("start compaction {}", err);
             }
         }
     }
 
-    async fn handle_uow(&mut self, uow: UnitOfWork) -> Result<()> {
+    fn should_compact(&self) -> bool {
+        if self.compaction.is_some() || self.path.is_none() {
+            return false;
+        }
+        if self.len == 0 || self.len < self.config.compaction_min_bytes() {
+            return false;
+        }
+
+        let garbage_bytes = self.len.saturating_sub(self.index.live_bytes() as u64);
+        garbage_bytes as f64 / self.len as f64 >= self.config.compaction_garbage_ratio()
+    }
+
+    async fn start_compaction(&mut self) -> Result<()> {
+        let path = self
+            .path
+            .clone()
+            .ok_or_else(|| ErrorKind::Internal("compaction requires table file path".into()))?;
+
+        // Make sure that compaction task can read all the entries in index.
+        self.file.flush().await?;
+
+        info!(
+            path = %path.display(),
+            len = self.len,
+            live_bytes = self.index.live_bytes(),
+            "Start compaction"
+        );
+
+        self.compaction = Some(Compaction::start(
+            path,
+            self.index.snapshot(),
+            self.index.max_version(),
+            self.len,
+        ));
+
+        Ok(())
+    }
+
+    async fn finish_compaction(&mut self, compacted: Result<Compacted>) -> Result<()> {
+        let result = match compacted {
+            Ok(compacted) => self.swap_or_discard(compacted).await,
+            Err(err) => Err(err),
+        };
+
+        for mut waiter in self.compaction_waiters.drain(..) {
+            let response = match &result {
+                Ok(()) => Ok(()),
+                Err(err) => Err(ErrorKind::Internal(format!("compaction {}", err)).into()),
+            };
+            // Client may have gone.
+            let _ = waiter.send_response(response);
+        }
+
+        result
+    }
+
+    async fn swap_or_discard(&mut self, compacted: Compacted) -> Result<()> {
+        let compacted_path = compacted.path.clone();
+
+        match self.swap_compacted(compacted).await {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                // Keep using current file.
+                let _ = fs::remove_file(&compacted_path).await;
+                self.file.seek(SeekFrom::End(0)).await?;
+                Err(err)
+            }
+        }
+    }
+
+    async fn swap_compacted(&mut self, compacted: Compacted) -> Result<()> {
+        let Compacted {
+            path: compacted_path,
+            mut index,
+            source_len,
+            len,
+            mut blobs,
+        } = compacted;
+        let path = self.path.clone().unwrap();
+
+        let mut dest = fs::OpenOptions::new()
+            .write(true)
+            .open(&compacted_path)
+            .await?;
+        dest.seek(SeekFrom::End(0)).await?;
+
+        // Copy the entries appended while compaction was running.
+        self.file.seek(SeekFrom::Start(source_len)).await?;
+        let mut reader = BufReader::new(&mut self.file);
+        let mut pos = len as usize;
+        loop {
+            match Entry::decode_from(&mut reader).await {
+                Ok((n, entry)) => {
+                    entry.encode_to(&mut dest).await?;
+                    if let Some(blob) = entry.blob() {
+                        blobs.insert(blob.id);
+                    }
+                    // Batches written by this table are complete, so markers can be skipped.
+                    if entry.batch_len().is_none() {
+                        index.apply(entry, pos, n);
+                    }
+                    pos += n;
+                }
+                Err(err) if err.is_eof() => break,
+                Err(err) => return Err(err),
+            }
+        }
+        dest.flush().await?;
+        dest.sync_all().await?;
+        drop(dest);
+
+        // Write the hint before swap, as it holds the versions of the keys whose tombstones
+        // are reclaimed. it replaces the hint of current file, which refers to its offsets.
+        Hint::write_for(&path, &compacted_path, &index, pos as u64).await?;
+        fs::rename(&compacted_path, &path).await?;
+        if let Some(dir) = path.parent() {
+            fs::File::open(dir).await?.sync_all().await?;
+        }
+
+        let mut file = Table::open(&path).await?;
+        file.seek(SeekFrom::End(0)).await?;
+
+        info!(
+            path = %path.display(),
+            before = self.len,
+            after = pos,
+            "Compaction completed"
+        );
+
+        self.file = file;
+        self.index = index;
+        self.len = pos as u64;
+
+        // Blobs are no longer referenced by the table file once the entries referencing them are reclaimed.
+        if let Some(current) = self.blobs.as_mut() {
+            if let Err(err) = current.remove_unreferenced(&blobs).await {
+                warn!(path = %path.display(), "Remove unreferenced blobs {}", err);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn open(path: &Path) -> Result<fs::File> {
+        let f = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .await?;
+
+        Ok(f)
+    }
+}
+
+// Wait for next tick. if interval is not set, wait forever.
+async fn tick(interval: &mut Option<tokio::time::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+impl<File> Table<File>
+where
+    File: AsyncWrite + AsyncRead + AsyncSeek + Unpin,
+{
+    // Maximum number of expired keys swept at once.
+    const MAX_SWEEP_KEYS: usize = 1024;
+
+    pub(super) async fn handle_uow(&mut self, uow: UnitOfWork) -> Result<()> {
         match uow {
             UnitOfWork::Set(set) => {
                 info!("{}", set.request);
 
-                let old_value = match self.lookup_entry(&set.request.key).await? {
-                    Some(entry) => {
-                        let (_, value) = entry.take_key_value();
-                        Some(value)
-                    }
-                    None => None,
+                let current = match self.lookup_entry(&set.request.key).await {
+                    Ok(entry) => entry,
+                    // Overwriting is the way to recover corrupted entry.
+                    Err(err) if err.is_corruption() && set.request.condition.is_none() => None,
+                    Err(err) => return self.send_value(set.response_sender, Err(err)),
                 };
 
-                let current = self.file.seek(SeekFrom::Current(0)).await?;
-                trace!("Seek {}", current);
+                if let Some(condition) = &set.request.condition {
+                    if !satisfies(condition, current.as_ref()) {
+                        return self
+                            .send_value(set.response_sender, Ok(SetResult::ConditionFailed));
+                    }
+                }
 
-                let entry = Entry::new(set.request.key.clone(), set.request.value)?;
-                entry.encode_to(&mut self.file).await?;
+                // Version continues from the deleted or expired one when the key is created again.
+                let version = self.index.next_version(&set.request.key);
+                let previous = match current {
+                    Some(entry) => match self.take_previous(entry).await {
+                        Ok(previous) => previous,
+                        Err(err) => return self.send_value(set.response_sender, Err(err)),
+                    },
+                    None => None,
+                };
 
-                self.index
-                    .add(set.request.key.into_string(), current as usize);
+                let expires_at_ms = set.request.ttl.map(expires_at_ms);
+                let entry = match Entry::new(set.request.key, set.request.value) {
+                    Ok(entry) => entry.with_expires_at(expires_at_ms).with_version(version),
+                    Err(err) => return self.send_value(set.response_sender, Err(err)),
+                };
+                if let Err(err) = self.append(entry).await {
+                    return self.send_value(set.response_sender, Err(err));
+                }
 
-                self.send_value(set.response_sender, Ok(old_value.map(Value::new_unchecked)))
+                self.ack_write(
+                    set.response_sender,
+                    SetResult::Applied { previous, version },
+                )
             }
             UnitOfWork::Get(get) => {
                 info!("{}", get.request);
 
-                let entry = match self.lookup_entry(&get.request.key).await? {
-                    Some(entry) => entry,
-                    None => return self.send_value(get.response_sender, Ok(None)),
+                let entry = match self.lookup_entry(&get.request.key).await {
+                    Ok(Some(entry)) => entry,
+                    Ok(None) => return self.send_value(get.response_sender, Ok(None)),
+                    Err(err) => return self.send_value(get.response_sender, Err(err)),
                 };
 
-                let (key, value) = entry.take_key_value();
-
-                debug_assert_eq!(*get.request.key, key);
+                let version = entry.version();
+                let value = match self.take_value(entry).await {
+                    Ok(value) => value,
+                    Err(err) => return self.send_value(get.response_sender, Err(err)),
+                };
 
-                self.send_value(get.response_sender, Ok(Some(Value::new_unchecked(value))))
+                let versioned = Versioned { value, version };
+                self.send_value(get.response_sender, Ok(Some(versioned)))
             }
             UnitOfWork::Delete(delete) => {
                 info!("{}", delete.request);
 
-                let mut entry = match self.lookup_entry(&delete.request.key).await? {
-                    Some(entry) => entry,
-                    None => return self.send_value(delete.response_sender, Ok(None)),
+                let entry = match self.lookup_entry(&delete.request.key).await {
+                    Ok(Some(entry)) => entry,
+                    Ok(None) => return self.send_value(delete.response_sender, Ok(None)),
+                    Err(err) => return self.send_value(delete.response_sender, Err(err)),
                 };
 
-                let value = entry.mark_deleted();
-                entry.encode_to(&mut self.file).await?;
+                let previous = match self.take_previous(entry).await {
+                    Ok(previous) => previous,
+                    Err(err) => return self.send_value(delete.response_sender, Err(err)),
+                };
+                let version = self.index.version(&delete.request.key);
+                let tombstone = Entry::tombstone(delete.request.key.into_string(), version);
+                if let Err(err) = self.append(tombstone).await {
+                    return self.send_value(delete.response_sender, Err(err));
+                }
+
+                self.ack_write(delete.response_sender, previous)
+            }
+            UnitOfWork::Scan(mut scan) => {
+                info!("{}", scan.request);
 
-                self.index.remove(delete.request.key.as_str());
+                let page = self.scan(&scan.request).await;
+                scan.send_response(page)
+            }
+            UnitOfWork::MSet(mset) => {
+                info!("{}", mset.request);
 
-                self.send_value(
-                    delete.response_sender,
-                    Ok(Some(Value::new(value.unwrap())?)),
-                )
+                let entries = match self.mset_entries(mset.request.entries).await {
+                    Ok(entries) => entries,
+                    Err(err) => return self.send_value(mset.response_sender, Err(err)),
+                };
+                if let Err(err) = self.append_batch(entries).await {
+                    return self.send_value(mset.response_sender, Err(err));
+                }
+
+                self.ack_write(mset.response_sender, ())
+            }
+            UnitOfWork::MGet(mut mget) => {
+                info!("{}", mget.request);
+
+                let mut values = Vec::with_capacity(mget.request.keys.len());
+                for key in &mget.request.keys {
+                    let value = match self.lookup_entry(key).await {
+                        Ok(Some(entry)) => self.take_value(entry).await.map(Some),
+                        Ok(None) => Ok(None),
+                        Err(err) => Err(err),
+                    };
+                    match value {
+                        Ok(value) => values.push(value),
+                        Err(err) => return mget.send_response(Err(err)),
+                    }
+                }
+
+                mget.send_response(Ok(values))
+            }
+            UnitOfWork::MDelete(mdelete) => {
+                info!("{}", mdelete.request);
+
+                let (entries, values) = match self.mdelete_entries(mdelete.request.keys).await {
+                    Ok(deleted) => deleted,
+                    Err(err) => return self.send_value(mdelete.response_sender, Err(err)),
+                };
+                if let Err(err) = self.append_batch(entries).await {
+                    return self.send_value(mdelete.response_sender, Err(err));
+                }
+
+                self.ack_write(mdelete.response_sender, values)
+            }
+            UnitOfWork::Expire(expire) => {
+                info!("{}", expire.request);
+
+                let entry = match self.lookup_entry(&expire.request.key).await {
+                    Ok(Some(entry)) => entry,
+                    Ok(None) => return self.send_value(expire.response_sender, Ok(None)),
+                    Err(err) => return self.send_value(expire.response_sender, Err(err)),
+                };
+
+                // Rewrite the entry with new expiration. value is not changed, so is the version.
+                let entry = entry
+                    .touch()
+                    .with_expires_at(expire.request.ttl.map(expires_at_ms));
+                if let Err(err) = self.append(entry).await {
+                    return self.send_value(expire.response_sender, Err(err));
+                }
+
+                let expiry = match expire.request.ttl {
+                    Some(ttl) => Expiry::In(ttl),
+                    None => Expiry::Never,
+                };
+                self.ack_write(expire.response_sender, Some(expiry))
+            }
+            UnitOfWork::Ttl(ttl) => {
+                info!("{}", ttl.request);
+
+                let now_ms = Utc::now().timestamp_millis();
+                let expiry = self
+                    .index
+                    .lookup(&ttl.request.key)
+                    .filter(|location| !location.is_expired(now_ms))
+                    .map(|location| match location.expires_at_ms {
+                        Some(expires_at_ms) => Expiry::In(Duration::from_millis(
+                            expires_at_ms.saturating_sub(now_ms) as u64,
+                        )),
+                        None => Expiry::Never,
+                    });
+
+                self.send_value(ttl.response_sender, Ok(expiry))
+            }
+            UnitOfWork::WriteChunk(write) => {
+                debug!("{}", write.request);
+
+                match self.write_chunk(write.request).await {
+                    // Value is set by the last chunk.
+                    Ok(written) if written.version.is_some() => {
+                        self.ack_write(write.response_sender, written)
+                    }
+                    written => self.send_value(write.response_sender, written),
+                }
+            }
+            UnitOfWork::ReadChunk(mut read) => {
+                debug!("{}", read.request);
+
+                let chunk = self.read_chunk(&read.request).await;
+                read.send_response(chunk)
             }
             _ => unreachable!(),
         }
     }
 
-    fn send_value(
+    // Append the entry to the table file and reflect it to the index.
+    async fn append(&mut self, entry: Entry) -> Result<()> {
+        let current = self.file.seek(SeekFrom::Current(0)).await?;
+        trace!("Seek {}", current);
+
+        let mut buf = Vec::with_capacity(entry.encoded_len());
+        let n = entry.encode_to(&mut buf).await?;
+        self.write_at_end(current, &buf).await?;
+
+        self.index.apply(entry, current as usize, n);
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    // Write the encoded entries at the end of the table file.
+    // on failure, the write position goes back to the end, so that the next entry overwrites
+    // the partially written ones. index is left as is, as the entries are not written.
+    async fn write_at_end(&mut self, current: u64, buf: &[u8]) -> Result<()> {
+        let written = match self.file.write_all(buf).await {
+            Ok(()) => self.file.flush().await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = written {
+            self.file.seek(SeekFrom::Start(current)).await?;
+            return Err(err.into());
+        }
+        self.len += buf.len() as u64;
+
+        Ok(())
+    }
+
+    // Append the entries as a batch. recovery applies either all of them or none of them.
+    async fn append_batch(&mut self, entries: Vec<Entry>) -> Result<()> {
+        if entries.len() <= 1 {
+            // Single entry is atomic without marker.
+            for entry in entries {
+                self.append(entry).await?;
+            }
+            return Ok(());
+        }
+
+        let current = self.file.seek(SeekFrom::Current(0)).await? as usize;
+
+        let mut buf = Vec::new();
+        Entry::batch_marker(entries.len())
+            .encode_to(&mut buf)
+            .await?;
+        let mut locations = Vec::with_capacity(entries.len());
+        for entry in &entries {
+            let offset = current + buf.len();
+            let n = entry.encode_to(&mut buf).await?;
+            locations.push((offset, n));
+        }
+
+        self.write_at_end(current as u64, &buf).await?;
+
+        for (entry, (offset, n)) in entries.into_iter().zip(locations) {
+            self.index.apply(entry, offset, n);
+        }
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    // Construct the entries of MSet with the versions assigned.
+    async fn mset_entries(&mut self, entries: Vec<(Key, Value)>) -> Result<Vec<Entry>> {
+        // Same key may appear more than once in the batch.
+        let mut versions: HashMap<String, u64> = HashMap::new();
+        let mut batch = Vec::with_capacity(entries.len());
+
+        for (key, value) in entries {
+            let version = match versions.get(key.as_str()) {
+                Some(version) => version + 1,
+                None => self.index.next_version(&key),
+            };
+            versions.insert(key.to_string(), version);
+            batch.push(Entry::new(key, value)?.with_version(version));
+        }
+
+        Ok(batch)
+    }
+
+    // Construct the tombstones of MDelete and the deleted values.
+    async fn mdelete_entries(
+        &mut self,
+        keys: Vec<Key>,
+    ) -> Result<(Vec<Entry>, Vec<Option<Previous>>)> {
+        let mut deleted = HashSet::new();
+        let mut tombstones = Vec::with_capacity(keys.len());
+        let mut values = Vec::with_capacity(keys.len());
+
+        for key in keys {
+            if deleted.contains(key.as_str()) {
+                values.push(None);
+                continue;
+            }
+            match self.lookup_entry(&key).await? {
+                Some(entry) => {
+                    deleted.insert(key.to_string());
+                    let version = self.index.version(&key);
+                    tombstones.push(Entry::tombstone(key.into_string(), version));
+                    values.push(self.take_previous(entry).await?);
+                }
+                None => values.push(None),
+            }
+        }
+
+        Ok((tombstones, values))
+    }
+
+    // Append the chunk to the upload. the last chunk sets the value to the uploaded blob.
+    async fn write_chunk(&mut self, chunk: WriteChunk) -> Result<ChunkWritten> {
+        let blobs = self.blobs_mut()?;
+        if chunk.data.len() > MAX_CHUNK_BYTES {
+            if let Some(upload) = chunk.upload {
+                blobs.abort(upload).await;
+            }
+            return Err(ErrorKind::InvalidArgument(format!(
+                "chunk exceeds maximum bytes({})",
+                MAX_CHUNK_BYTES
+            ))
+            .into());
+        }
+
+        let upload = match blobs.write(chunk.upload, &chunk.key, &chunk.data).await {
+            Ok(upload) => upload,
+            Err(err) => {
+                if let Some(upload) = chunk.upload {
+                    blobs.abort(upload).await;
+                }
+                return Err(err);
+            }
+        };
+        if !chunk.last {
+            return Ok(ChunkWritten {
+                upload,
+                version: None,
+            });
+        }
+
+        let blob = blobs.finish(upload).await?;
+        let version = self.index.next_version(&chunk.key);
+        let entry = Entry::blob_reference(chunk.key, &blob)
+            .with_expires_at(chunk.ttl.map(expires_at_ms))
+            .with_version(version);
+        self.append(entry).await?;
+
+        Ok(ChunkWritten {
+            upload,
+            version: Some(version),
+        })
+    }
+
+    // Read the part of the value.
+    // fails with conflict if the value is not the version read by the previous chunk.
+    async fn read_chunk(&mut self, read: &ReadChunk) -> Result<Option<ValueChunk>> {
+        let entry = self.lookup_entry(&read.key).await?;
+        if let Some(version) = read.version {
+            if entry.as_ref().map(Entry::version) != Some(version) {
+                return Err(ErrorKind::Conflict(format!(
+                    "{}/{} {} has been changed",
+                    read.namespace, read.table, read.key
+                ))
+                .into());
+            }
+        }
+        let entry = match entry {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let version = entry.version();
+        let (data, total_bytes) = match entry.blob() {
+            Some(blob) => (
+                self.blobs_mut()?.read(&blob, read.offset, read.len).await?,
+                blob.len,
+            ),
+            None => {
+                let (_, value) = entry.take_key_value();
+                let start = (read.offset as usize).min(value.len());
+                let end = start.saturating_add(read.len).min(value.len());
+                (value[start..end].to_vec(), value.len() as u64)
+            }
+        };
+
+        Ok(Some(ValueChunk {
+            data,
+            version,
+            total_bytes,
+        }))
+    }
+
+    // Return the value of the entry.
+    // value stored in the blob is read unless it is too large to be returned at once.
+    async fn take_value(&mut self, entry: Entry) -> Result<Value> {
+        match entry.blob() {
+            Some(blob) if blob.len > MAX_VALUE_BYTES as u64 => Err(KvsdError::MaxValueBytes {
+                max_bytes: MAX_VALUE_BYTES,
+            }
+            .into()),
+            Some(blob) => Ok(Value::new_unchecked(
+                self.blobs_mut()?.read_all(&blob).await?,
+            )),
+            None => {
+                let (_, value) = entry.take_key_value();
+                Ok(Value::new_unchecked(value))
+            }
+        }
+    }
+
+    // Return the value overwritten or deleted.
+    // blob which is too large is returned as the marker and corrupted one is omitted,
+    // as overwriting or deleting it should not fail.
+    async fn take_previous(&mut self, entry: Entry) -> Result<Option<Previous>> {
+        match entry.blob() {
+            Some(blob) if blob.len > MAX_VALUE_BYTES as u64 => {
+                Ok(Some(Previous::TooLarge(blob.len)))
+            }
+            Some(_) => match self.take_value(entry).await {
+                Ok(value) => Ok(Some(Previous::Value(value))),
+                Err(err) if err.is_corruption() => Ok(None),
+                Err(err) => Err(err),
+            },
+            None => self
+                .take_value(entry)
+                .await
+                .map(|value| Some(Previous::Value(value))),
+        }
+    }
+
+    fn blobs_mut(&mut self) -> Result<&mut Blobs> {
+        self.blobs
+            .as_mut()
+            .ok_or_else(|| ErrorKind::Internal("blob requires table file path".into()).into())
+    }
+
+    // Write tombstones of the expired keys so that compaction can reclaim them.
+    async fn sweep_expired(&mut self) -> Result<()> {
+        let expired = self
+            .index
+            .expired(Utc::now().timestamp_millis(), Self::MAX_SWEEP_KEYS);
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        debug!(keys = expired.len(), "Sweep expired keys");
+
+        for key in expired {
+            let version = self.index.version(&key);
+            self.append(Entry::tombstone(key, version)).await?;
+        }
+
+        Ok(())
+    }
+
+    // Acknowledge the write. if durability requires sync, response is sent after sync.
+    fn ack_write<T>(&mut self, sender: Option<oneshot::Sender<Result<T>>>, value: T) -> Result<()>
+    where
+        T: Send + 'static,
+    {
+        match self.config.durability() {
+            Durability::Always => {
+                let sender = sender.expect("response already sent");
+                self.pending_acks.push(PendingAck::new(sender, value));
+                Ok(())
+            }
+            _ => self.send_value(sender, Ok(value)),
+        }
+    }
+
+    fn send_value<T>(
         &self,
-        sender: Option<oneshot::Sender<Result<Option<Value>>>>,
-        value: Result<Option<Value>>,
+        sender: Option<oneshot::Sender<Result<T>>>,
+        value: Result<T>,
     ) -> Result<()> {
         sender
             .expect("response already sent")
@@ -132,49 +1051,511 @@ where
             .map_err(|_| ErrorKind::Internal("send to resp channel".to_owned()).into())
     }
 
-    async fn lookup_entry(&mut self, key: &Key) -> Result<Option<Entry>> {
-        let maybe_offset = self.index.lookup_offset(key);
+    // Read the entries in key order from the scan lower bound up to the limit.
+    async fn scan(&mut self, scan: &Scan) -> Result<ScanPage> {
+        let prefix = scan.prefix.as_deref().unwrap_or("");
+        let end = scan.end.as_deref();
 
-        let offset = match maybe_offset {
-            Some(offset) => offset,
-            None => return Ok(None),
+        // Lower bound is the greatest of prefix, start and cursor.
+        let mut from = Bound::Included(prefix);
+        if let Some(start) = scan.start.as_deref().filter(|start| *start > prefix) {
+            from = Bound::Included(start);
+        }
+        if let Some(cursor) = scan.cursor.as_deref() {
+            let lower = match from {
+                Bound::Included(key) | Bound::Excluded(key) => key,
+                Bound::Unbounded => "",
+            };
+            if cursor >= lower {
+                from = Bound::Excluded(cursor);
+            }
+        }
+
+        // Read one more key to know whether next page exists.
+        let now_ms = Utc::now().timestamp_millis();
+        let mut locations = self
+            .index
+            .range(from)
+            .filter(|(_, location)| !location.is_expired(now_ms))
+            .take_while(|(key, _)| key.starts_with(prefix) && end.map_or(true, |end| *key < end))
+            .take(scan.limit.saturating_add(1))
+            .map(|(key, location)| (key.to_owned(), location.offset))
+            .collect::<Vec<_>>();
+        let mut has_next = locations.len() > scan.limit;
+        locations.truncate(scan.limit);
+
+        let mut entries = Vec::with_capacity(locations.len());
+        let mut bytes: usize = 0;
+        for (key, offset) in locations {
+            let entry = self.read_entry(&key, offset).await?;
+            let value = self.take_value(entry).await?;
+            bytes = bytes.saturating_add(key.len() + value.len());
+            // Page ends before the entry exceeding max bytes, unless it is the first one.
+            if bytes > scan.max_bytes && !entries.is_empty() {
+                has_next = true;
+                break;
+            }
+            entries.push((Key::new(key)?, value));
+        }
+
+        let next_cursor = if has_next {
+            entries.last().map(|(key, _)| key.to_string())
+        } else {
+            None
         };
 
+        Ok(ScanPage {
+            entries,
+            next_cursor,
+        })
+    }
+
+    // Expired entry is treated as absent.
+    async fn lookup_entry(&mut self, key: &Key) -> Result<Option<Entry>> {
+        let now_ms = Utc::now().timestamp_millis();
+
+        match self.index.lookup(key) {
+            Some(location) if !location.is_expired(now_ms) => {
+                self.read_entry(key, location.offset).await.map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // Read the entry at offset without changing the write position.
+    async fn read_entry(&mut self, key: &str, offset: usize) -> Result<Entry> {
         let current = self.file.seek(SeekFrom::Current(0)).await?;
 
         self.file.seek(SeekFrom::Start(offset as u64)).await?;
-        let (_, entry) = Entry::decode_from(&mut self.file).await?;
+        let decoded = Entry::decode_from(&mut self.file).await;
+        // Restore write position even if decode failed.
         self.file.seek(SeekFrom::Start(current)).await?;
 
-        Ok(Some(entry))
+        match decoded {
+            Ok((_, entry)) => Ok(entry),
+            Err(err) => {
+                error!(%key, offset, "Lookup entry {}", err);
+                Err(err)
+            }
+        }
     }
 }
 
-impl<File> Table<File>
-where
-    File: AsyncRead + AsyncSeek + Unpin,
-{
-    pub(crate) async fn dump<F>(&mut self, mut callback: F) -> Result<()>
-    where
-        F: FnMut(EntryDump),
-    {
-        let current = self.file.seek(SeekFrom::Current(0)).await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::table::testing::{
+        commit_without_resume, delete, expire, get, mdelete, mget, mset, prepare, read_chunk, scan,
+        scan_bytes, set, set_if, set_with_ttl, try_get, try_set, ttl, write_chunks,
+    };
+    use crate::protocol::message::{Fail, FailCode};
 
-        self.file.seek(SeekFrom::Start(0)).await?;
+    #[tokio::test]
+    async fn scan_range() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut table = Table::from_path(dir.path().join("default.kvsd"), TableConfig::default())
+            .await
+            .unwrap();
 
-        loop {
-            match Entry::decode_from(&mut self.file).await {
-                Ok((_, entry)) => {
-                    callback(entry.into());
-                }
-                Err(err) if err.is_eof() => break,
-                Err(err) => {
-                    tracing::error!("{err}");
-                }
-            }
+        for key in ["b", "user:3", "a", "user:1", "user:2", "user:10", "z"] {
+            set(&mut table, key, key).await;
         }
 
-        self.file.seek(SeekFrom::Start(current)).await?;
-        Ok(())
+        let (keys, next) = scan(&mut table, None, None, None, None, 100).await;
+        assert_eq!(
+            keys,
+            vec!["a", "b", "user:1", "user:10", "user:2", "user:3", "z"]
+        );
+        assert_eq!(next, None);
+
+        let (keys, _) = scan(&mut table, Some("user:"), None, None, None, 100).await;
+        assert_eq!(keys, vec!["user:1", "user:10", "user:2", "user:3"]);
+
+        let (keys, _) = scan(&mut table, None, Some("b"), Some("user:2"), None, 100).await;
+        assert_eq!(keys, vec!["b", "user:1", "user:10"]);
+
+        // Paging with cursor.
+        let (keys, next) = scan(&mut table, Some("user:"), None, None, None, 3).await;
+        assert_eq!(keys, vec!["user:1", "user:10", "user:2"]);
+        assert_eq!(next.as_deref(), Some("user:2"));
+
+        let (keys, next) = scan(&mut table, Some("user:"), None, None, next.as_deref(), 3).await;
+        assert_eq!(keys, vec!["user:3"]);
+        assert_eq!(next, None);
+
+        // Page ends early by bytes. key and value of "user:10" are 14 bytes.
+        let (keys, next) = scan_bytes(&mut table, Some("user:"), None, 25).await;
+        assert_eq!(keys, vec!["user:1"]);
+        assert_eq!(next.as_deref(), Some("user:1"));
+        let (keys, next) = scan_bytes(&mut table, Some("user:"), next.as_deref(), 30).await;
+        assert_eq!(keys, vec!["user:10", "user:2"]);
+        assert_eq!(next.as_deref(), Some("user:2"));
+        // Entry exceeding max bytes alone is returned.
+        let (keys, next) = scan_bytes(&mut table, Some("user:"), next.as_deref(), 1).await;
+        assert_eq!(keys, vec!["user:3"]);
+        assert_eq!(next, None);
+    }
+
+    #[tokio::test]
+    async fn expiration() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("default.kvsd");
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+
+        set_with_ttl(
+            &mut table,
+            "short",
+            "short",
+            Some(Duration::from_millis(10)),
+        )
+        .await;
+        set_with_ttl(&mut table, "long", "long", Some(Duration::from_secs(3600))).await;
+        set(&mut table, "forever", "forever").await;
+
+        assert!(matches!(ttl(&mut table, "long").await, Some(Expiry::In(_))));
+        assert_eq!(ttl(&mut table, "forever").await, Some(Expiry::Never));
+        assert_eq!(ttl(&mut table, "missing").await, None);
+
+        // Persist removes the expiration.
+        assert_eq!(expire(&mut table, "long", None).await, Some(Expiry::Never));
+        assert_eq!(ttl(&mut table, "long").await, Some(Expiry::Never));
+        assert_eq!(expire(&mut table, "missing", None).await, None);
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // Expired key is absent even before swept.
+        assert!(get(&mut table, "short").await.is_none());
+        assert_eq!(ttl(&mut table, "short").await, None);
+        let (keys, _) = scan(&mut table, None, None, None, None, 100).await;
+        assert_eq!(keys, vec!["forever", "long"]);
+
+        // Sweeper writes tombstone, so the key stays deleted after reopen.
+        table.sweep_expired().await.unwrap();
+        assert_eq!(table.index.lookup("short"), None);
+        drop(table);
+
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        assert!(get(&mut table, "short").await.is_none());
+        assert!(get(&mut table, "long").await.is_some());
+        assert_eq!(ttl(&mut table, "long").await, Some(Expiry::Never));
+    }
+
+    #[tokio::test]
+    async fn conditional_set() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("default.kvsd");
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            set_if(&mut table, "k", "v1", Condition::Present).await,
+            None
+        );
+        assert_eq!(
+            set_if(&mut table, "k", "v1", Condition::Absent).await,
+            Some(1)
+        );
+        assert_eq!(set_if(&mut table, "k", "v2", Condition::Absent).await, None);
+        assert_eq!(
+            set_if(&mut table, "k", "v2", Condition::Present).await,
+            Some(2)
+        );
+
+        let value = |v: &str| Condition::Value(Value::new(v.as_bytes()).unwrap());
+        assert_eq!(set_if(&mut table, "k", "v3", value("v1")).await, None);
+        assert_eq!(set_if(&mut table, "k", "v3", value("v2")).await, Some(3));
+
+        assert_eq!(
+            set_if(&mut table, "k", "v4", Condition::Version(2)).await,
+            None
+        );
+        assert_eq!(
+            set_if(&mut table, "k", "v4", Condition::Version(3)).await,
+            Some(4)
+        );
+        // Absent key has version 0, and is versioned after the greatest version of the table.
+        assert_eq!(
+            set_if(&mut table, "new", "v1", Condition::Version(0)).await,
+            Some(5)
+        );
+
+        // Version survives expire and reopen.
+        expire(&mut table, "k", Some(Duration::from_secs(3600))).await;
+        drop(table);
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        let versioned = get(&mut table, "k").await.unwrap();
+        assert_eq!(versioned.value.as_ref(), b"v4");
+        assert_eq!(versioned.version, 4);
+    }
+
+    #[tokio::test]
+    async fn version_after_delete() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("default.kvsd");
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+
+        set(&mut table, "k", "v1").await;
+        delete(&mut table, "k").await.unwrap();
+        assert_eq!(
+            set_if(&mut table, "k", "v2", Condition::Absent).await,
+            Some(2)
+        );
+        // Version read before the delete does not match the key created again.
+        assert_eq!(
+            set_if(&mut table, "k", "v3", Condition::Version(1)).await,
+            None
+        );
+
+        // Version continues after the key expired.
+        set_with_ttl(&mut table, "k", "v3", Some(Duration::from_millis(1))).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        table.sweep_expired().await.unwrap();
+        set(&mut table, "k", "v4").await;
+        assert_eq!(get(&mut table, "k").await.unwrap().version, 4);
+
+        // Last version of the deleted key survives compaction and reopen.
+        mdelete(&mut table, &["k"]).await;
+        table.compact().await.unwrap();
+        drop(table);
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        assert!(get(&mut table, "k").await.is_none());
+        mset(&mut table, &[("k", "v5")]).await;
+        assert_eq!(get(&mut table, "k").await.unwrap().version, 5);
+    }
+
+    #[tokio::test]
+    async fn append_failure() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("default.kvsd");
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        set(&mut table, "k", "v1").await;
+
+        // Writes fail on the file opened read only, and the failure is responded.
+        table.file = fs::File::open(&path).await.unwrap();
+        table.file.seek(SeekFrom::End(0)).await.unwrap();
+        let Err(err) = try_set(&mut table, "k", "v2").await else {
+            panic!("write to the read only file must fail");
+        };
+        assert_eq!(Fail::from(&err).code(), FailCode::Internal);
+        let err = delete(&mut table, "k").await.unwrap_err();
+        assert_eq!(Fail::from(&err).code(), FailCode::Internal);
+        let len = table.len;
+
+        // Index is not changed by the failed writes.
+        let versioned = get(&mut table, "k").await.unwrap();
+        assert_eq!(
+            (versioned.value.as_ref(), versioned.version),
+            (b"v1".as_ref(), 1)
+        );
+
+        table.file = Table::open(&path).await.unwrap();
+        table.file.seek(SeekFrom::Start(len)).await.unwrap();
+        assert_eq!(
+            set_if(&mut table, "k", "v3", Condition::Version(1)).await,
+            Some(2)
+        );
+        drop(table);
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(get(&mut table, "k").await.unwrap().value.as_ref(), b"v3");
+    }
+
+    #[tokio::test]
+    async fn batch() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("default.kvsd");
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        mset(&mut table, &[("a", "a"), ("b", "b"), ("a", "a")]).await;
+        // Versions are incremented even within the batch.
+        assert_eq!(get(&mut table, "a").await.unwrap().version, 2);
+
+        let deleted = mdelete(&mut table, &["a", "missing", "a"]).await;
+        assert_eq!(
+            deleted,
+            vec![
+                Some(Previous::Value(Value::new(b"a".as_ref()).unwrap())),
+                None,
+                None
+            ]
+        );
+        drop(table);
+
+        // Batches are applied on reopen.
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        let values = mget(&mut table, &["a", "b"]).await;
+        assert_eq!(values, vec![None, Some(Value::new(b"b".as_ref()).unwrap())]);
+    }
+
+    #[tokio::test]
+    async fn transaction() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("default.kvsd");
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        set(&mut table, "a", "a").await;
+
+        // Read versions are validated.
+        let err = prepare(&mut table, &[("a", 0)], &[("b", Some("b"))], true)
+            .await
+            .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Conflict(_)));
+        assert!(get(&mut table, "b").await.is_none());
+
+        // Aborted writes are not applied.
+        let writes = prepare(&mut table, &[("a", 1)], &[("b", Some("b"))], false)
+            .await
+            .unwrap();
+        assert_eq!(writes[0].version, 2);
+        assert!(get(&mut table, "b").await.is_none());
+
+        let writes = prepare(
+            &mut table,
+            &[("a", 1), ("missing", 0)],
+            &[("a", Some("a2")), ("b", Some("b")), ("missing", None)],
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            writes.iter().map(|write| write.version).collect::<Vec<_>>(),
+            vec![2, 2, 0]
+        );
+        let a = get(&mut table, "a").await.unwrap();
+        assert_eq!((a.value.as_ref(), a.version), (b"a2".as_ref(), 2));
+        assert_eq!(get(&mut table, "b").await.unwrap().version, 2);
+
+        // Redo of the committed writes is idempotent.
+        table.apply_committed(writes).await.unwrap();
+        drop(table);
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(get(&mut table, "a").await.unwrap().version, 2);
+        assert_eq!(get(&mut table, "b").await.unwrap().version, 2);
+
+        // Table is stopped if the transaction log is kept, so later writes are not redone over.
+        let writes = commit_without_resume(&mut table, &[("a", Some("a3"))])
+            .await
+            .unwrap();
+        assert!(try_get(&mut table, "a").await.is_err());
+        drop(table);
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        table.apply_committed(writes).await.unwrap();
+        assert_eq!(get(&mut table, "a").await.unwrap().version, 3);
+    }
+
+    #[tokio::test]
+    async fn stream() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("default.kvsd");
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+
+        // Value larger than the maximum value bytes.
+        let chunks = (0..=MAX_VALUE_BYTES / MAX_CHUNK_BYTES)
+            .map(|i| vec![i as u8; MAX_CHUNK_BYTES])
+            .collect::<Vec<_>>();
+        assert_eq!(write_chunks(&mut table, "large", &chunks).await.unwrap(), 1);
+        assert!(
+            write_chunks(&mut table, "large", &[vec![0; MAX_CHUNK_BYTES + 1]])
+                .await
+                .is_err()
+        );
+
+        let mut offset = 0;
+        let mut version = None;
+        for want in &chunks {
+            let chunk = read_chunk(&mut table, "large", offset, version)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(&chunk.data, want);
+            assert_eq!(chunk.total_bytes, (chunks.len() * MAX_CHUNK_BYTES) as u64);
+            offset += chunk.data.len() as u64;
+            version = Some(chunk.version);
+        }
+        assert!(read_chunk(&mut table, "large", offset, version)
+            .await
+            .unwrap()
+            .unwrap()
+            .data
+            .is_empty());
+        let Err(err) = read_chunk(&mut table, "large", 0, Some(2)).await else {
+            panic!("changed value must not be read");
+        };
+        assert!(matches!(err.kind(), ErrorKind::Conflict(_)));
+
+        // Large value can not be returned at once.
+        let Err(err) = try_get(&mut table, "large").await else {
+            panic!("large value must not be returned");
+        };
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::Kvsd(KvsdError::MaxValueBytes { .. })
+        ));
+
+        // Small value set by stream is read as usual, and is kept by expire.
+        let small = vec![b"hello ".to_vec(), b"world".to_vec()];
+        assert_eq!(write_chunks(&mut table, "small", &small).await.unwrap(), 2);
+        expire(&mut table, "small", Some(Duration::from_secs(3600))).await;
+        let versioned = get(&mut table, "small").await.unwrap();
+        assert_eq!(versioned.value.as_ref(), b"hello world");
+        assert_eq!(versioned.version, 2);
+
+        // Value set by Set is read by chunks.
+        set(&mut table, "inline", "inline").await;
+        let chunk = read_chunk(&mut table, "inline", 2, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((chunk.data, chunk.total_bytes), (b"line".to_vec(), 6));
+        assert!(read_chunk(&mut table, "missing", 0, None)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Large value deleted is not returned, but is marked so.
+        let large = (chunks.len() * MAX_CHUNK_BYTES) as u64;
+        assert_eq!(
+            delete(&mut table, "large").await.unwrap(),
+            Some(Previous::TooLarge(large))
+        );
+
+        // Blobs are removed by compaction once they are no longer referenced.
+        drop(table);
+        let mut table = Table::from_path(&path, TableConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(get(&mut table, "small").await.unwrap().version, 2);
+
+        let blobs = dir.path().join("default.kvsd.blobs");
+        let count = || std::fs::read_dir(&blobs).unwrap().count();
+        table.compact().await.unwrap();
+        assert_eq!(count(), 1);
+        set(&mut table, "small", "small").await;
+        table.compact().await.unwrap();
+        assert_eq!(count(), 0);
     }
 }
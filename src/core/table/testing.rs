@@ -0,0 +1,334 @@
+// Helpers of the table tests.
+// each helper sends a unit of work on the default table and returns its response.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+
+use crate::common::Result;
+use crate::core::table::Table;
+use crate::core::transaction::CommittedWrite;
+use crate::core::uow::{
+    Decision, Delete, Expire, Get, MDelete, MGet, MSet, Prepare, ReadChunk, Scan, Set, SetResult,
+    Ttl, ValueChunk, Versioned, WriteChunk,
+};
+use crate::core::{Principal, UnitOfWork};
+use crate::protocol::{Condition, Expiry, Key, Previous, Value, MAX_CHUNK_BYTES};
+
+fn principal() -> Arc<Principal> {
+    Arc::new(Principal::AnonymousUser)
+}
+
+fn key(key: &str) -> Key {
+    Key::new(key).unwrap()
+}
+
+fn keys(keys: &[&str]) -> Vec<Key> {
+    keys.iter().map(|k| key(k)).collect()
+}
+
+fn value(value: &str) -> Value {
+    Value::new(value.as_bytes()).unwrap()
+}
+
+// Let the table handle the unit of work, then return its response.
+async fn request<T>(
+    table: &mut Table,
+    (uow, rx): (UnitOfWork, oneshot::Receiver<Result<T>>),
+) -> Result<T> {
+    table.handle(uow).await.unwrap();
+    rx.await.unwrap()
+}
+
+pub(super) async fn set(table: &mut Table, k: &str, v: &str) {
+    set_with(table, k, v, None, None).await;
+}
+
+pub(super) async fn set_with_ttl(table: &mut Table, k: &str, v: &str, ttl: Option<Duration>) {
+    set_with(table, k, v, ttl, None).await;
+}
+
+// Return the version of the written value.
+pub(super) async fn set_if(
+    table: &mut Table,
+    k: &str,
+    v: &str,
+    condition: Condition,
+) -> Option<u64> {
+    match set_with(table, k, v, None, Some(condition)).await {
+        SetResult::Applied { version, .. } => Some(version),
+        SetResult::ConditionFailed => None,
+    }
+}
+
+async fn set_with(
+    table: &mut Table,
+    k: &str,
+    v: &str,
+    ttl: Option<Duration>,
+    condition: Option<Condition>,
+) -> SetResult {
+    try_set_with(table, k, v, ttl, condition).await.unwrap()
+}
+
+pub(super) async fn try_set(table: &mut Table, k: &str, v: &str) -> Result<SetResult> {
+    try_set_with(table, k, v, None, None).await
+}
+
+async fn try_set_with(
+    table: &mut Table,
+    k: &str,
+    v: &str,
+    ttl: Option<Duration>,
+    condition: Option<Condition>,
+) -> Result<SetResult> {
+    let set = Set {
+        namespace: "default".into(),
+        table: "default".into(),
+        key: key(k),
+        value: value(v),
+        ttl,
+        condition,
+    };
+    request(table, UnitOfWork::new_set(principal(), set)).await
+}
+
+pub(super) async fn try_get(table: &mut Table, k: &str) -> Result<Option<Versioned>> {
+    let get = Get {
+        namespace: "default".into(),
+        table: "default".into(),
+        key: key(k),
+    };
+    request(table, UnitOfWork::new_get(principal(), get)).await
+}
+
+pub(super) async fn get(table: &mut Table, k: &str) -> Option<Versioned> {
+    try_get(table, k).await.unwrap()
+}
+
+// Return the deleted value.
+pub(super) async fn delete(table: &mut Table, k: &str) -> Result<Option<Previous>> {
+    let delete = Delete {
+        namespace: "default".into(),
+        table: "default".into(),
+        key: key(k),
+    };
+    request(table, UnitOfWork::new_delete(principal(), delete)).await
+}
+
+pub(super) async fn expire(table: &mut Table, k: &str, ttl: Option<Duration>) -> Option<Expiry> {
+    let expire = Expire {
+        namespace: "default".into(),
+        table: "default".into(),
+        key: key(k),
+        ttl,
+    };
+    request(table, UnitOfWork::new_expire(principal(), expire))
+        .await
+        .unwrap()
+}
+
+pub(super) async fn ttl(table: &mut Table, k: &str) -> Option<Expiry> {
+    let ttl = Ttl {
+        namespace: "default".into(),
+        table: "default".into(),
+        key: key(k),
+    };
+    request(table, UnitOfWork::new_ttl(principal(), ttl))
+        .await
+        .unwrap()
+}
+
+// Return the scanned keys and the cursor of the next page.
+// the values are expected to be the same as the keys.
+pub(super) async fn scan(
+    table: &mut Table,
+    prefix: Option<&str>,
+    start: Option<&str>,
+    end: Option<&str>,
+    cursor: Option<&str>,
+    limit: usize,
+) -> (Vec<String>, Option<String>) {
+    let scan = Scan {
+        namespace: "default".into(),
+        table: "default".into(),
+        prefix: prefix.map(Into::into),
+        start: start.map(Into::into),
+        end: end.map(Into::into),
+        cursor: cursor.map(Into::into),
+        limit,
+        max_bytes: usize::MAX,
+    };
+    scan_page(table, scan).await
+}
+
+// Scan the page capped by bytes instead of entries.
+pub(super) async fn scan_bytes(
+    table: &mut Table,
+    prefix: Option<&str>,
+    cursor: Option<&str>,
+    max_bytes: usize,
+) -> (Vec<String>, Option<String>) {
+    let scan = Scan {
+        namespace: "default".into(),
+        table: "default".into(),
+        prefix: prefix.map(Into::into),
+        start: None,
+        end: None,
+        cursor: cursor.map(Into::into),
+        limit: 100,
+        max_bytes,
+    };
+    scan_page(table, scan).await
+}
+
+async fn scan_page(table: &mut Table, scan: Scan) -> (Vec<String>, Option<String>) {
+    let page = request(table, UnitOfWork::new_scan(principal(), scan))
+        .await
+        .unwrap();
+
+    for (key, value) in &page.entries {
+        assert_eq!(key.as_bytes(), value.as_ref());
+    }
+    let keys = page
+        .entries
+        .into_iter()
+        .map(|(key, _)| key.into_string())
+        .collect();
+    (keys, page.next_cursor)
+}
+
+pub(super) async fn mset(table: &mut Table, entries: &[(&str, &str)]) {
+    let mset = MSet {
+        namespace: "default".into(),
+        table: "default".into(),
+        entries: entries.iter().map(|(k, v)| (key(k), value(v))).collect(),
+    };
+    request(table, UnitOfWork::new_mset(principal(), mset))
+        .await
+        .unwrap()
+}
+
+pub(super) async fn mget(table: &mut Table, ks: &[&str]) -> Vec<Option<Value>> {
+    let mget = MGet {
+        namespace: "default".into(),
+        table: "default".into(),
+        keys: keys(ks),
+    };
+    request(table, UnitOfWork::new_mget(principal(), mget))
+        .await
+        .unwrap()
+}
+
+pub(super) async fn mdelete(table: &mut Table, ks: &[&str]) -> Vec<Option<Previous>> {
+    let mdelete = MDelete {
+        namespace: "default".into(),
+        table: "default".into(),
+        keys: keys(ks),
+    };
+    request(table, UnitOfWork::new_mdelete(principal(), mdelete))
+        .await
+        .unwrap()
+}
+
+// Prepare the transaction, then commit or abort it.
+pub(super) async fn prepare(
+    table: &mut Table,
+    reads: &[(&str, u64)],
+    writes: &[(&str, Option<&str>)],
+    commit: bool,
+) -> Result<Vec<CommittedWrite>> {
+    decide(table, reads, writes, commit, true).await
+}
+
+// Commit the transaction, but do not resume the table as if the transaction log is kept.
+pub(super) async fn commit_without_resume(
+    table: &mut Table,
+    writes: &[(&str, Option<&str>)],
+) -> Result<Vec<CommittedWrite>> {
+    decide(table, &[], writes, true, false).await
+}
+
+async fn decide(
+    table: &mut Table,
+    reads: &[(&str, u64)],
+    writes: &[(&str, Option<&str>)],
+    commit: bool,
+    resume: bool,
+) -> Result<Vec<CommittedWrite>> {
+    let prepare = Prepare {
+        namespace: "default".into(),
+        table: "default".into(),
+        reads: reads
+            .iter()
+            .map(|(k, version)| (key(k), *version))
+            .collect(),
+        writes: writes.iter().map(|(k, v)| (key(k), v.map(value))).collect(),
+    };
+    let (uow, rx) = UnitOfWork::new_prepare(principal(), prepare);
+
+    // Table waits for the decision while handling prepare.
+    let decide = async move {
+        let prepared = rx.await.unwrap()?;
+        if commit {
+            let (ack, ack_rx) = oneshot::channel();
+            let (resume_tx, resume_rx) = oneshot::channel();
+            prepared
+                .decision
+                .send(Decision {
+                    ack,
+                    resume: resume_rx,
+                })
+                .ok()
+                .unwrap();
+            ack_rx.await.unwrap()?;
+            if resume {
+                resume_tx.send(()).unwrap();
+            }
+        }
+        Ok(prepared.writes)
+    };
+    let (handled, decided) = tokio::join!(table.handle(uow), decide);
+    handled.unwrap();
+    decided
+}
+
+// Return the version of the value set by the chunks.
+pub(super) async fn write_chunks(table: &mut Table, k: &str, chunks: &[Vec<u8>]) -> Result<u64> {
+    let mut upload = None;
+    for (i, data) in chunks.iter().enumerate() {
+        let write_chunk = WriteChunk {
+            namespace: "default".into(),
+            table: "default".into(),
+            key: key(k),
+            upload,
+            data: data.clone(),
+            last: i == chunks.len() - 1,
+            ttl: None,
+        };
+        let written = request(table, UnitOfWork::new_write_chunk(principal(), write_chunk)).await?;
+        upload = Some(written.upload);
+        if let Some(version) = written.version {
+            return Ok(version);
+        }
+    }
+    unreachable!()
+}
+
+pub(super) async fn read_chunk(
+    table: &mut Table,
+    k: &str,
+    offset: u64,
+    version: Option<u64>,
+) -> Result<Option<ValueChunk>> {
+    let read_chunk = ReadChunk {
+        namespace: "default".into(),
+        table: "default".into(),
+        key: key(k),
+        offset,
+        len: MAX_CHUNK_BYTES,
+        version,
+    };
+    request(table, UnitOfWork::new_read_chunk(principal(), read_chunk)).await
+}
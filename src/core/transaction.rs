@@ -0,0 +1,299 @@
+mod coordinator;
+pub(crate) use self::coordinator::{Coordinator, Participant};
+
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use bytes::{Buf, BufMut};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use crate::common::{debug, error, ErrorKind, Result};
+use crate::protocol::{Key, Value};
+
+// Write applied to the table when the transaction is committed.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CommittedWrite {
+    pub(crate) key: Key,
+    // None deletes the key.
+    pub(crate) value: Option<Value>,
+    // version of the written value.
+    pub(crate) version: u64,
+}
+
+// Committed writes of a table.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TableWrites {
+    pub(crate) namespace: String,
+    pub(crate) table: String,
+    pub(crate) writes: Vec<CommittedWrite>,
+}
+
+// Transaction log records the writes of the transaction being committed.
+//
+// Transaction is committed once its writes are synced to the log.
+// the log is cleared after every table has synced the writes, so if kvsd stops in between,
+// the writes are applied again at startup.
+// the log holds one transaction at most. the log not cleared is never overwritten,
+// as the transaction in it has to be redone at startup.
+//
+// Format
+// | magic(8) | tables(8) | table... | crc_checksum(4) |
+//
+// table
+// | namespace_bytes(8) | namespace | table_bytes(8) | table | writes(8) | write... |
+//
+// write
+// | key_bytes(8) | key | state(1) | version(8) | value_bytes(8) | value |
+pub(crate) struct TransactionLog {
+    path: PathBuf,
+}
+
+impl TransactionLog {
+    const MAGIC: &'static [u8; 8] = b"KVSDTXLG";
+    const STATE_SET: u8 = 1;
+    const STATE_DELETE: u8 = 2;
+
+    pub(crate) fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    // Load the writes of the transaction committed but not cleared.
+    // partially written log means the transaction was not committed.
+    pub(crate) async fn load(&self) -> Result<Vec<TableWrites>> {
+        let buf = match fs::read(&self.path).await {
+            Ok(buf) => buf,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        if buf.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        match TransactionLog::decode(&buf) {
+            Ok(tables) => Ok(tables),
+            Err(err) => {
+                debug!(path = %self.path.display(), "Discard uncommitted transaction {}", err);
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    // Write the writes of the transaction. the transaction is committed when this returns Ok.
+    // fails without touching the log if the previous transaction is still in it.
+    pub(crate) async fn write(&self, tables: &[TableWrites]) -> Result<()> {
+        if self.is_pending().await? {
+            return Err(ErrorKind::Internal(
+                "transaction log is pending until the committed transaction is redone".to_owned(),
+            )
+            .into());
+        }
+
+        let buf = TransactionLog::encode(tables);
+        let result = async {
+            let mut f = fs::File::create(&self.path).await?;
+            f.write_all(&buf).await?;
+            f.sync_all().await
+        }
+        .await;
+
+        if let Err(err) = result {
+            // Make sure partially written log is not redone.
+            if let Err(err) = self.clear().await {
+                error!(path = %self.path.display(), "clear transaction log {}", err);
+            }
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
+    // Whether the log holds the transaction which is not cleared.
+    async fn is_pending(&self) -> Result<bool> {
+        match fs::metadata(&self.path).await {
+            Ok(metadata) => Ok(metadata.len() > 0),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub(crate) async fn clear(&self) -> Result<()> {
+        fs::File::create(&self.path).await?.sync_all().await?;
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub(crate) fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    fn encode(tables: &[TableWrites]) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.put_slice(TransactionLog::MAGIC);
+        buf.put_u64(tables.len() as u64);
+
+        for table in tables {
+            put_bytes(&mut buf, table.namespace.as_bytes());
+            put_bytes(&mut buf, table.table.as_bytes());
+            buf.put_u64(table.writes.len() as u64);
+
+            for write in &table.writes {
+                put_bytes(&mut buf, write.key.as_bytes());
+                match &write.value {
+                    Some(value) => {
+                        buf.put_u8(TransactionLog::STATE_SET);
+                        buf.put_u64(write.version);
+                        put_bytes(&mut buf, value);
+                    }
+                    None => {
+                        buf.put_u8(TransactionLog::STATE_DELETE);
+                        buf.put_u64(write.version);
+                        put_bytes(&mut buf, &[]);
+                    }
+                }
+            }
+        }
+
+        let crc_checksum = crc32fast::hash(&buf);
+        buf.put_u32(crc_checksum);
+
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> Result<Vec<TableWrites>> {
+        if buf.len() < TransactionLog::MAGIC.len() + 8 + 4 {
+            return Err(invalid("too short").into());
+        }
+        let (body, checksum) = buf.split_at(buf.len() - 4);
+        if crc32fast::hash(body) != u32::from_be_bytes(checksum.try_into().unwrap()) {
+            return Err(invalid("crc checksum mismatch").into());
+        }
+
+        let mut src = Cursor::new(body);
+        let mut magic = [0u8; 8];
+        src.copy_to_slice(&mut magic);
+        if &magic != TransactionLog::MAGIC {
+            return Err(invalid("magic mismatch").into());
+        }
+
+        let mut tables = Vec::new();
+        for _ in 0..get_u64(&mut src)? {
+            let namespace = get_string(&mut src)?;
+            let table = get_string(&mut src)?;
+
+            let mut writes = Vec::new();
+            for _ in 0..get_u64(&mut src)? {
+                let key = Key::new(get_string(&mut src)?)?;
+                if src.remaining() < 1 {
+                    return Err(invalid("unexpected end of write").into());
+                }
+                let state = src.get_u8();
+                let version = get_u64(&mut src)?;
+                let value = get_bytes(&mut src)?;
+                let value = match state {
+                    TransactionLog::STATE_SET => Some(Value::new(value)?),
+                    TransactionLog::STATE_DELETE => None,
+                    state => return Err(invalid(&format!("unexpected state {}", state)).into()),
+                };
+                writes.push(CommittedWrite {
+                    key,
+                    value,
+                    version,
+                });
+            }
+
+            tables.push(TableWrites {
+                namespace,
+                table,
+                writes,
+            });
+        }
+
+        if src.has_remaining() {
+            return Err(invalid("trailing bytes").into());
+        }
+
+        Ok(tables)
+    }
+}
+
+fn invalid(description: &str) -> ErrorKind {
+    ErrorKind::EntryDecode {
+        description: format!("transaction log: {}", description),
+    }
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.put_u64(bytes.len() as u64);
+    buf.put_slice(bytes);
+}
+
+fn get_u64(src: &mut Cursor<&[u8]>) -> Result<u64> {
+    if src.remaining() < 8 {
+        return Err(invalid("unexpected end of log").into());
+    }
+    Ok(src.get_u64())
+}
+
+fn get_bytes(src: &mut Cursor<&[u8]>) -> Result<Vec<u8>> {
+    let len = get_u64(src)?;
+    if (src.remaining() as u64) < len {
+        return Err(invalid("unexpected end of log").into());
+    }
+    let mut bytes = vec![0u8; len as usize];
+    src.copy_to_slice(&mut bytes);
+    Ok(bytes)
+}
+
+fn get_string(src: &mut Cursor<&[u8]>) -> Result<String> {
+    String::from_utf8(get_bytes(src)?).map_err(|err| invalid(&err.to_string()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn write_load_clear() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log = TransactionLog::new(dir.path().join("transaction.log"));
+
+        assert!(log.load().await.unwrap().is_empty());
+
+        let tables = vec![
+            TableWrites {
+                namespace: "default".into(),
+                table: "orders".into(),
+                writes: vec![CommittedWrite {
+                    key: Key::new("order:1").unwrap(),
+                    value: Some(Value::new(b"pending".as_ref()).unwrap()),
+                    version: 1,
+                }],
+            },
+            TableWrites {
+                namespace: "default".into(),
+                table: "inventory".into(),
+                writes: vec![CommittedWrite {
+                    key: Key::new("item:1").unwrap(),
+                    value: None,
+                    version: 0,
+                }],
+            },
+        ];
+        log.write(&tables).await.unwrap();
+        assert_eq!(log.load().await.unwrap(), tables);
+
+        // Log not cleared is not overwritten.
+        assert!(log.write(&tables[..1]).await.is_err());
+        assert_eq!(log.load().await.unwrap(), tables);
+
+        log.clear().await.unwrap();
+        assert!(log.load().await.unwrap().is_empty());
+
+        // Partially written log is not committed.
+        let buf = TransactionLog::encode(&tables);
+        std::fs::write(log.path(), &buf[..buf.len() - 1]).unwrap();
+        assert!(log.load().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot, Mutex};
+
+use crate::common::{error, warn, ErrorKind, Result};
+use crate::core::transaction::{TableWrites, TransactionLog};
+use crate::core::uow::{Decision, Prepare, Prepared};
+use crate::core::{Principal, UnitOfWork};
+
+// Coordinator commits the transaction across the tables.
+// it runs in its own task, so the dispatcher keeps handling the other unit of works meanwhile.
+//
+// 1. Lock the tables in name order, so that concurrent transactions on the same tables do not
+//    deadlock by preparing them in different order.
+// 2. Prepare the tables. each table validates the read versions and waits for the decision.
+// 3. Write the transaction log. the transaction is committed once it is synced.
+// 4. Let the tables apply the writes, then clear the log after all of them are synced.
+// 5. Resume the tables only if the log is cleared. otherwise the tables stop handling the
+//    unit of works until restart, as the redo of the transaction would overwrite them.
+pub(crate) struct Coordinator {
+    principal: Arc<Principal>,
+    // ordered by namespace and table.
+    participants: Vec<Participant>,
+    log: Arc<Mutex<TransactionLog>>,
+}
+
+// Table taking part in the transaction.
+pub(crate) struct Participant {
+    pub(crate) prepare: Prepare,
+    pub(crate) sender: mpsc::Sender<UnitOfWork>,
+    // held by the transaction from prepare until the table resumes.
+    pub(crate) lock: Arc<Mutex<()>>,
+}
+
+impl Coordinator {
+    // Attempts to clear the log before giving up on resuming the tables.
+    const CLEAR_ATTEMPTS: u32 = 3;
+    const CLEAR_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+    pub(crate) fn new(
+        principal: Arc<Principal>,
+        mut participants: Vec<Participant>,
+        log: Arc<Mutex<TransactionLog>>,
+    ) -> Self {
+        participants.sort_by(|a, b| {
+            (&a.prepare.namespace, &a.prepare.table).cmp(&(&b.prepare.namespace, &b.prepare.table))
+        });
+        Self {
+            principal,
+            participants,
+            log,
+        }
+    }
+
+    pub(crate) async fn commit(self) -> Result<()> {
+        let mut locks = Vec::with_capacity(self.participants.len());
+        for participant in &self.participants {
+            locks.push(Arc::clone(&participant.lock).lock_owned().await);
+        }
+
+        // Dropping the prepared tables on error aborts the transaction.
+        let mut prepared: Vec<(String, String, Prepared)> =
+            Vec::with_capacity(self.participants.len());
+        for Participant {
+            prepare, sender, ..
+        } in self.participants
+        {
+            let (namespace, table) = (prepare.namespace.clone(), prepare.table.clone());
+            let (uow, rx) = UnitOfWork::new_prepare(Arc::clone(&self.principal), prepare);
+            sender.send(uow).await?;
+            let p = rx
+                .await
+                .map_err(|_| ErrorKind::Internal("receive prepared".to_owned()))??;
+            prepared.push((namespace, table, p));
+        }
+
+        // Tables which are only read have nothing to apply.
+        prepared.retain(|(_, _, p)| !p.writes.is_empty());
+        if prepared.is_empty() {
+            return Ok(());
+        }
+
+        let writes = prepared
+            .iter()
+            .map(|(namespace, table, p)| TableWrites {
+                namespace: namespace.clone(),
+                table: table.clone(),
+                writes: p.writes.clone(),
+            })
+            .collect::<Vec<_>>();
+        // Log is held until it is cleared, so the next transaction does not overwrite it.
+        let log = self.log.lock().await;
+        log.write(&writes).await?;
+
+        let mut result = Ok(());
+        let mut acks = Vec::with_capacity(prepared.len());
+        let mut resumes = Vec::with_capacity(prepared.len());
+        for (namespace, table, p) in prepared {
+            let (ack_tx, ack_rx) = oneshot::channel();
+            let (resume_tx, resume_rx) = oneshot::channel::<()>();
+            let decision = Decision {
+                ack: ack_tx,
+                resume: resume_rx,
+            };
+            if p.decision.send(decision).is_ok() {
+                acks.push(ack_rx);
+            } else {
+                result = Err(ErrorKind::Internal(format!(
+                    "table {}/{} did not receive the decision",
+                    namespace, table
+                ))
+                .into());
+            }
+            resumes.push(resume_tx);
+        }
+
+        for ack in acks {
+            match ack.await {
+                Ok(Ok(())) => (),
+                Ok(Err(err)) => result = Err(err),
+                Err(_) => result = Err(ErrorKind::Internal("receive commit ack".to_owned()).into()),
+            }
+        }
+
+        // Keep the log unless all the tables synced the writes, so that they are redone at startup.
+        if result.is_ok() {
+            result = Coordinator::clear(&log).await;
+        }
+
+        match result {
+            Ok(()) => {
+                for resume in resumes {
+                    let _ = resume.send(());
+                }
+                Ok(())
+            }
+            Err(err) => {
+                // Dropping resume senders stops the tables until the transaction is redone.
+                error!("transaction log is kept until restart {}", err);
+                Err(err)
+            }
+        }
+    }
+
+    async fn clear(log: &TransactionLog) -> Result<()> {
+        let mut attempt = 1;
+        loop {
+            match log.clear().await {
+                Ok(()) => return Ok(()),
+                Err(err) if attempt < Coordinator::CLEAR_ATTEMPTS => {
+                    warn!(attempt, "clear transaction log {}", err);
+                    tokio::time::sleep(Coordinator::CLEAR_RETRY_INTERVAL).await;
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
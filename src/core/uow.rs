@@ -1,27 +1,72 @@
 mod set;
-pub(crate) use self::set::Set;
+pub(crate) use self::set::{Set, SetResult};
 
 mod get;
-pub(crate) use self::get::Get;
+pub(crate) use self::get::{Get, Versioned};
 
 mod delete;
 pub(crate) use self::delete::Delete;
 
+mod expire;
+pub(crate) use self::expire::{Expire, Ttl};
+
+mod stream;
+pub(crate) use self::stream::{ChunkWritten, ReadChunk, ValueChunk, WriteChunk};
+
+mod batch;
+pub(crate) use self::batch::{MDelete, MGet, MSet};
+
+mod transaction;
+pub(crate) use self::transaction::{Commit, Decision, Prepare, Prepared, TableKey};
+
+mod scan;
+pub(crate) use self::scan::{Scan, ScanPage};
+
+mod namespace;
+pub(crate) use self::namespace::{CreateNamespace, DropNamespace};
+
+mod table;
+pub(crate) use self::table::{CompactTable, CreateTable, DropTable, LookupTable, TableNames};
+
 use std::fmt;
 use std::sync::Arc;
 
 use tokio::sync::oneshot;
 
-use crate::common::{ErrorKind, Result, Time};
+use crate::common::{Error, ErrorKind, Result, Time};
 use crate::core::{credential, Principal};
-use crate::protocol::Value;
+use crate::protocol::{Expiry, Previous, Value};
 
 pub(crate) enum UnitOfWork {
     Authenticate(Work<Box<dyn credential::Provider + Send>, Option<Principal>>),
     Ping(Work<(), Time>),
-    Set(Work<Set, Option<Value>>),
-    Get(Work<Get, Option<Value>>),
-    Delete(Work<Delete, Option<Value>>),
+    Set(Work<Set, SetResult>),
+    Get(Work<Get, Option<Versioned>>),
+    Delete(Work<Delete, Option<Previous>>),
+    Scan(Work<Scan, ScanPage>),
+    MSet(Work<MSet, ()>),
+    // Respond the values in the order of the keys.
+    MGet(Work<MGet, Vec<Option<Value>>>),
+    // Respond the deleted values in the order of the keys.
+    MDelete(Work<MDelete, Vec<Option<Previous>>>),
+    // Respond None if the key does not exist.
+    Expire(Work<Expire, Option<Expiry>>),
+    Ttl(Work<Ttl, Option<Expiry>>),
+    WriteChunk(Work<WriteChunk, ChunkWritten>),
+    // Respond None if the key does not exist.
+    ReadChunk(Work<ReadChunk, Option<ValueChunk>>),
+    Commit(Work<Commit, ()>),
+    // Sent by the dispatcher to the tables the transaction touches.
+    Prepare(Work<Prepare, Prepared>),
+    CreateNamespace(Work<CreateNamespace, ()>),
+    DropNamespace(Work<DropNamespace, ()>),
+    CreateTable(Work<CreateTable, ()>),
+    DropTable(Work<DropTable, ()>),
+    // Respond once the compaction completes.
+    CompactTable(Work<CompactTable, ()>),
+    ListTables(Work<(), TableNames>),
+    // Respond TableNotFound if the table does not exist.
+    LookupTable(Work<LookupTable, ()>),
 }
 
 pub(crate) struct Work<Req, Res> {
@@ -74,7 +119,7 @@ impl UnitOfWork {
     pub(crate) fn new_set(
         principal: Arc<Principal>,
         set: Set,
-    ) -> (UnitOfWork, oneshot::Receiver<Result<Option<Value>>>) {
+    ) -> (UnitOfWork, oneshot::Receiver<Result<SetResult>>) {
         let (tx, rx) = oneshot::channel();
         (
             UnitOfWork::Set(Work {
@@ -89,7 +134,7 @@ impl UnitOfWork {
     pub(crate) fn new_get(
         principal: Arc<Principal>,
         get: Get,
-    ) -> (UnitOfWork, oneshot::Receiver<Result<Option<Value>>>) {
+    ) -> (UnitOfWork, oneshot::Receiver<Result<Option<Versioned>>>) {
         let (tx, rx) = oneshot::channel();
         (
             UnitOfWork::Get(Work {
@@ -104,7 +149,7 @@ impl UnitOfWork {
     pub(crate) fn new_delete(
         principal: Arc<Principal>,
         delete: Delete,
-    ) -> (UnitOfWork, oneshot::Receiver<Result<Option<Value>>>) {
+    ) -> (UnitOfWork, oneshot::Receiver<Result<Option<Previous>>>) {
         let (tx, rx) = oneshot::channel();
         (
             UnitOfWork::Delete(Work {
@@ -115,6 +160,288 @@ impl UnitOfWork {
             rx,
         )
     }
+
+    pub(crate) fn new_expire(
+        principal: Arc<Principal>,
+        expire: Expire,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<Option<Expiry>>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::Expire(Work {
+                principal,
+                request: expire,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_ttl(
+        principal: Arc<Principal>,
+        ttl: Ttl,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<Option<Expiry>>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::Ttl(Work {
+                principal,
+                request: ttl,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_write_chunk(
+        principal: Arc<Principal>,
+        write: WriteChunk,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<ChunkWritten>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::WriteChunk(Work {
+                principal,
+                request: write,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_read_chunk(
+        principal: Arc<Principal>,
+        read: ReadChunk,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<Option<ValueChunk>>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::ReadChunk(Work {
+                principal,
+                request: read,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_scan(
+        principal: Arc<Principal>,
+        scan: Scan,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<ScanPage>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::Scan(Work {
+                principal,
+                request: scan,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_mset(
+        principal: Arc<Principal>,
+        mset: MSet,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<()>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::MSet(Work {
+                principal,
+                request: mset,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_mget(
+        principal: Arc<Principal>,
+        mget: MGet,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<Vec<Option<Value>>>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::MGet(Work {
+                principal,
+                request: mget,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_mdelete(
+        principal: Arc<Principal>,
+        mdelete: MDelete,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<Vec<Option<Previous>>>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::MDelete(Work {
+                principal,
+                request: mdelete,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_commit(
+        principal: Arc<Principal>,
+        commit: Commit,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<()>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::Commit(Work {
+                principal,
+                request: commit,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_prepare(
+        principal: Arc<Principal>,
+        prepare: Prepare,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<Prepared>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::Prepare(Work {
+                principal,
+                request: prepare,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_create_namespace(
+        principal: Arc<Principal>,
+        create: CreateNamespace,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<()>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::CreateNamespace(Work {
+                principal,
+                request: create,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_drop_namespace(
+        principal: Arc<Principal>,
+        drop: DropNamespace,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<()>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::DropNamespace(Work {
+                principal,
+                request: drop,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_create_table(
+        principal: Arc<Principal>,
+        create: CreateTable,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<()>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::CreateTable(Work {
+                principal,
+                request: create,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_drop_table(
+        principal: Arc<Principal>,
+        drop: DropTable,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<()>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::DropTable(Work {
+                principal,
+                request: drop,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_compact_table(
+        principal: Arc<Principal>,
+        compact: CompactTable,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<()>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::CompactTable(Work {
+                principal,
+                request: compact,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_list_tables(
+        principal: Arc<Principal>,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<TableNames>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::ListTables(Work {
+                principal,
+                request: (),
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    pub(crate) fn new_lookup_table(
+        principal: Arc<Principal>,
+        lookup: LookupTable,
+    ) -> (UnitOfWork, oneshot::Receiver<Result<()>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            UnitOfWork::LookupTable(Work {
+                principal,
+                request: lookup,
+                response_sender: Some(tx),
+            }),
+            rx,
+        )
+    }
+
+    // Respond the error instead of applying the unit of work.
+    pub(crate) fn send_error(self, err: Error) -> Result<()> {
+        match self {
+            UnitOfWork::Authenticate(mut work) => work.send_response(Err(err)),
+            UnitOfWork::Ping(mut work) => work.send_response(Err(err)),
+            UnitOfWork::Set(mut work) => work.send_response(Err(err)),
+            UnitOfWork::Get(mut work) => work.send_response(Err(err)),
+            UnitOfWork::Delete(mut work) => work.send_response(Err(err)),
+            UnitOfWork::Scan(mut work) => work.send_response(Err(err)),
+            UnitOfWork::MSet(mut work) => work.send_response(Err(err)),
+            UnitOfWork::MGet(mut work) => work.send_response(Err(err)),
+            UnitOfWork::MDelete(mut work) => work.send_response(Err(err)),
+            UnitOfWork::Expire(mut work) => work.send_response(Err(err)),
+            UnitOfWork::Ttl(mut work) => work.send_response(Err(err)),
+            UnitOfWork::WriteChunk(mut work) => work.send_response(Err(err)),
+            UnitOfWork::ReadChunk(mut work) => work.send_response(Err(err)),
+            UnitOfWork::Commit(mut work) => work.send_response(Err(err)),
+            UnitOfWork::Prepare(mut work) => work.send_response(Err(err)),
+            UnitOfWork::CreateNamespace(mut work) => work.send_response(Err(err)),
+            UnitOfWork::DropNamespace(mut work) => work.send_response(Err(err)),
+            UnitOfWork::CreateTable(mut work) => work.send_response(Err(err)),
+            UnitOfWork::DropTable(mut work) => work.send_response(Err(err)),
+            UnitOfWork::CompactTable(mut work) => work.send_response(Err(err)),
+            UnitOfWork::ListTables(mut work) => work.send_response(Err(err)),
+            UnitOfWork::LookupTable(mut work) => work.send_response(Err(err)),
+        }
+    }
 }
 
 impl fmt::Debug for UnitOfWork {
@@ -135,6 +462,57 @@ impl fmt::Debug for UnitOfWork {
             UnitOfWork::Delete(delete) => {
                 write!(f, "{}", delete.request)
             }
+            UnitOfWork::Scan(scan) => {
+                write!(f, "{}", scan.request)
+            }
+            UnitOfWork::MSet(mset) => {
+                write!(f, "{}", mset.request)
+            }
+            UnitOfWork::MGet(mget) => {
+                write!(f, "{}", mget.request)
+            }
+            UnitOfWork::MDelete(mdelete) => {
+                write!(f, "{}", mdelete.request)
+            }
+            UnitOfWork::Expire(expire) => {
+                write!(f, "{}", expire.request)
+            }
+            UnitOfWork::Ttl(ttl) => {
+                write!(f, "{}", ttl.request)
+            }
+            UnitOfWork::WriteChunk(write) => {
+                write!(f, "{}", write.request)
+            }
+            UnitOfWork::ReadChunk(read) => {
+                write!(f, "{}", read.request)
+            }
+            UnitOfWork::Commit(commit) => {
+                write!(f, "{}", commit.request)
+            }
+            UnitOfWork::Prepare(prepare) => {
+                write!(f, "{}", prepare.request)
+            }
+            UnitOfWork::CreateNamespace(create) => {
+                write!(f, "{}", create.request)
+            }
+            UnitOfWork::DropNamespace(drop) => {
+                write!(f, "{}", drop.request)
+            }
+            UnitOfWork::CreateTable(create) => {
+                write!(f, "{}", create.request)
+            }
+            UnitOfWork::DropTable(drop) => {
+                write!(f, "{}", drop.request)
+            }
+            UnitOfWork::CompactTable(compact) => {
+                write!(f, "{}", compact.request)
+            }
+            UnitOfWork::ListTables(_) => {
+                write!(f, "ListTables")
+            }
+            UnitOfWork::LookupTable(lookup) => {
+                write!(f, "{}", lookup.request)
+            }
         }
     }
 }
@@ -0,0 +1,59 @@
+use std::fmt;
+
+use crate::protocol::{Key, Value};
+
+// Set the key values atomically.
+pub struct MSet {
+    pub namespace: String,
+    pub table: String,
+    pub entries: Vec<(Key, Value)>,
+}
+
+impl fmt::Display for MSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MSet {}/{} {} entries",
+            self.namespace,
+            self.table,
+            self.entries.len()
+        )
+    }
+}
+
+pub struct MGet {
+    pub namespace: String,
+    pub table: String,
+    pub keys: Vec<Key>,
+}
+
+impl fmt::Display for MGet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MGet {}/{} {} keys",
+            self.namespace,
+            self.table,
+            self.keys.len()
+        )
+    }
+}
+
+// Delete the keys atomically.
+pub struct MDelete {
+    pub namespace: String,
+    pub table: String,
+    pub keys: Vec<Key>,
+}
+
+impl fmt::Display for MDelete {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MDelete {}/{} {} keys",
+            self.namespace,
+            self.table,
+            self.keys.len()
+        )
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::protocol::Key;
+
+pub struct Expire {
+    pub namespace: String,
+    pub table: String,
+    pub key: Key,
+    // None removes the expiration.
+    pub ttl: Option<Duration>,
+}
+
+impl fmt::Display for Expire {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.ttl {
+            Some(ttl) => write!(
+                f,
+                "Expire {}/{} {} {:?}",
+                self.namespace, self.table, self.key, ttl
+            ),
+            None => write!(f, "Persist {}/{} {}", self.namespace, self.table, self.key),
+        }
+    }
+}
+
+pub struct Ttl {
+    pub namespace: String,
+    pub table: String,
+    pub key: Key,
+}
+
+impl fmt::Display for Ttl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Ttl {}/{} {}", self.namespace, self.table, self.key)
+    }
+}
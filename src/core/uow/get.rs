@@ -1,6 +1,6 @@
 use std::fmt;
 
-use crate::protocol::Key;
+use crate::protocol::{Key, Value};
 
 pub struct Get {
     pub namespace: String,
@@ -13,3 +13,9 @@ impl fmt::Display for Get {
         write!(f, "Get {}/{} {}", self.namespace, self.table, self.key,)
     }
 }
+
+// Value with its version.
+pub(crate) struct Versioned {
+    pub(crate) value: Value,
+    pub(crate) version: u64,
+}
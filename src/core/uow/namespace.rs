@@ -0,0 +1,21 @@
+use std::fmt;
+
+pub struct CreateNamespace {
+    pub namespace: String,
+}
+
+impl fmt::Display for CreateNamespace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CreateNamespace {}", self.namespace)
+    }
+}
+
+pub struct DropNamespace {
+    pub namespace: String,
+}
+
+impl fmt::Display for DropNamespace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DropNamespace {}", self.namespace)
+    }
+}
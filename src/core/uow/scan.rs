@@ -0,0 +1,47 @@
+use std::fmt;
+
+use crate::protocol::{Key, Value};
+
+pub struct Scan {
+    pub namespace: String,
+    pub table: String,
+    // only keys starting with prefix.
+    pub prefix: Option<String>,
+    // inclusive lower bound.
+    pub start: Option<String>,
+    // exclusive upper bound.
+    pub end: Option<String>,
+    // last key of the previous page. scan resumes after it.
+    pub cursor: Option<String>,
+    // maximum entries of the page.
+    pub limit: usize,
+    // maximum bytes of the keys and values of the page.
+    // page ends early with the cursor, but has at least one entry.
+    pub max_bytes: usize,
+}
+
+impl fmt::Display for Scan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Scan {}/{} prefix: {:?} start: {:?} end: {:?} cursor: {:?} limit: {} max_bytes: {}",
+            self.namespace,
+            self.table,
+            self.prefix,
+            self.start,
+            self.end,
+            self.cursor,
+            self.limit,
+            self.max_bytes,
+        )
+    }
+}
+
+// A page of scan result.
+#[derive(Debug, Default)]
+pub struct ScanPage {
+    // entries ordered by key.
+    pub entries: Vec<(Key, Value)>,
+    // Some if there are remaining entries.
+    pub next_cursor: Option<String>,
+}
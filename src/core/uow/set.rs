@@ -1,12 +1,28 @@
 use std::fmt;
+use std::time::Duration;
 
-use crate::protocol::{Key, Value};
+use crate::protocol::{Condition, Key, Previous, Value};
 
 pub struct Set {
     pub namespace: String,
     pub table: String,
     pub key: Key,
     pub value: Value,
+    // the key expires after ttl.
+    pub ttl: Option<Duration>,
+    // the value is written only if the condition is satisfied.
+    pub condition: Option<Condition>,
+}
+
+// Result of the set.
+pub(crate) enum SetResult {
+    // value is written. previous value and the version of the written value.
+    Applied {
+        previous: Option<Previous>,
+        version: u64,
+    },
+    // value is not written because the condition is not satisfied.
+    ConditionFailed,
 }
 
 impl fmt::Display for Set {
@@ -18,6 +34,13 @@ impl fmt::Display for Set {
             table = &self.table,
             key = &self.key,
             value = &self.value,
-        )
+        )?;
+        if let Some(ttl) = self.ttl {
+            write!(f, " ttl: {:?}", ttl)?;
+        }
+        if let Some(condition) = &self.condition {
+            write!(f, " if: {:?}", condition)?;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,69 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::protocol::Key;
+
+// Write the chunk of the value uploaded by stream.
+// chunks of the upload are written to the blob file one by one,
+// and the value is set when the last chunk is written.
+pub struct WriteChunk {
+    pub namespace: String,
+    pub table: String,
+    pub key: Key,
+    // None starts new upload.
+    pub upload: Option<u64>,
+    pub data: Vec<u8>,
+    pub last: bool,
+    pub ttl: Option<Duration>,
+}
+
+impl fmt::Display for WriteChunk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "WriteChunk {}/{} {} upload: {:?} bytes: {} last: {}",
+            self.namespace,
+            self.table,
+            self.key,
+            self.upload,
+            self.data.len(),
+            self.last
+        )
+    }
+}
+
+pub(crate) struct ChunkWritten {
+    // id of the upload to which the next chunk is written.
+    pub(crate) upload: u64,
+    // version of the value set by the last chunk.
+    pub(crate) version: Option<u64>,
+}
+
+// Read the part of the value downloaded by stream.
+pub struct ReadChunk {
+    pub namespace: String,
+    pub table: String,
+    pub key: Key,
+    pub offset: u64,
+    pub len: usize,
+    // version of the value read by the previous chunk.
+    // the read fails with conflict if the value has been changed.
+    pub version: Option<u64>,
+}
+
+impl fmt::Display for ReadChunk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ReadChunk {}/{} {} offset: {} len: {}",
+            self.namespace, self.table, self.key, self.offset, self.len
+        )
+    }
+}
+
+pub(crate) struct ValueChunk {
+    pub(crate) data: Vec<u8>,
+    pub(crate) version: u64,
+    // length of the whole value.
+    pub(crate) total_bytes: u64,
+}
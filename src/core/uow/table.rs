@@ -0,0 +1,49 @@
+use std::fmt;
+
+pub struct CreateTable {
+    pub namespace: String,
+    pub table: String,
+}
+
+impl fmt::Display for CreateTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CreateTable {}/{}", self.namespace, self.table)
+    }
+}
+
+pub struct DropTable {
+    pub namespace: String,
+    pub table: String,
+}
+
+impl fmt::Display for DropTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DropTable {}/{}", self.namespace, self.table)
+    }
+}
+
+pub struct CompactTable {
+    pub namespace: String,
+    pub table: String,
+}
+
+impl fmt::Display for CompactTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CompactTable {}/{}", self.namespace, self.table)
+    }
+}
+
+// Check the table exists, so that the connection can use it.
+pub struct LookupTable {
+    pub namespace: String,
+    pub table: String,
+}
+
+impl fmt::Display for LookupTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LookupTable {}/{}", self.namespace, self.table)
+    }
+}
+
+// (namespace, table) pairs.
+pub(crate) type TableNames = Vec<(String, String)>;
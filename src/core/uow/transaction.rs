@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use tokio::sync::oneshot;
+
+use crate::common::Result;
+use crate::core::transaction::CommittedWrite;
+use crate::protocol::{Key, Value};
+
+// Key qualified by the table.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TableKey {
+    pub namespace: String,
+    pub table: String,
+    pub key: Key,
+}
+
+impl fmt::Display for TableKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{} {}", self.namespace, self.table, self.key)
+    }
+}
+
+// Commit the writes of the transaction if none of the keys it read have been changed.
+#[derive(Default)]
+pub struct Commit {
+    // Versions of the keys when the transaction read them first. 0 means absent.
+    pub reads: BTreeMap<TableKey, u64>,
+    // Buffered writes. None deletes the key.
+    pub writes: BTreeMap<TableKey, Option<Value>>,
+}
+
+impl fmt::Display for Commit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Commit {} reads {} writes",
+            self.reads.len(),
+            self.writes.len()
+        )
+    }
+}
+
+// Validate the reads of the transaction and lock the table until the decision is made.
+pub(crate) struct Prepare {
+    pub(crate) namespace: String,
+    pub(crate) table: String,
+    pub(crate) reads: Vec<(Key, u64)>,
+    pub(crate) writes: Vec<(Key, Option<Value>)>,
+}
+
+impl fmt::Display for Prepare {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Prepare {}/{} {} reads {} writes",
+            self.namespace,
+            self.table,
+            self.reads.len(),
+            self.writes.len()
+        )
+    }
+}
+
+// Table is waiting for the decision.
+pub(crate) struct Prepared {
+    // Writes with the versions assigned by the table.
+    pub(crate) writes: Vec<CommittedWrite>,
+    pub(crate) decision: oneshot::Sender<Decision>,
+}
+
+// Decision to commit the prepared writes. dropping the sender instead aborts the transaction.
+pub(crate) struct Decision {
+    // Sent after the writes are synced to the table file.
+    pub(crate) ack: oneshot::Sender<Result<()>>,
+    // Table waits for it before handling the next unit of work,
+    // so that later writes are not overwritten by the redo of the transaction.
+    // dropping the sender stops the table until restart.
+    pub(crate) resume: oneshot::Receiver<()>,
+}
@@ -21,20 +21,22 @@ pub(crate) struct Error {
 pub(crate) enum ErrorKind {
     Io(io::Error),
     Yaml(serde_yaml::Error),
-    EntryDecode {
-        description: String,
-    },
-    UnknownMessageType {
-        message_type: u8,
-    },
+    EntryDecode { description: String },
+    UnknownMessageType { message_type: u8 },
     // Unintentional disconnection.
     ConnectionResetByPeer,
     NetworkFraming(String),
     Kvsd(KvsdError),
-    #[allow(dead_code)]
-    Unauthorized(String), // not implemented yet :(
+    Unauthorized(String),
     Unauthenticated,
     TableNotFound(String),
+    NamespaceNotFound(String),
+    AlreadyExists(String),
+    InvalidArgument(String),
+    // Transaction conflicts with other writes.
+    Conflict(String),
+    // Stored data does not match its checksum.
+    Corruption(String),
     Internal(String), // Box<dyn std::error::Error + Send + 'static> does not work :(
 }
 
@@ -57,6 +59,11 @@ impl fmt::Display for Error {
             ErrorKind::Unauthorized(err) => write!(f, "unauthorized {}", err),
             ErrorKind::Unauthenticated => write!(f, "unauthenticated"),
             ErrorKind::TableNotFound(err) => write!(f, "table {} not found", err),
+            ErrorKind::NamespaceNotFound(err) => write!(f, "namespace {} not found", err),
+            ErrorKind::AlreadyExists(err) => write!(f, "{} already exists", err),
+            ErrorKind::InvalidArgument(err) => write!(f, "invalid argument {}", err),
+            ErrorKind::Conflict(err) => write!(f, "conflict {}", err),
+            ErrorKind::Corruption(err) => write!(f, "data corruption {}", err),
             ErrorKind::Internal(err) => write!(f, "internal error {}", err),
         }
     }
@@ -146,8 +153,8 @@ impl Error {
         }
     }
 
-    pub fn is_unauthorized(&self) -> bool {
-        matches!(self.kind, ErrorKind::Unauthorized(_))
+    pub fn is_corruption(&self) -> bool {
+        matches!(self.kind, ErrorKind::Corruption(_))
     }
 
     #[allow(dead_code)]
@@ -24,6 +24,24 @@ pub enum KvsdError {
     Io(io::Error),
     /// Unauthenticated user request operations that require authentication.
     Unauthenticated,
+    /// Authenticated user is not permitted the operation.
+    Unauthorized(String),
+    /// Stored data is corrupted and could not be served.
+    Corruption(String),
+    /// Requested table does not exist.
+    TableNotFound(String),
+    /// Requested namespace does not exist.
+    NamespaceNotFound(String),
+    /// Namespace or table to be created already exists.
+    AlreadyExists(String),
+    /// Request contains invalid argument.
+    InvalidArgument(String),
+    /// Transaction is aborted because the keys it read were changed.
+    Conflict(String),
+    /// Server could not write the data.
+    ReadOnly(String),
+    /// Client and server protocols are not compatible.
+    IncompatibleProtocol(String),
     /// Etc error, maybe bug.
     Internal(Box<dyn std::error::Error + Send + Sync>),
 }
@@ -39,6 +57,15 @@ impl fmt::Display for KvsdError {
             }
             KvsdError::Io(err) => err.fmt(f),
             KvsdError::Unauthenticated => write!(f, "unauthenticated"),
+            KvsdError::Unauthorized(err) => write!(f, "unauthorized {}", err),
+            KvsdError::Corruption(err) => write!(f, "data corruption {}", err),
+            KvsdError::TableNotFound(err) => write!(f, "table not found {}", err),
+            KvsdError::NamespaceNotFound(err) => write!(f, "namespace not found {}", err),
+            KvsdError::AlreadyExists(err) => write!(f, "already exists {}", err),
+            KvsdError::InvalidArgument(err) => write!(f, "invalid argument {}", err),
+            KvsdError::Conflict(err) => write!(f, "conflict {}", err),
+            KvsdError::ReadOnly(err) => write!(f, "read only {}", err),
+            KvsdError::IncompatibleProtocol(err) => write!(f, "incompatible protocol {}", err),
             KvsdError::Internal(err) => err.fmt(f),
         }
     }
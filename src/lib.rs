@@ -16,9 +16,11 @@ pub use crate::error::KvsdError;
 /// A specialized Result type for kvsd operations.
 pub type Result<T, E = crate::error::KvsdError> = std::result::Result<T, E>;
 
-pub use protocol::{Key, Value};
+pub use protocol::{Condition, Expiry, Key, Previous, Value};
 
 pub(crate) mod common {
+    pub(crate) mod glob;
+
     pub(crate) type Result<T, E = crate::error::internal::Error> = std::result::Result<T, E>;
 
     pub(crate) type Error = crate::error::internal::Error;
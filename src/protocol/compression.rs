@@ -0,0 +1,130 @@
+use std::io::{self, Read};
+
+use crate::protocol::message::Hello;
+
+// Compression of the bytes frames.
+// the compression is negotiated by the features of Hello, and the bytes frames not smaller than
+// MIN_BYTES are sent compressed if the compression makes them smaller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Compression {
+    Zstd,
+    Lz4,
+}
+
+impl Compression {
+    // In the order of preference in the negotiation.
+    pub(crate) const ALL: [Compression; 2] = [Compression::Zstd, Compression::Lz4];
+
+    // Smaller bytes are sent uncompressed, as the compression hardly pays off.
+    pub(crate) const MIN_BYTES: usize = 1024;
+
+    const ZSTD_LEVEL: i32 = 3;
+    // The window the peer can make the decoder allocate is limited to 8 MiB.
+    const ZSTD_WINDOW_LOG_MAX: u32 = 23;
+    // A lz4 block can not be decompressed to more than 255 times its size.
+    const LZ4_MAX_RATIO: usize = 255;
+
+    // Feature of Hello by which the compression is negotiated.
+    pub(crate) fn feature(self) -> &'static str {
+        match self {
+            Compression::Zstd => "zstd",
+            Compression::Lz4 => "lz4",
+        }
+    }
+
+    // Compression enabled by the negotiated Hello.
+    pub(crate) fn negotiated(hello: &Hello) -> Option<Compression> {
+        Compression::ALL
+            .into_iter()
+            .find(|compression| hello.supports(compression.feature()))
+    }
+
+    pub(crate) fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::Zstd => zstd::bulk::compress(data, Compression::ZSTD_LEVEL),
+            Compression::Lz4 => Ok(lz4_flex::block::compress(data)),
+        }
+    }
+
+    // Decompress the data of the given decompressed length.
+    // the length claimed by the peer is not allocated unless the data can be decompressed to it.
+    pub(crate) fn decompress(self, data: &[u8], len: usize) -> io::Result<Vec<u8>> {
+        let decompressed = match self {
+            Compression::Zstd => {
+                let mut decoder = zstd::stream::read::Decoder::with_buffer(data)?;
+                decoder.window_log_max(Compression::ZSTD_WINDOW_LOG_MAX)?;
+
+                let mut decompressed = Vec::new();
+                decoder
+                    .take((len as u64).saturating_add(1))
+                    .read_to_end(&mut decompressed)?;
+                decompressed
+            }
+            Compression::Lz4 => {
+                if len > data.len().saturating_mul(Compression::LZ4_MAX_RATIO) {
+                    return Err(invalid_data("lz4 decompressed length is too large"));
+                }
+                lz4_flex::block::decompress(data, len).map_err(invalid_data)?
+            }
+        };
+
+        if decompressed.len() != len {
+            return Err(invalid_data("decompressed length mismatch"));
+        }
+        Ok(decompressed)
+    }
+}
+
+impl From<Compression> for u8 {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::Zstd => b'z',
+            Compression::Lz4 => b'l',
+        }
+    }
+}
+
+impl TryFrom<u8> for Compression {
+    type Error = u8;
+
+    fn try_from(b: u8) -> Result<Self, Self::Error> {
+        match b {
+            b'z' => Ok(Compression::Zstd),
+            b'l' => Ok(Compression::Lz4),
+            b => Err(b),
+        }
+    }
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let data = br#"{"name":"kvsd","tags":["key","value","store"]}"#.repeat(100);
+
+        for compression in Compression::ALL {
+            let compressed = compression.compress(&data).unwrap();
+            assert!(compressed.len() < data.len());
+            assert_eq!(
+                compression.decompress(&compressed, data.len()).unwrap(),
+                data
+            );
+
+            // The claimed length must match.
+            assert!(compression.decompress(&compressed, data.len() - 1).is_err());
+            assert!(compression.decompress(&compressed, data.len() + 1).is_err());
+            assert!(compression.decompress(&compressed, usize::MAX).is_err());
+            assert!(compression.decompress(b"garbage", data.len()).is_err());
+            assert_eq!(
+                Compression::try_from(u8::from(compression)),
+                Ok(compression)
+            );
+        }
+    }
+}
@@ -7,12 +7,19 @@ use tokio::time::Duration;
 
 use crate::common::{Error, Result};
 use crate::error::internal::ErrorKind;
-use crate::protocol::message::{frameprefix, Frame, FrameError, Message, MessageFrames, DELIMITER};
+use crate::protocol::compression::Compression;
+use crate::protocol::message::{
+    frameprefix, Frame, FrameError, FrameLimits, Message, MessageFrames, RequestId, DELIMITER,
+};
 
 pub struct Connection<T = TcpStream> {
     stream: BufWriter<T>,
     // The buffer for reading frames.
     buffer: BytesMut,
+    // The limits of the frames read from the peer.
+    frame_limits: FrameLimits,
+    // The compression of the bytes frames written to the peer.
+    compression: Option<Compression>,
 }
 
 impl<T> Connection<T>
@@ -23,19 +30,61 @@ where
         Self {
             stream: BufWriter::new(stream),
             buffer: BytesMut::with_capacity(buffer_size.unwrap_or(4 * 1024)),
+            frame_limits: FrameLimits::default(),
+            compression: None,
         }
     }
 
+    pub(crate) fn frame_limits(&self) -> FrameLimits {
+        self.frame_limits
+    }
+
+    // Change the limits of the frames read after this call.
+    pub(crate) fn set_frame_limits(&mut self, limits: FrameLimits) {
+        self.frame_limits = limits;
+    }
+
+    // Compress the bytes frames written after this call.
+    pub(crate) fn set_compression(&mut self, compression: Option<Compression>) {
+        self.compression = compression;
+    }
+
     pub(crate) async fn write_message(&mut self, message: impl Into<MessageFrames>) -> Result<()> {
-        let frames = message.into();
+        self.write_message_with_id(None, message).await
+    }
+
+    pub(crate) async fn write_message_with_id(
+        &mut self,
+        request_id: Option<RequestId>,
+        message: impl Into<MessageFrames>,
+    ) -> Result<()> {
+        self.feed_message(request_id, message).await?;
+        self.flush().await
+    }
+
+    // Write the message to the buffer without flush, so that pipelined messages are sent at once.
+    pub(crate) async fn feed_message(
+        &mut self,
+        request_id: Option<RequestId>,
+        message: impl Into<MessageFrames>,
+    ) -> Result<()> {
+        let frames = message.into().with_request_id(request_id);
 
         self.stream.write_u8(frameprefix::MESSAGE_FRAMES).await?;
         self.write_decimal(frames.len()).await?;
 
+        if let Some(request_id) = frames.request_id() {
+            self.stream.write_u8(frameprefix::REQUEST_ID).await?;
+            self.write_decimal(request_id).await?;
+        }
         for frame in frames {
             self.write_frame(frame).await?
         }
 
+        Ok(())
+    }
+
+    pub(crate) async fn flush(&mut self) -> Result<()> {
         self.stream.flush().await?;
         Ok(())
     }
@@ -52,6 +101,15 @@ where
                 self.stream.write_all(DELIMITER).await?;
             }
             Frame::Bytes(val) => {
+                if let Some((compression, compressed)) = self.compress(&val)? {
+                    self.stream.write_u8(frameprefix::COMPRESSED_BYTES).await?;
+                    self.stream.write_u8(compression.into()).await?;
+                    self.write_decimal(val.len() as u64).await?;
+                    self.write_decimal(compressed.len() as u64).await?;
+                    self.stream.write_all(&compressed).await?;
+                    self.stream.write_all(DELIMITER).await?;
+                    return Ok(());
+                }
                 self.stream.write_u8(frameprefix::BYTES).await?;
                 self.write_decimal(val.len() as u64).await?;
                 self.stream.write_all(val.as_ref()).await?;
@@ -81,8 +139,20 @@ where
     }
 
     pub(crate) async fn read_message(&mut self) -> Result<Option<Message>> {
+        Ok(self
+            .read_message_with_id()
+            .await?
+            .map(|(_, message)| message))
+    }
+
+    pub(crate) async fn read_message_with_id(
+        &mut self,
+    ) -> Result<Option<(Option<RequestId>, Message)>> {
         match self.read_message_frames().await? {
-            Some(message_frames) => Ok(Some(Message::from_frames(message_frames)?)),
+            Some(message_frames) => {
+                let request_id = message_frames.request_id();
+                Ok(Some((request_id, Message::from_frames(message_frames)?)))
+            }
             None => Ok(None),
         }
     }
@@ -108,7 +178,7 @@ where
 
         let mut buf = Cursor::new(&self.buffer[..]);
 
-        match MessageFrames::check_parse(&mut buf) {
+        match MessageFrames::check_parse(&mut buf, self.frame_limits) {
             Ok(_) => {
                 let len = buf.position() as usize;
                 buf.set_position(0);
@@ -122,10 +192,22 @@ where
         }
     }
 
+    // Compress the bytes if the compression is enabled and makes them smaller.
+    fn compress(&self, val: &[u8]) -> io::Result<Option<(Compression, Vec<u8>)>> {
+        match self.compression {
+            Some(compression) if val.len() >= Compression::MIN_BYTES => {
+                let compressed = compression.compress(val)?;
+                Ok((compressed.len() < val.len()).then_some((compression, compressed)))
+            }
+            _ => Ok(None),
+        }
+    }
+
     async fn write_decimal(&mut self, val: u64) -> io::Result<()> {
         use std::io::Write;
 
-        let mut buf = [0u8; 12];
+        // u64::MAX has 20 digits.
+        let mut buf = [0u8; 20];
         let mut buf = Cursor::new(&mut buf[..]);
         write!(&mut buf, "{}", val)?;
 
@@ -139,9 +221,13 @@ where
 mod tests {
     use super::*;
     use crate::protocol::message::{
-        Authenticate, Delete, Fail, FailCode, Get, Message, Ping, Set, Success,
+        Abort, Authenticate, Begin, Chunk, Commit, CompactTable, CreateNamespace, CreateTable,
+        Delete, DropNamespace, DropTable, Expire, Fail, FailCode, Get, GetStream, Hello,
+        ListTables, MDelete, MGet, MSet, Message, Persist, Ping, Scan, ScanPage, Set, SetStream,
+        Success, Tables, Ttl, TtlResult, UseTable, Values,
     };
-    use crate::protocol::{Key, Value};
+    use crate::protocol::{Condition, Expiry, Key, Previous, Value, MAX_VALUE_BYTES};
+    use std::time::Duration;
 
     #[test]
     fn message_frames() {
@@ -151,6 +237,12 @@ mod tests {
             let mut server_conn = Connection::new(server, None);
 
             let messages: Vec<Message> = vec![
+                Message::Hello(Hello::new()),
+                Message::Hello(Hello {
+                    version: 2,
+                    message_types: vec![],
+                    features: vec![],
+                }),
                 Message::Authenticate(Authenticate::new("user", "pass")),
                 Message::Ping(Ping::new().record_client_time()),
                 Message::Success(Success::new()),
@@ -158,6 +250,13 @@ mod tests {
                 Message::Success(Success::with_value(
                     Value::new(b"a\r\nb\r\nc".as_ref()).unwrap(),
                 )),
+                Message::Success(Success::new().with_version(1)),
+                Message::Success(Success::with_previous(Previous::TooLarge(
+                    MAX_VALUE_BYTES as u64 + 1,
+                ))),
+                Message::Success(
+                    Success::with_value(Value::new(b"value1".as_ref()).unwrap()).with_version(2),
+                ),
                 Message::Fail(Fail::new(FailCode::Unauthenticated)),
                 Message::Fail(
                     Fail::new(FailCode::UnexpectedMessage).with_message("unexpected message X"),
@@ -166,8 +265,117 @@ mod tests {
                     Key::new("key1").unwrap(),
                     Value::new(b"value1".as_ref()).unwrap(),
                 )),
+                Message::Set(
+                    Set::new(
+                        Key::new("key1").unwrap(),
+                        Value::new(b"value1".as_ref()).unwrap(),
+                    )
+                    .with_ttl(Duration::from_millis(1500)),
+                ),
+                Message::Set(
+                    Set::new(
+                        Key::new("key1").unwrap(),
+                        Value::new(b"value1".as_ref()).unwrap(),
+                    )
+                    .with_condition(Condition::Absent),
+                ),
+                Message::Set(
+                    Set::new(
+                        Key::new("key1").unwrap(),
+                        Value::new(b"value1".as_ref()).unwrap(),
+                    )
+                    .with_ttl(Duration::from_millis(1500))
+                    .with_condition(Condition::Value(Value::new(b"value0".as_ref()).unwrap())),
+                ),
+                Message::Set(
+                    Set::new(
+                        Key::new("key1").unwrap(),
+                        Value::new(b"value1".as_ref()).unwrap(),
+                    )
+                    .with_condition(Condition::Version(3)),
+                ),
+                Message::Fail(Fail::new(FailCode::ConditionFailed)),
+                Message::Fail(Fail::new(FailCode::Conflict)),
+                Message::Begin(Begin::new()),
+                Message::Commit(Commit::new()),
+                Message::Abort(Abort::new()),
                 Message::Get(Get::new(Key::new("key1").unwrap())),
                 Message::Delete(Delete::new(Key::new("key1").unwrap())),
+                Message::CreateNamespace(CreateNamespace::new("ns1")),
+                Message::DropNamespace(DropNamespace::new("ns1")),
+                Message::CreateTable(CreateTable::new("ns1", "table1")),
+                Message::DropTable(DropTable::new("ns1", "table1")),
+                Message::CompactTable(CompactTable::new("ns1", "table1")),
+                Message::ListTables(ListTables::new()),
+                Message::Tables(Tables::new(vec![])),
+                Message::Tables(Tables::new(vec![
+                    ("default".into(), "default".into()),
+                    ("ns1".into(), "table1".into()),
+                ])),
+                Message::Use(UseTable::new("ns1", "table1")),
+                Message::Scan(Scan::default()),
+                Message::Scan(Scan {
+                    prefix: Some("user:".into()),
+                    start: Some("user:1".into()),
+                    end: Some("user:9".into()),
+                    cursor: Some("user:5".into()),
+                    limit: 100,
+                }),
+                Message::MSet(MSet::new(vec![
+                    (
+                        Key::new("key1").unwrap(),
+                        Value::new(b"value1".as_ref()).unwrap(),
+                    ),
+                    (
+                        Key::new("key2").unwrap(),
+                        Value::new(b"value2".as_ref()).unwrap(),
+                    ),
+                ])),
+                Message::MGet(MGet::new(vec![
+                    Key::new("key1").unwrap(),
+                    Key::new("key2").unwrap(),
+                ])),
+                Message::MDelete(MDelete::new(vec![Key::new("key1").unwrap()])),
+                Message::Values(Values::new(vec![])),
+                Message::Values(Values::new(vec![
+                    Some(Value::new(b"value1".as_ref()).unwrap()),
+                    None,
+                ])),
+                Message::Values(Values::with_previous(vec![
+                    Some(Previous::TooLarge(MAX_VALUE_BYTES as u64 + 1)),
+                    None,
+                ])),
+                Message::Expire(Expire::new(
+                    Key::new("key1").unwrap(),
+                    Duration::from_secs(60),
+                )),
+                Message::Persist(Persist::new(Key::new("key1").unwrap())),
+                Message::Ttl(Ttl::new(Key::new("key1").unwrap())),
+                Message::TtlResult(TtlResult::new(None)),
+                Message::TtlResult(TtlResult::new(Some(Expiry::Never))),
+                Message::TtlResult(TtlResult::new(Some(Expiry::In(Duration::from_millis(300))))),
+                Message::ScanPage(ScanPage::new(vec![], None)),
+                Message::ScanPage(ScanPage::new(
+                    vec![
+                        (
+                            Key::new("key1").unwrap(),
+                            Value::new(b"value1".as_ref()).unwrap(),
+                        ),
+                        (
+                            Key::new("key2").unwrap(),
+                            Value::new(b"value2".as_ref()).unwrap(),
+                        ),
+                    ],
+                    Some("key2".into()),
+                )),
+                Message::SetStream(SetStream::new(Key::new("key1").unwrap())),
+                Message::SetStream(SetStream {
+                    key: Key::new("key1").unwrap(),
+                    ttl: Some(Duration::from_secs(60)),
+                }),
+                Message::GetStream(GetStream::new(Key::new("key1").unwrap())),
+                Message::Chunk(Chunk::new(b"a\r\nb".to_vec(), false)),
+                Message::Chunk(Chunk::new(vec![], true)),
             ];
             let messages_clone = messages.clone();
 
@@ -188,4 +396,73 @@ mod tests {
             read_handle.await.unwrap();
         })
     }
+
+    #[test]
+    fn request_id() {
+        tokio_test::block_on(async move {
+            let (client, server) = tokio::io::duplex(1024);
+            let mut client_conn = Connection::new(client, None);
+            let mut server_conn = Connection::new(server, None);
+
+            let requests = vec![
+                (Some(1), Message::Get(Get::new(Key::new("key1").unwrap()))),
+                (None, Message::Ping(Ping::new())),
+                (Some(u64::MAX), Message::ListTables(ListTables::new())),
+            ];
+            for (request_id, message) in requests.clone() {
+                client_conn.feed_message(request_id, message).await.unwrap();
+            }
+            client_conn.flush().await.unwrap();
+
+            for want in requests {
+                let got = server_conn.read_message_with_id().await.unwrap().unwrap();
+                assert_eq!(want, got);
+            }
+        })
+    }
+
+    #[test]
+    fn compression() {
+        tokio_test::block_on(async move {
+            use rand::RngCore;
+
+            let json = br#"{"id":1,"name":"kvsd","tags":["a","b"]}"#.repeat(1000);
+            let mut random = vec![0; 4096];
+            rand::thread_rng().fill_bytes(&mut random);
+
+            for compression in Compression::ALL {
+                // Small or incompressible bytes are sent as is.
+                for (value, compressed) in [
+                    (json.clone(), true),
+                    (json[..Compression::MIN_BYTES - 1].to_vec(), false),
+                    (random.clone(), false),
+                ] {
+                    let (client, mut raw) = tokio::io::duplex(1024 * 1024);
+                    let mut client_conn = Connection::new(client, None);
+                    client_conn.set_compression(Some(compression));
+                    let message = Message::Chunk(Chunk::new(value.clone(), true));
+                    client_conn.write_message(message.clone()).await.unwrap();
+
+                    let mut buf = vec![0; 1024 * 1024];
+                    let n = raw.read(&mut buf).await.unwrap();
+                    assert_eq!(n < value.len(), compressed);
+
+                    let mut src = Cursor::new(&buf[..n]);
+                    MessageFrames::check_parse(&mut src, FrameLimits::default()).unwrap();
+                    assert_eq!(src.position() as usize, n);
+                    src.set_position(0);
+                    let got = Message::from_frames(MessageFrames::parse(&mut src).unwrap());
+                    assert_eq!(got.unwrap(), message);
+
+                    // Compressed bytes are not accepted before the authentication.
+                    if compressed {
+                        let mut src = Cursor::new(&buf[..n]);
+                        let checked =
+                            MessageFrames::check_parse(&mut src, FrameLimits::UNAUTHENTICATED);
+                        assert!(matches!(checked, Err(FrameError::Invalid(_))));
+                    }
+                }
+            }
+        })
+    }
 }
@@ -0,0 +1,146 @@
+use crate::common::Result;
+use crate::protocol::message::{MessageFrames, MessageType, Parse};
+use crate::protocol::{Key, Previous, Value};
+
+// Set the key values atomically.
+// frames are key and value pairs.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MSet {
+    pub(crate) entries: Vec<(Key, Value)>,
+}
+
+impl MSet {
+    pub(crate) fn new(entries: Vec<(Key, Value)>) -> Self {
+        Self { entries }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let mut entries = Vec::new();
+        while parse.has_remaining() {
+            let key = Key::new(parse.next_string()?)?;
+            let value = Value::new(parse.next_bytes()?)?;
+            entries.push((key, value));
+        }
+
+        Ok(MSet::new(entries))
+    }
+}
+
+impl From<MSet> for MessageFrames {
+    fn from(mset: MSet) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::MSet, mset.entries.len() * 2);
+
+        for (key, value) in mset.entries {
+            frames.push_string(key.into_string());
+            frames.push_bytes(value.into_boxed_bytes());
+        }
+
+        frames
+    }
+}
+
+// Get the values of the keys.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MGet {
+    pub(crate) keys: Vec<Key>,
+}
+
+impl MGet {
+    pub(crate) fn new(keys: Vec<Key>) -> Self {
+        Self { keys }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        Ok(MGet::new(parse_keys(parse)?))
+    }
+}
+
+impl From<MGet> for MessageFrames {
+    fn from(mget: MGet) -> Self {
+        key_frames(MessageType::MGet, mget.keys)
+    }
+}
+
+// Delete the keys atomically.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MDelete {
+    pub(crate) keys: Vec<Key>,
+}
+
+impl MDelete {
+    pub(crate) fn new(keys: Vec<Key>) -> Self {
+        Self { keys }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        Ok(MDelete::new(parse_keys(parse)?))
+    }
+}
+
+impl From<MDelete> for MessageFrames {
+    fn from(mdelete: MDelete) -> Self {
+        key_frames(MessageType::MDelete, mdelete.keys)
+    }
+}
+
+// Response of MGet and MDelete.
+// frames are the values in the order of the requested keys. absent value is null.
+// deleted value too large to be sent inline is its number of bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Values {
+    pub(crate) values: Vec<Option<Previous>>,
+}
+
+impl Values {
+    pub(crate) fn new(values: Vec<Option<Value>>) -> Self {
+        Values::with_previous(
+            values
+                .into_iter()
+                .map(|value| value.map(Previous::Value))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn with_previous(values: Vec<Option<Previous>>) -> Self {
+        Self { values }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let mut values = Vec::new();
+        while parse.has_remaining() {
+            values.push(parse.next_previous_or_null()?);
+        }
+
+        Ok(Values::with_previous(values))
+    }
+}
+
+impl From<Values> for MessageFrames {
+    fn from(values: Values) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::Values, values.values.len());
+
+        for value in values.values {
+            frames.push_previous_or_null(value);
+        }
+
+        frames
+    }
+}
+
+fn parse_keys(parse: &mut Parse) -> Result<Vec<Key>> {
+    let mut keys = Vec::new();
+    while parse.has_remaining() {
+        keys.push(Key::new(parse.next_string()?)?);
+    }
+    Ok(keys)
+}
+
+fn key_frames(mt: MessageType, keys: Vec<Key>) -> MessageFrames {
+    let mut frames = MessageFrames::with_capacity(mt, keys.len());
+
+    for key in keys {
+        frames.push_string(key.into_string());
+    }
+
+    frames
+}
@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use crate::common::{ErrorKind, Result};
+use crate::protocol::message::{MessageFrames, MessageType, Parse};
+use crate::protocol::{Expiry, Key};
+
+// Set the ttl of the key.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Expire {
+    pub(crate) key: Key,
+    pub(crate) ttl: Duration,
+}
+
+impl Expire {
+    pub(crate) fn new(key: Key, ttl: Duration) -> Self {
+        Self { key, ttl }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let key = Key::new(parse.next_string()?)?;
+        let ttl = Duration::from_millis(parse.next_u64()?);
+
+        parse.expect_consumed()?;
+
+        Ok(Expire::new(key, ttl))
+    }
+}
+
+impl From<Expire> for MessageFrames {
+    fn from(expire: Expire) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::Expire, 2);
+
+        frames.push_string(expire.key.into_string());
+        frames.push_u64(expire.ttl.as_millis() as u64);
+
+        frames
+    }
+}
+
+// Remove the ttl of the key.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Persist {
+    pub(crate) key: Key,
+}
+
+impl Persist {
+    pub(crate) fn new(key: Key) -> Self {
+        Self { key }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let key = Key::new(parse.next_string()?)?;
+
+        parse.expect_consumed()?;
+
+        Ok(Persist::new(key))
+    }
+}
+
+impl From<Persist> for MessageFrames {
+    fn from(persist: Persist) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::Persist, 1);
+
+        frames.push_string(persist.key.into_string());
+
+        frames
+    }
+}
+
+// Query the remaining ttl of the key.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Ttl {
+    pub(crate) key: Key,
+}
+
+impl Ttl {
+    pub(crate) fn new(key: Key) -> Self {
+        Self { key }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let key = Key::new(parse.next_string()?)?;
+
+        parse.expect_consumed()?;
+
+        Ok(Ttl::new(key))
+    }
+}
+
+impl From<Ttl> for MessageFrames {
+    fn from(ttl: Ttl) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::Ttl, 1);
+
+        frames.push_string(ttl.key.into_string());
+
+        frames
+    }
+}
+
+// Response of Expire, Persist and Ttl.
+// frame is null if the key does not exist, "NEVER" if the key does not expire,
+// otherwise remaining milliseconds.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TtlResult {
+    pub(crate) expiry: Option<Expiry>,
+}
+
+impl TtlResult {
+    const NEVER: &'static str = "NEVER";
+
+    pub(crate) fn new(expiry: Option<Expiry>) -> Self {
+        Self { expiry }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let expiry = match parse.next_string_or_null()? {
+            None => None,
+            Some(s) if s == TtlResult::NEVER => Some(Expiry::Never),
+            Some(ms) => {
+                let ms = ms
+                    .parse::<u64>()
+                    .map_err(|_| ErrorKind::NetworkFraming(format!("invalid ttl {}", ms)))?;
+                Some(Expiry::In(Duration::from_millis(ms)))
+            }
+        };
+
+        parse.expect_consumed()?;
+
+        Ok(TtlResult::new(expiry))
+    }
+}
+
+impl From<TtlResult> for MessageFrames {
+    fn from(result: TtlResult) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::TtlResult, 1);
+
+        match result.expiry {
+            None => frames.push_null(),
+            Some(Expiry::Never) => frames.push_string(TtlResult::NEVER),
+            Some(Expiry::In(ttl)) => frames.push_u64(ttl.as_millis() as u64),
+        }
+
+        frames
+    }
+}
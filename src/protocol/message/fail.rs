@@ -1,17 +1,47 @@
 use std::fmt;
+use std::io;
 
-use crate::common::Result;
+use crate::common::{Error, ErrorKind, KvsdError, Result};
 use crate::protocol::message::{MessageFrames, MessageType, Parse};
+use crate::protocol::{MAX_KYE_BYTES, MAX_VALUE_BYTES};
 
 const UNDEFINED: &str = "UNDEFINED";
 const UNAUTHENTICATED: &str = "UNAUTHENTICATED";
+const UNAUTHORIZED: &str = "UNAUTHORIZED";
 const UNEXPECTED_MESSAGE: &str = "UNEXPECTED_MESSAGE";
+const CORRUPTION: &str = "CORRUPTION";
+const TABLE_NOT_FOUND: &str = "TABLE_NOT_FOUND";
+const NAMESPACE_NOT_FOUND: &str = "NAMESPACE_NOT_FOUND";
+const ALREADY_EXISTS: &str = "ALREADY_EXISTS";
+const INVALID_ARGUMENT: &str = "INVALID_ARGUMENT";
+const CONDITION_FAILED: &str = "CONDITION_FAILED";
+const CONFLICT: &str = "CONFLICT";
+const KEY_TOO_LARGE: &str = "KEY_TOO_LARGE";
+const VALUE_TOO_LARGE: &str = "VALUE_TOO_LARGE";
+const READ_ONLY: &str = "READ_ONLY";
+const INTERNAL: &str = "INTERNAL";
+const INCOMPATIBLE_PROTOCOL: &str = "INCOMPATIBLE_PROTOCOL";
+const INVALID_FRAME: &str = "INVALID_FRAME";
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub(crate) enum FailCode {
     Undefined,
     Unauthenticated,
+    Unauthorized,
     UnexpectedMessage,
+    Corruption,
+    TableNotFound,
+    NamespaceNotFound,
+    AlreadyExists,
+    InvalidArgument,
+    ConditionFailed,
+    Conflict,
+    KeyTooLarge,
+    ValueTooLarge,
+    ReadOnly,
+    Internal,
+    IncompatibleProtocol,
+    InvalidFrame,
 }
 
 impl fmt::Display for FailCode {
@@ -22,7 +52,21 @@ impl fmt::Display for FailCode {
             match self {
                 FailCode::Undefined => UNDEFINED,
                 FailCode::Unauthenticated => UNAUTHENTICATED,
+                FailCode::Unauthorized => UNAUTHORIZED,
                 FailCode::UnexpectedMessage => UNEXPECTED_MESSAGE,
+                FailCode::Corruption => CORRUPTION,
+                FailCode::TableNotFound => TABLE_NOT_FOUND,
+                FailCode::NamespaceNotFound => NAMESPACE_NOT_FOUND,
+                FailCode::AlreadyExists => ALREADY_EXISTS,
+                FailCode::InvalidArgument => INVALID_ARGUMENT,
+                FailCode::ConditionFailed => CONDITION_FAILED,
+                FailCode::Conflict => CONFLICT,
+                FailCode::KeyTooLarge => KEY_TOO_LARGE,
+                FailCode::ValueTooLarge => VALUE_TOO_LARGE,
+                FailCode::ReadOnly => READ_ONLY,
+                FailCode::Internal => INTERNAL,
+                FailCode::IncompatibleProtocol => INCOMPATIBLE_PROTOCOL,
+                FailCode::InvalidFrame => INVALID_FRAME,
             }
         )
     }
@@ -32,7 +76,21 @@ impl From<String> for FailCode {
     fn from(s: String) -> Self {
         match s.as_str() {
             UNAUTHENTICATED => FailCode::Unauthenticated,
+            UNAUTHORIZED => FailCode::Unauthorized,
             UNEXPECTED_MESSAGE => FailCode::UnexpectedMessage,
+            CORRUPTION => FailCode::Corruption,
+            TABLE_NOT_FOUND => FailCode::TableNotFound,
+            NAMESPACE_NOT_FOUND => FailCode::NamespaceNotFound,
+            ALREADY_EXISTS => FailCode::AlreadyExists,
+            INVALID_ARGUMENT => FailCode::InvalidArgument,
+            CONDITION_FAILED => FailCode::ConditionFailed,
+            CONFLICT => FailCode::Conflict,
+            KEY_TOO_LARGE => FailCode::KeyTooLarge,
+            VALUE_TOO_LARGE => FailCode::ValueTooLarge,
+            READ_ONLY => FailCode::ReadOnly,
+            INTERNAL => FailCode::Internal,
+            INCOMPATIBLE_PROTOCOL => FailCode::IncompatibleProtocol,
+            INVALID_FRAME => FailCode::InvalidFrame,
             _ => FailCode::Undefined,
         }
     }
@@ -57,6 +115,10 @@ impl Fail {
         self
     }
 
+    pub(crate) fn code(&self) -> FailCode {
+        self.code
+    }
+
     pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
         let code = parse.next_string()?;
         let message = parse.next_string()?;
@@ -80,3 +142,103 @@ impl From<FailCode> for Fail {
         Fail::new(code)
     }
 }
+
+impl From<&Error> for Fail {
+    fn from(err: &Error) -> Self {
+        let code = match err.kind() {
+            ErrorKind::Unauthenticated => FailCode::Unauthenticated,
+            ErrorKind::Unauthorized(_) => FailCode::Unauthorized,
+            ErrorKind::Corruption(_) => FailCode::Corruption,
+            ErrorKind::TableNotFound(_) => FailCode::TableNotFound,
+            ErrorKind::NamespaceNotFound(_) => FailCode::NamespaceNotFound,
+            ErrorKind::AlreadyExists(_) => FailCode::AlreadyExists,
+            ErrorKind::InvalidArgument(_) => FailCode::InvalidArgument,
+            ErrorKind::Conflict(_) => FailCode::Conflict,
+            ErrorKind::UnknownMessageType { .. } => FailCode::UnexpectedMessage,
+            ErrorKind::NetworkFraming(_) => FailCode::InvalidFrame,
+            // The key itself is sent so that the client can restore the error.
+            ErrorKind::Kvsd(KvsdError::MaxKeyBytes { key, .. }) => {
+                return Fail::new(FailCode::KeyTooLarge).with_message(key.clone())
+            }
+            ErrorKind::Kvsd(KvsdError::MaxValueBytes { .. }) => FailCode::ValueTooLarge,
+            // Writing to the table files is not permitted.
+            ErrorKind::Io(io_err) if io_err.kind() == io::ErrorKind::PermissionDenied => {
+                FailCode::ReadOnly
+            }
+            _ => FailCode::Internal,
+        };
+        Fail::new(code).with_message(err.to_string())
+    }
+}
+
+impl From<Fail> for KvsdError {
+    fn from(fail: Fail) -> Self {
+        match fail.code {
+            FailCode::Unauthenticated => KvsdError::Unauthenticated,
+            FailCode::Unauthorized => KvsdError::Unauthorized(fail.message),
+            FailCode::Corruption => KvsdError::Corruption(fail.message),
+            FailCode::TableNotFound => KvsdError::TableNotFound(fail.message),
+            FailCode::NamespaceNotFound => KvsdError::NamespaceNotFound(fail.message),
+            FailCode::AlreadyExists => KvsdError::AlreadyExists(fail.message),
+            FailCode::InvalidArgument => KvsdError::InvalidArgument(fail.message),
+            FailCode::Conflict => KvsdError::Conflict(fail.message),
+            FailCode::KeyTooLarge => KvsdError::MaxKeyBytes {
+                key: fail.message,
+                max_bytes: MAX_KYE_BYTES,
+            },
+            FailCode::ValueTooLarge => KvsdError::MaxValueBytes {
+                max_bytes: MAX_VALUE_BYTES,
+            },
+            FailCode::ReadOnly => KvsdError::ReadOnly(fail.message),
+            FailCode::IncompatibleProtocol => KvsdError::IncompatibleProtocol(fail.message),
+            code => KvsdError::from(format!("{} {}", code, fail.message)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Key;
+
+    #[test]
+    fn error_to_fail() {
+        let fail = Fail::from(&Error::from(ErrorKind::TableNotFound("default/t1".into())));
+        assert_eq!(fail.code(), FailCode::TableNotFound);
+        assert!(matches!(KvsdError::from(fail), KvsdError::TableNotFound(_)));
+
+        let key = "k".repeat(MAX_KYE_BYTES + 1);
+        let fail = Fail::from(&Error::from(Key::new(key.clone()).unwrap_err()));
+        assert_eq!(fail.code(), FailCode::KeyTooLarge);
+        assert!(matches!(KvsdError::from(fail), KvsdError::MaxKeyBytes { key: k, .. } if k == key));
+
+        let fail = Fail::from(&Error::from(io::Error::from(
+            io::ErrorKind::PermissionDenied,
+        )));
+        assert_eq!(fail.code(), FailCode::ReadOnly);
+        assert!(matches!(KvsdError::from(fail), KvsdError::ReadOnly(_)));
+
+        let fail = Fail::from(&Error::from(ErrorKind::Internal("oops".into())));
+        assert_eq!(fail.code(), FailCode::Internal);
+        assert!(matches!(KvsdError::from(fail), KvsdError::Internal(_)));
+
+        let fail = Fail::from(&Error::from(ErrorKind::NetworkFraming("oops".into())));
+        assert_eq!(fail.code(), FailCode::InvalidFrame);
+    }
+
+    #[test]
+    fn fail_code() {
+        for code in [
+            FailCode::TableNotFound,
+            FailCode::KeyTooLarge,
+            FailCode::ValueTooLarge,
+            FailCode::ReadOnly,
+            FailCode::Internal,
+            FailCode::IncompatibleProtocol,
+            FailCode::InvalidFrame,
+        ] {
+            assert_eq!(FailCode::from(code.to_string()), code);
+        }
+        assert_eq!(FailCode::from("NEW_CODE".to_owned()), FailCode::Undefined);
+    }
+}
@@ -3,7 +3,9 @@ use std::convert::TryFrom;
 use bytes::Buf;
 
 use crate::common::{self, Time};
+use crate::protocol::compression::Compression;
 use crate::protocol::message::{MessageType, DELIMITER};
+use crate::protocol::Previous;
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum Frame {
@@ -21,8 +23,15 @@ pub(crate) mod frameprefix {
     pub(crate) const BYTES: u8 = b'$';
     pub(crate) const TIME: u8 = b'T';
     pub(crate) const NULL: u8 = b'|';
+    pub(crate) const REQUEST_ID: u8 = b'@';
+    // ~{compression}{decompressed_len}\r\n{compressed_len}\r\n{compressed}\r\n
+    // decoded as the bytes frame.
+    pub(crate) const COMPRESSED_BYTES: u8 = b'~';
 }
 
+// Identifier to match the response with the pipelined request.
+pub(crate) type RequestId = u64;
+
 #[derive(Debug)]
 pub(crate) enum Error {
     /// Not enough data is available to decode a message frames from buffer.
@@ -31,8 +40,65 @@ pub(crate) enum Error {
     Other(common::Error),
 }
 
+// Limits of the message frames read from the peer, so that the peer can not make the reader
+// buffer unbounded data by the length prefixes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FrameLimits {
+    // Maximum number of frames including the message type and request id frames.
+    pub(crate) max_frames: u64,
+    // Maximum number of bytes of the whole message frames.
+    // compressed bytes frames are counted by the decompressed length.
+    pub(crate) max_bytes: usize,
+    // Whether the compressed bytes frames are accepted.
+    pub(crate) compressed: bool,
+}
+
+impl FrameLimits {
+    // Only Hello and Authenticate are expected before the authentication.
+    pub(crate) const UNAUTHENTICATED: FrameLimits = FrameLimits {
+        max_frames: 32,
+        max_bytes: 1024 * 4,
+        compressed: false,
+    };
+
+    // Large enough for the batch of the maximum keys and several values of MAX_VALUE_BYTES.
+    pub(crate) const AUTHENTICATED: FrameLimits = FrameLimits {
+        max_frames: 4096,
+        max_bytes: 1024 * 1024 * 64,
+        compressed: true,
+    };
+
+    // Default for the responses read by the client.
+    // large enough for the scan page and the batch of several values of MAX_VALUE_BYTES,
+    // and the listing of many tables.
+    pub(crate) const CLIENT: FrameLimits = FrameLimits {
+        max_frames: 1 << 16,
+        max_bytes: 1024 * 1024 * 64,
+        compressed: true,
+    };
+
+    #[cfg(test)]
+    pub(crate) const UNLIMITED: FrameLimits = FrameLimits {
+        max_frames: u64::MAX,
+        max_bytes: usize::MAX,
+        compressed: true,
+    };
+}
+
+impl Default for FrameLimits {
+    fn default() -> Self {
+        FrameLimits::AUTHENTICATED
+    }
+}
+
+// Frames of a message.
+// request id frame precedes the message type frame if the request is pipelined.
+// *{frames}\r\n(@{request_id}\r\n)#{message_type}...
 #[derive(Clone, PartialEq, Debug)]
-pub(crate) struct MessageFrames(Vec<Frame>);
+pub(crate) struct MessageFrames {
+    frames: Vec<Frame>,
+    request_id: Option<RequestId>,
+}
 
 type ByteCursor<'a> = std::io::Cursor<&'a [u8]>;
 
@@ -40,19 +106,43 @@ impl MessageFrames {
     pub(crate) fn with_capacity(mt: MessageType, n: usize) -> Self {
         let mut v = Vec::with_capacity(n + 1);
         v.push(Frame::MessageType(mt));
-        Self(v)
+        Self {
+            frames: v,
+            request_id: None,
+        }
+    }
+
+    pub(crate) fn with_request_id(mut self, request_id: Option<RequestId>) -> Self {
+        self.request_id = request_id;
+        self
+    }
+
+    pub(crate) fn request_id(&self) -> Option<RequestId> {
+        self.request_id
     }
 
     pub(crate) fn push_string(&mut self, s: impl Into<String>) {
-        self.0.push(Frame::String(s.into()))
+        self.frames.push(Frame::String(s.into()))
+    }
+
+    pub(crate) fn push_string_or_null(&mut self, s: Option<impl Into<String>>) {
+        match s {
+            Some(s) => self.push_string(s),
+            None => self.push_null(),
+        }
+    }
+
+    // Number is represented as string frame.
+    pub(crate) fn push_u64(&mut self, n: u64) {
+        self.push_string(n.to_string())
     }
 
     pub(crate) fn push_bytes(&mut self, bytes: impl Into<Vec<u8>>) {
-        self.0.push(Frame::Bytes(bytes.into()));
+        self.frames.push(Frame::Bytes(bytes.into()));
     }
 
     pub(crate) fn push_time(&mut self, time: Time) {
-        self.0.push(Frame::Time(time));
+        self.frames.push(Frame::Time(time));
     }
     pub(crate) fn push_time_or_null(&mut self, time: Option<Time>) {
         match time {
@@ -61,25 +151,65 @@ impl MessageFrames {
         }
     }
     pub(crate) fn push_null(&mut self) {
-        self.0.push(Frame::Null);
+        self.frames.push(Frame::Null);
+    }
+
+    // Previous value too large to be sent inline is represented by its number of bytes.
+    pub(crate) fn push_previous_or_null(&mut self, previous: Option<Previous>) {
+        match previous {
+            Some(Previous::Value(value)) => self.push_bytes(value.into_boxed_bytes()),
+            Some(Previous::TooLarge(bytes)) => self.push_u64(bytes),
+            None => self.push_null(),
+        }
     }
 
     pub(crate) fn len(&self) -> u64 {
-        self.0.iter().fold(0, |acc, frame| acc + frame.len())
+        let request_id = u64::from(self.request_id.is_some());
+        self.frames
+            .iter()
+            .fold(request_id, |acc, frame| acc + frame.len())
     }
 
-    pub(crate) fn check_parse(src: &mut ByteCursor) -> Result<(), Error> {
+    // Check whether the whole message frames within the limits are buffered.
+    // the frames exceeding the limits are invalid before they are buffered.
+    pub(crate) fn check_parse(src: &mut ByteCursor, limits: FrameLimits) -> Result<(), Error> {
         let frames_len = MessageFrames::ensure_prefix_format(src)?;
+        if frames_len > limits.max_frames {
+            return Err(Error::Invalid(format!(
+                "message exceeds {} frames",
+                limits.max_frames
+            )));
+        }
 
+        // Bytes by which the compressed frames grow when decompressed.
+        let mut decompressed = 0_usize;
         for _ in 0..frames_len {
-            Frame::check(src)?;
+            decompressed += Frame::check(src, limits, decompressed)?;
+            if src.position() as usize + decompressed > limits.max_bytes {
+                return Err(Error::Invalid(format!(
+                    "message exceeds {} bytes",
+                    limits.max_bytes
+                )));
+            }
         }
 
         Ok(())
     }
 
     pub(crate) fn parse(src: &mut ByteCursor) -> Result<MessageFrames, Error> {
-        let frames_len = (MessageFrames::ensure_prefix_format(src)? - 1) as usize;
+        let mut frames_len = MessageFrames::ensure_prefix_format(src)?;
+
+        let mut request_id = None;
+        if src.chunk().first() == Some(&frameprefix::REQUEST_ID) {
+            src.advance(1);
+            request_id = Some(cursor::get_decimal(src)?);
+            frames_len -= 1;
+        }
+        // Frames following the message type frame.
+        let frames_len = frames_len
+            .checked_sub(1)
+            .ok_or_else(|| Error::Invalid("message type frame expected".into()))?
+            as usize;
 
         if cursor::get_u8(src)? != frameprefix::MESSAGE_TYPE {
             return Err(Error::Invalid("message type expected".into()));
@@ -87,10 +217,13 @@ impl MessageFrames {
         let message_type = cursor::get_u8(src)?;
         let message_type = MessageType::try_from(message_type).map_err(Error::Other)?;
 
-        let mut frames = MessageFrames::with_capacity(message_type, frames_len);
+        // Each frame has at least a prefix byte, so the capacity is bounded by the buffer.
+        let mut frames =
+            MessageFrames::with_capacity(message_type, frames_len.min(src.remaining()))
+                .with_request_id(request_id);
 
         for _ in 0..frames_len {
-            frames.0.push(Frame::parse(src)?);
+            frames.frames.push(Frame::parse(src)?);
         }
 
         Ok(frames)
@@ -101,7 +234,10 @@ impl MessageFrames {
             return Err(Error::Invalid("message frames prefix expected".into()));
         }
 
-        cursor::get_decimal(src)
+        match cursor::get_decimal(src)? {
+            0 => Err(Error::Invalid("message type frame expected".into())),
+            frames_len => Ok(frames_len),
+        }
     }
 }
 
@@ -110,27 +246,61 @@ impl Frame {
         // impl when Frame::Array added
         1
     }
-    fn check(src: &mut ByteCursor) -> Result<(), Error> {
+    // Check the frame ends within the limits, counting the bytes the preceding frames grow by
+    // decompression. return the bytes this frame grows by.
+    fn check(
+        src: &mut ByteCursor,
+        limits: FrameLimits,
+        decompressed: usize,
+    ) -> Result<usize, Error> {
+        // Bytes of the message left for the frame from the current position.
+        let remaining = |src: &ByteCursor| {
+            limits
+                .max_bytes
+                .saturating_sub(decompressed)
+                .saturating_sub(src.position() as usize)
+        };
+        let exceeds = || Error::Invalid(format!("message exceeds {} bytes", limits.max_bytes));
+
         match cursor::get_u8(src)? {
             frameprefix::MESSAGE_TYPE => {
                 cursor::get_u8(src)?;
-                Ok(())
+                Ok(0)
             }
-            frameprefix::STRING => {
-                cursor::get_line(src)?;
-                Ok(())
+            frameprefix::STRING | frameprefix::TIME => {
+                cursor::get_line(src, remaining(src))?;
+                Ok(0)
             }
             frameprefix::BYTES => {
-                let len = cursor::get_decimal(src)? as usize;
-                // skip bytes length + delimiter
-                cursor::skip(src, len + 2)
+                let len = cursor::get_length(src)?;
+                // bytes length + delimiter
+                if len + 2 > remaining(src) {
+                    return Err(exceeds());
+                }
+                cursor::skip(src, len)?;
+                cursor::expect_delimiter(src)?;
+                Ok(0)
             }
-            frameprefix::TIME => {
-                cursor::get_line(src)?;
-                Ok(())
+            frameprefix::COMPRESSED_BYTES => {
+                if !limits.compressed {
+                    return Err(Error::Invalid("compressed bytes are not accepted".into()));
+                }
+                cursor::get_compression(src)?;
+                let decompressed_len = cursor::get_length(src)?;
+                let len = cursor::get_length(src)?;
+                if len + 2 > remaining(src) || decompressed_len > remaining(src) {
+                    return Err(exceeds());
+                }
+                cursor::skip(src, len)?;
+                cursor::expect_delimiter(src)?;
+                Ok(decompressed_len.saturating_sub(len))
+            }
+            frameprefix::NULL => Ok(0),
+            frameprefix::REQUEST_ID => {
+                cursor::get_decimal(src)?;
+                Ok(0)
             }
-            frameprefix::NULL => Ok(()),
-            _ => unreachable!(),
+            prefix => Err(Error::Invalid(format!("unknown frame prefix {}", prefix))),
         }
     }
     fn parse(src: &mut ByteCursor) -> Result<Frame, Error> {
@@ -139,34 +309,49 @@ impl Frame {
                 Err(Error::Invalid("unexpected message type frame".into()))
             }
             frameprefix::STRING => {
-                let line = cursor::get_line(src)?.to_vec();
+                let line = cursor::get_line(src, usize::MAX)?.to_vec();
                 let string = String::from_utf8(line).map_err(|e| Error::Invalid(e.to_string()))?;
                 Ok(Frame::String(string))
             }
             frameprefix::BYTES => {
-                let len = cursor::get_decimal(src)? as usize;
-                let n = len + 2;
-                if src.remaining() < n {
+                let len = cursor::get_length(src)?;
+                if src.remaining() < len {
                     return Err(Error::Incomplete);
                 }
                 let value = Vec::from(&src.chunk()[..len]);
 
-                cursor::skip(src, n)?;
+                cursor::skip(src, len)?;
+                cursor::expect_delimiter(src)?;
+
+                Ok(Frame::Bytes(value))
+            }
+            frameprefix::COMPRESSED_BYTES => {
+                let compression = cursor::get_compression(src)?;
+                let decompressed_len = cursor::get_length(src)?;
+                let len = cursor::get_length(src)?;
+                if src.remaining() < len {
+                    return Err(Error::Incomplete);
+                }
+                let value = compression
+                    .decompress(&src.chunk()[..len], decompressed_len)
+                    .map_err(|e| Error::Invalid(format!("invalid compressed bytes {}", e)))?;
+
+                cursor::skip(src, len)?;
+                cursor::expect_delimiter(src)?;
 
                 Ok(Frame::Bytes(value))
             }
             frameprefix::TIME => {
                 use chrono::{DateTime, Utc};
-                let line = cursor::get_line(src)?.to_vec();
+                let line = cursor::get_line(src, usize::MAX)?.to_vec();
                 let string = String::from_utf8(line).map_err(|e| Error::Invalid(e.to_string()))?;
-                Ok(Frame::Time(
-                    DateTime::parse_from_rfc3339(&string)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap(),
-                ))
+                DateTime::parse_from_rfc3339(&string)
+                    .map(|dt| Frame::Time(dt.with_timezone(&Utc)))
+                    .map_err(|e| Error::Invalid(format!("invalid time {}", e)))
             }
             frameprefix::NULL => Ok(Frame::Null),
-            _ => unreachable!(),
+            frameprefix::REQUEST_ID => Err(Error::Invalid("unexpected request id frame".into())),
+            prefix => Err(Error::Invalid(format!("unknown frame prefix {}", prefix))),
         }
     }
 }
@@ -176,7 +361,7 @@ impl IntoIterator for MessageFrames {
     type IntoIter = std::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.frames.into_iter()
     }
 }
 
@@ -199,25 +384,136 @@ mod cursor {
         Ok(())
     }
 
+    pub(super) fn expect_delimiter(src: &mut ByteCursor) -> Result<(), Error> {
+        if src.remaining() < DELIMITER.len() {
+            return Err(Error::Incomplete);
+        }
+        if &src.chunk()[..DELIMITER.len()] != DELIMITER {
+            return Err(Error::Invalid("bytes is not terminated".into()));
+        }
+        src.advance(DELIMITER.len());
+        Ok(())
+    }
+
+    pub(super) fn get_compression(src: &mut ByteCursor) -> Result<Compression, Error> {
+        Compression::try_from(get_u8(src)?)
+            .map_err(|b| Error::Invalid(format!("unknown compression {}", b)))
+    }
+
     pub(super) fn get_decimal(src: &mut ByteCursor) -> Result<u64, Error> {
-        let line = get_line(src)?;
+        // u64::MAX has 20 digits.
+        let line = get_line(src, 20)?;
 
         atoi::atoi::<u64>(line)
             .ok_or_else(|| Error::Invalid("invalid protocol decimal format".into()))
     }
 
-    pub(super) fn get_line<'a>(src: &'a mut ByteCursor) -> Result<&'a [u8], Error> {
-        let start = src.position() as usize;
-        let end = src.get_ref().len() - 1;
-
-        for i in start..end {
-            if src.get_ref()[i] == DELIMITER[0] && src.get_ref()[i + 1] == DELIMITER[1] {
-                src.set_position((i + 2) as u64);
+    pub(super) fn get_length(src: &mut ByteCursor) -> Result<usize, Error> {
+        usize::try_from(get_decimal(src)?)
+            .ok()
+            .filter(|len| len.checked_add(DELIMITER.len()).is_some())
+            .ok_or_else(|| Error::Invalid("invalid bytes length".into()))
+    }
 
-                return Ok(&src.get_ref()[start..i]);
+    // Lines longer than max_bytes are invalid even if the delimiter is not buffered yet.
+    pub(super) fn get_line<'a>(
+        src: &'a mut ByteCursor,
+        max_bytes: usize,
+    ) -> Result<&'a [u8], Error> {
+        let start = src.position() as usize;
+        let buf: &[u8] = src.get_ref();
+        let buf = buf.get(start..).unwrap_or_default();
+
+        match buf.windows(DELIMITER.len()).position(|w| w == DELIMITER) {
+            Some(len) if len <= max_bytes => {
+                let line = &buf[..len];
+                src.set_position((start + len + DELIMITER.len()) as u64);
+                Ok(line)
             }
+            // The last byte may be the start of the delimiter.
+            None if buf.len() <= max_bytes.saturating_add(1) => Err(Error::Incomplete),
+            _ => Err(Error::Invalid(format!("line exceeds {} bytes", max_bytes))),
         }
+    }
+}
 
-        Err(Error::Incomplete)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn check(buf: &[u8], limits: FrameLimits) -> Result<(), Error> {
+        MessageFrames::check_parse(&mut Cursor::new(buf), limits)
+    }
+
+    #[test]
+    fn limits() {
+        let limits = FrameLimits::UNAUTHENTICATED;
+        let ping = [MessageType::Ping.into()];
+        let message = [b"*2\r\n#".as_ref(), &ping, b"+hello\r\n"].concat();
+        assert!(check(&message, limits).is_ok());
+        assert!(matches!(
+            check(&message[..message.len() - 1], limits),
+            Err(Error::Incomplete)
+        ));
+
+        // Exceeding limits are invalid before the frames are buffered.
+        assert!(matches!(check(b"*33\r\n", limits), Err(Error::Invalid(_))));
+        assert!(matches!(
+            check(b"*2\r\n$4097\r\n", limits),
+            Err(Error::Invalid(_))
+        ));
+        let line = [b"*2\r\n+".as_ref(), &[b'a'; 4096]].concat();
+        assert!(matches!(check(&line, limits), Err(Error::Invalid(_))));
+        assert!(matches!(
+            check(&line[..1024], limits),
+            Err(Error::Incomplete)
+        ));
+        // Compressed bytes are counted by the decompressed length.
+        assert!(matches!(
+            check(b"*2\r\n~z67108865\r\n5\r\n", FrameLimits::AUTHENTICATED),
+            Err(Error::Invalid(_))
+        ));
+        assert!(matches!(
+            check(b"*2\r\n~x5\r\n5\r\n", FrameLimits::AUTHENTICATED),
+            Err(Error::Invalid(_))
+        ));
+        assert!(matches!(
+            check(b"*2\r\n$18446744073709551615\r\n", FrameLimits::UNLIMITED),
+            Err(Error::Invalid(_))
+        ));
+        assert!(matches!(
+            check(b"*1111111111111111111111", FrameLimits::UNLIMITED),
+            Err(Error::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn malformed() {
+        let parse = |buf: &[u8]| MessageFrames::parse(&mut Cursor::new(buf));
+        let ping = [MessageType::Ping.into()];
+        let message = |frame: &[u8]| [b"*2\r\n#".as_ref(), &ping, frame].concat();
+
+        for buf in [
+            b"*0\r\n".to_vec(),
+            message(b"!"),
+            message(b"Tyesterday\r\n"),
+            message(b"$3\r\nabcde"),
+            message(b"+\xff\r\n"),
+        ] {
+            let checked = check(&buf, FrameLimits::AUTHENTICATED);
+            assert!(
+                matches!(checked, Err(Error::Invalid(_))) || parse(&buf).is_err(),
+                "{:?}",
+                buf
+            );
+        }
+        assert!(matches!(parse(b"*1\r\n@1\r\n#"), Err(Error::Invalid(_))));
+        assert!(matches!(parse(b""), Err(Error::Incomplete)));
+        assert!(matches!(parse(b"*"), Err(Error::Incomplete)));
+        assert!(matches!(
+            parse(b"*18446744073709551615\r\n#"),
+            Err(Error::Incomplete)
+        ));
     }
 }
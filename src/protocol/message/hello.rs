@@ -0,0 +1,184 @@
+use std::convert::TryFrom;
+
+use crate::common::Result;
+use crate::protocol::compression::Compression;
+use crate::protocol::message::{MessageFrames, MessageType, Parse};
+
+// Version of the protocol this build speaks.
+pub(crate) const PROTOCOL_VERSION: u64 = 1;
+// Oldest version of the peer protocol this build can talk with.
+pub(crate) const MIN_PROTOCOL_VERSION: u64 = 1;
+
+// Hello is the first message of the connection in which the client and the server advertise
+// the protocol version, supported message types and features.
+// the server responds with Hello containing the negotiated version and the features both support,
+// or Fail if the client is incompatible.
+// frames are version, message types as bytes, and features.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Hello {
+    pub(crate) version: u64,
+    pub(crate) message_types: Vec<MessageType>,
+    pub(crate) features: Vec<String>,
+}
+
+impl Hello {
+    // Requests with id are handled concurrently.
+    pub(crate) const PIPELINING: &'static str = "pipelining";
+
+    // Hello advertising what this build supports.
+    pub(crate) fn new() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            message_types: MessageType::ALL.to_vec(),
+            features: [Hello::PIPELINING]
+                .into_iter()
+                .chain(Compression::ALL.map(Compression::feature))
+                .map(str::to_owned)
+                .collect(),
+        }
+    }
+
+    // Protocol of the client which does not send Hello.
+    // it is built before Hello, so it speaks the first version without any features.
+    pub(crate) fn legacy() -> Self {
+        Self {
+            version: MIN_PROTOCOL_VERSION,
+            message_types: MessageType::ALL.to_vec(),
+            features: Vec::new(),
+        }
+    }
+
+    // Hello not advertising any compression.
+    pub(crate) fn without_compression(mut self) -> Self {
+        self.features
+            .retain(|feature| Compression::ALL.iter().all(|c| c.feature() != feature));
+        self
+    }
+
+    // Whether the peer speaking the version can talk with this build.
+    pub(crate) fn is_compatible(&self) -> bool {
+        self.version >= MIN_PROTOCOL_VERSION
+    }
+
+    // Negotiate with the peer hello.
+    // the version is the lower one and the features are those both support.
+    // only the most preferred compression of them is kept, so that both use the same one.
+    pub(crate) fn negotiate(&self, peer: &Hello) -> Hello {
+        let negotiated = Hello {
+            version: self.version.min(peer.version),
+            message_types: self
+                .message_types
+                .iter()
+                .filter(|mt| peer.message_types.contains(mt))
+                .copied()
+                .collect(),
+            features: self
+                .features
+                .iter()
+                .filter(|feature| peer.features.contains(feature))
+                .cloned()
+                .collect(),
+        };
+
+        match Compression::negotiated(&negotiated) {
+            Some(compression) => {
+                let mut negotiated = negotiated.without_compression();
+                negotiated.features.push(compression.feature().to_owned());
+                negotiated
+            }
+            None => negotiated,
+        }
+    }
+
+    pub(crate) fn supports(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let version = parse.next_u64()?;
+        // Message types unknown to this build are ignored.
+        let message_types = parse
+            .next_bytes()?
+            .into_iter()
+            .filter_map(|mt| MessageType::try_from(mt).ok())
+            .collect();
+        let mut features = Vec::new();
+        while parse.has_remaining() {
+            features.push(parse.next_string()?);
+        }
+
+        Ok(Hello {
+            version,
+            message_types,
+            features,
+        })
+    }
+}
+
+impl From<Hello> for MessageFrames {
+    fn from(hello: Hello) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::Hello, 2 + hello.features.len());
+
+        frames.push_u64(hello.version);
+        frames.push_bytes(
+            hello
+                .message_types
+                .into_iter()
+                .map(u8::from)
+                .collect::<Vec<_>>(),
+        );
+        for feature in hello.features {
+            frames.push_string(feature);
+        }
+
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiate() {
+        let server = Hello::new();
+        let client = Hello {
+            version: PROTOCOL_VERSION + 1,
+            message_types: vec![MessageType::Ping, MessageType::Get],
+            features: vec!["unknown".into(), Hello::PIPELINING.into()],
+        };
+
+        let negotiated = server.negotiate(&client);
+        assert_eq!(negotiated.version, PROTOCOL_VERSION);
+        assert_eq!(
+            negotiated.message_types,
+            vec![MessageType::Ping, MessageType::Get]
+        );
+        assert!(negotiated.supports(Hello::PIPELINING));
+        assert!(!negotiated.supports("unknown"));
+
+        assert_eq!(Compression::negotiated(&negotiated), None);
+
+        // The preferred compression of those both support is chosen.
+        let negotiated = server.negotiate(&Hello::new());
+        assert_eq!(
+            Compression::negotiated(&negotiated),
+            Some(Compression::Zstd)
+        );
+        assert!(!negotiated.supports(Compression::Lz4.feature()));
+        let negotiated = server.negotiate(&Hello {
+            features: vec![Compression::Lz4.feature().into()],
+            ..Hello::new()
+        });
+        assert_eq!(Compression::negotiated(&negotiated), Some(Compression::Lz4));
+        let negotiated = server.without_compression().negotiate(&Hello::new());
+        assert_eq!(Compression::negotiated(&negotiated), None);
+
+        assert!(client.is_compatible());
+        assert!(!Hello {
+            version: 0,
+            ..Hello::new()
+        }
+        .is_compatible());
+    }
+}
@@ -2,7 +2,10 @@ use std::convert::TryFrom;
 
 use crate::common::{Error, ErrorKind, Result};
 use crate::protocol::message::{
-    Authenticate, Delete, Fail, Get, MessageFrames, Parse, Ping, Set, Success,
+    Abort, Authenticate, Begin, Chunk, Commit, CompactTable, CreateNamespace, CreateTable, Delete,
+    DropNamespace, DropTable, Expire, Fail, Get, GetStream, Hello, ListTables, MDelete, MGet, MSet,
+    MessageFrames, Parse, Persist, Ping, Scan, ScanPage, Set, SetStream, Success, Tables, Ttl,
+    TtlResult, UseTable, Values,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -14,6 +17,69 @@ pub(crate) enum MessageType {
     Set = 5,
     Get = 6,
     Delete = 7,
+    CreateNamespace = 8,
+    DropNamespace = 9,
+    CreateTable = 10,
+    DropTable = 11,
+    ListTables = 12,
+    Tables = 13,
+    Use = 14,
+    Scan = 15,
+    ScanPage = 16,
+    Expire = 17,
+    Persist = 18,
+    Ttl = 19,
+    TtlResult = 20,
+    MSet = 21,
+    MGet = 22,
+    MDelete = 23,
+    Values = 24,
+    Begin = 25,
+    Commit = 26,
+    Abort = 27,
+    Hello = 28,
+    SetStream = 29,
+    GetStream = 30,
+    Chunk = 31,
+    CompactTable = 32,
+}
+
+impl MessageType {
+    // Message types this build supports.
+    pub(crate) const ALL: &'static [MessageType] = &[
+        MessageType::Ping,
+        MessageType::Authenticate,
+        MessageType::Success,
+        MessageType::Fail,
+        MessageType::Set,
+        MessageType::Get,
+        MessageType::Delete,
+        MessageType::CreateNamespace,
+        MessageType::DropNamespace,
+        MessageType::CreateTable,
+        MessageType::DropTable,
+        MessageType::ListTables,
+        MessageType::Tables,
+        MessageType::Use,
+        MessageType::Scan,
+        MessageType::ScanPage,
+        MessageType::Expire,
+        MessageType::Persist,
+        MessageType::Ttl,
+        MessageType::TtlResult,
+        MessageType::MSet,
+        MessageType::MGet,
+        MessageType::MDelete,
+        MessageType::Values,
+        MessageType::Begin,
+        MessageType::Commit,
+        MessageType::Abort,
+        MessageType::Hello,
+        MessageType::SetStream,
+        MessageType::GetStream,
+        MessageType::Chunk,
+        MessageType::CompactTable,
+    ];
 }
 
 impl From<MessageType> for u8 {
@@ -33,6 +99,31 @@ impl TryFrom<u8> for MessageType {
             5 => Ok(MessageType::Set),
             6 => Ok(MessageType::Get),
             7 => Ok(MessageType::Delete),
+            8 => Ok(MessageType::CreateNamespace),
+            9 => Ok(MessageType::DropNamespace),
+            10 => Ok(MessageType::CreateTable),
+            11 => Ok(MessageType::DropTable),
+            12 => Ok(MessageType::ListTables),
+            13 => Ok(MessageType::Tables),
+            14 => Ok(MessageType::Use),
+            15 => Ok(MessageType::Scan),
+            16 => Ok(MessageType::ScanPage),
+            17 => Ok(MessageType::Expire),
+            18 => Ok(MessageType::Persist),
+            19 => Ok(MessageType::Ttl),
+            20 => Ok(MessageType::TtlResult),
+            21 => Ok(MessageType::MSet),
+            22 => Ok(MessageType::MGet),
+            23 => Ok(MessageType::MDelete),
+            24 => Ok(MessageType::Values),
+            25 => Ok(MessageType::Begin),
+            26 => Ok(MessageType::Commit),
+            27 => Ok(MessageType::Abort),
+            28 => Ok(MessageType::Hello),
+            29 => Ok(MessageType::SetStream),
+            30 => Ok(MessageType::GetStream),
+            31 => Ok(MessageType::Chunk),
+            32 => Ok(MessageType::CompactTable),
             _ => Err(Error::from(ErrorKind::UnknownMessageType {
                 message_type: n,
             })),
@@ -49,6 +140,31 @@ pub(crate) enum Message {
     Set(Set),
     Get(Get),
     Delete(Delete),
+    CreateNamespace(CreateNamespace),
+    DropNamespace(DropNamespace),
+    CreateTable(CreateTable),
+    DropTable(DropTable),
+    ListTables(ListTables),
+    Tables(Tables),
+    Use(UseTable),
+    Scan(Scan),
+    ScanPage(ScanPage),
+    Expire(Expire),
+    Persist(Persist),
+    Ttl(Ttl),
+    TtlResult(TtlResult),
+    MSet(MSet),
+    MGet(MGet),
+    MDelete(MDelete),
+    Values(Values),
+    Begin(Begin),
+    Commit(Commit),
+    Abort(Abort),
+    Hello(Hello),
+    SetStream(SetStream),
+    GetStream(GetStream),
+    Chunk(Chunk),
+    CompactTable(CompactTable),
 }
 
 impl Message {
@@ -68,6 +184,39 @@ impl Message {
             MessageType::Set => Message::Set(Set::parse_frames(&mut parse)?),
             MessageType::Get => Message::Get(Get::parse_frames(&mut parse)?),
             MessageType::Delete => Message::Delete(Delete::parse_frames(&mut parse)?),
+            MessageType::CreateNamespace => {
+                Message::CreateNamespace(CreateNamespace::parse_frames(&mut parse)?)
+            }
+            MessageType::DropNamespace => {
+                Message::DropNamespace(DropNamespace::parse_frames(&mut parse)?)
+            }
+            MessageType::CreateTable => {
+                Message::CreateTable(CreateTable::parse_frames(&mut parse)?)
+            }
+            MessageType::DropTable => Message::DropTable(DropTable::parse_frames(&mut parse)?),
+            MessageType::ListTables => Message::ListTables(ListTables::parse_frames(&mut parse)?),
+            MessageType::Tables => Message::Tables(Tables::parse_frames(&mut parse)?),
+            MessageType::Use => Message::Use(UseTable::parse_frames(&mut parse)?),
+            MessageType::Scan => Message::Scan(Scan::parse_frames(&mut parse)?),
+            MessageType::ScanPage => Message::ScanPage(ScanPage::parse_frames(&mut parse)?),
+            MessageType::Expire => Message::Expire(Expire::parse_frames(&mut parse)?),
+            MessageType::Persist => Message::Persist(Persist::parse_frames(&mut parse)?),
+            MessageType::Ttl => Message::Ttl(Ttl::parse_frames(&mut parse)?),
+            MessageType::TtlResult => Message::TtlResult(TtlResult::parse_frames(&mut parse)?),
+            MessageType::MSet => Message::MSet(MSet::parse_frames(&mut parse)?),
+            MessageType::MGet => Message::MGet(MGet::parse_frames(&mut parse)?),
+            MessageType::MDelete => Message::MDelete(MDelete::parse_frames(&mut parse)?),
+            MessageType::Values => Message::Values(Values::parse_frames(&mut parse)?),
+            MessageType::Begin => Message::Begin(Begin::parse_frames(&mut parse)?),
+            MessageType::Commit => Message::Commit(Commit::parse_frames(&mut parse)?),
+            MessageType::Abort => Message::Abort(Abort::parse_frames(&mut parse)?),
+            MessageType::Hello => Message::Hello(Hello::parse_frames(&mut parse)?),
+            MessageType::SetStream => Message::SetStream(SetStream::parse_frames(&mut parse)?),
+            MessageType::GetStream => Message::GetStream(GetStream::parse_frames(&mut parse)?),
+            MessageType::Chunk => Message::Chunk(Chunk::parse_frames(&mut parse)?),
+            MessageType::CompactTable => {
+                Message::CompactTable(CompactTable::parse_frames(&mut parse)?)
+            }
         };
 
         Ok(message)
@@ -84,6 +233,31 @@ impl From<Message> for MessageFrames {
             Message::Set(m) => m.into(),
             Message::Get(m) => m.into(),
             Message::Delete(m) => m.into(),
+            Message::CreateNamespace(m) => m.into(),
+            Message::DropNamespace(m) => m.into(),
+            Message::CreateTable(m) => m.into(),
+            Message::DropTable(m) => m.into(),
+            Message::ListTables(m) => m.into(),
+            Message::Tables(m) => m.into(),
+            Message::Use(m) => m.into(),
+            Message::Scan(m) => m.into(),
+            Message::ScanPage(m) => m.into(),
+            Message::Expire(m) => m.into(),
+            Message::Persist(m) => m.into(),
+            Message::Ttl(m) => m.into(),
+            Message::TtlResult(m) => m.into(),
+            Message::MSet(m) => m.into(),
+            Message::MGet(m) => m.into(),
+            Message::MDelete(m) => m.into(),
+            Message::Values(m) => m.into(),
+            Message::Begin(m) => m.into(),
+            Message::Commit(m) => m.into(),
+            Message::Abort(m) => m.into(),
+            Message::Hello(m) => m.into(),
+            Message::SetStream(m) => m.into(),
+            Message::GetStream(m) => m.into(),
+            Message::Chunk(m) => m.into(),
+            Message::CompactTable(m) => m.into(),
         }
     }
 }
@@ -1,6 +1,9 @@
 mod authentication;
 pub(crate) use authentication::Authenticate;
 
+mod hello;
+pub(crate) use hello::{Hello, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+
 mod ping;
 pub(crate) use ping::Ping;
 
@@ -14,7 +17,9 @@ mod message;
 pub(crate) use message::{Message, MessageType};
 
 mod frame;
-pub(crate) use frame::{frameprefix, Error as FrameError, Frame, MessageFrames};
+pub(crate) use frame::{
+    frameprefix, Error as FrameError, Frame, FrameLimits, MessageFrames, RequestId,
+};
 
 mod parse;
 pub(crate) use parse::{Parse, ParseError};
@@ -28,4 +33,28 @@ pub(crate) use get::Get;
 mod delete;
 pub(crate) use delete::Delete;
 
+mod batch;
+pub(crate) use batch::{MDelete, MGet, MSet, Values};
+
+mod expire;
+pub(crate) use expire::{Expire, Persist, Ttl, TtlResult};
+
+mod transaction;
+pub(crate) use transaction::{Abort, Begin, Commit};
+
+mod scan;
+pub(crate) use scan::{Scan, ScanPage};
+
+mod namespace;
+pub(crate) use namespace::{CreateNamespace, DropNamespace};
+
+mod table;
+pub(crate) use table::{CompactTable, CreateTable, DropTable, ListTables, Tables};
+
+mod stream;
+pub(crate) use stream::{Chunk, GetStream, SetStream};
+
+mod use_table;
+pub(crate) use use_table::UseTable;
+
 pub(crate) const DELIMITER: &[u8] = b"\r\n";
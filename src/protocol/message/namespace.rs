@@ -0,0 +1,64 @@
+use crate::common::Result;
+use crate::protocol::message::{MessageFrames, MessageType, Parse};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CreateNamespace {
+    pub(crate) namespace: String,
+}
+
+impl CreateNamespace {
+    pub(crate) fn new(namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: namespace.into(),
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let namespace = parse.next_string()?;
+
+        parse.expect_consumed()?;
+
+        Ok(CreateNamespace::new(namespace))
+    }
+}
+
+impl From<CreateNamespace> for MessageFrames {
+    fn from(create: CreateNamespace) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::CreateNamespace, 1);
+
+        frames.push_string(create.namespace);
+
+        frames
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DropNamespace {
+    pub(crate) namespace: String,
+}
+
+impl DropNamespace {
+    pub(crate) fn new(namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: namespace.into(),
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let namespace = parse.next_string()?;
+
+        parse.expect_consumed()?;
+
+        Ok(DropNamespace::new(namespace))
+    }
+}
+
+impl From<DropNamespace> for MessageFrames {
+    fn from(drop: DropNamespace) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::DropNamespace, 1);
+
+        frames.push_string(drop.namespace);
+
+        frames
+    }
+}
@@ -2,6 +2,7 @@ use std::vec;
 
 use crate::common::{self, ErrorKind, Result, Time};
 use crate::protocol::message::{Frame, MessageFrames, MessageType};
+use crate::protocol::{Previous, Value};
 
 pub(crate) struct Parse {
     frames: vec::IntoIter<Frame>,
@@ -33,6 +34,30 @@ impl Parse {
         }
     }
 
+    pub(crate) fn next_string_or_null(&mut self) -> Result<Option<String>, ParseError> {
+        match self.next()? {
+            Frame::String(s) => Ok(Some(s)),
+            Frame::Null => Ok(None),
+            frame => Err(format!("unexpected frame. want (string|null) got {:?}", frame).into()),
+        }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> Result<u64, ParseError> {
+        let s = self.next_string()?;
+        s.parse::<u64>()
+            .map_err(|_| format!("parse frame error; expected number, got {:?}", s).into())
+    }
+
+    pub(crate) fn next_u64_or_null(&mut self) -> Result<Option<u64>, ParseError> {
+        match self.next_string_or_null()? {
+            Some(s) => s
+                .parse::<u64>()
+                .map(Some)
+                .map_err(|_| format!("parse frame error; expected number, got {:?}", s).into()),
+            None => Ok(None),
+        }
+    }
+
     pub(crate) fn next_bytes(&mut self) -> Result<Vec<u8>, ParseError> {
         match self.next()? {
             Frame::Bytes(val) => Ok(val),
@@ -40,11 +65,19 @@ impl Parse {
         }
     }
 
-    pub(crate) fn next_bytes_or_null(&mut self) -> Result<Option<Vec<u8>>, ParseError> {
+    pub(crate) fn next_previous_or_null(&mut self) -> Result<Option<Previous>, ParseError> {
         match self.next()? {
-            Frame::Bytes(val) => Ok(Some(val)),
+            Frame::Bytes(val) => Value::new(val)
+                .map(|value| Some(Previous::Value(value)))
+                .map_err(|err| ParseError::Other(err.into())),
+            Frame::String(s) => s
+                .parse::<u64>()
+                .map(|bytes| Some(Previous::TooLarge(bytes)))
+                .map_err(|_| format!("parse frame error; expected number, got {:?}", s).into()),
             Frame::Null => Ok(None),
-            frame => Err(format!("unexpected frame. want (bytes|null) got {:?}", frame).into()),
+            frame => {
+                Err(format!("unexpected frame. want (bytes|number|null) got {:?}", frame).into())
+            }
         }
     }
 
@@ -56,6 +89,10 @@ impl Parse {
         }
     }
 
+    pub(crate) fn has_remaining(&self) -> bool {
+        self.frames.len() > 0
+    }
+
     // Make sure that caller has parse all the frames.
     pub(crate) fn expect_consumed(&mut self) -> Result<()> {
         match self.next() {
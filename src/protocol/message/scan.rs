@@ -0,0 +1,96 @@
+use crate::common::Result;
+use crate::protocol::message::{MessageFrames, MessageType, Parse};
+use crate::protocol::{Key, Value};
+
+// Request a page of key values in key order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct Scan {
+    pub(crate) prefix: Option<String>,
+    // inclusive
+    pub(crate) start: Option<String>,
+    // exclusive
+    pub(crate) end: Option<String>,
+    // next_cursor of the previous page.
+    pub(crate) cursor: Option<String>,
+    // maximum entries of the page.
+    pub(crate) limit: u64,
+}
+
+impl Scan {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let prefix = parse.next_string_or_null()?;
+        let start = parse.next_string_or_null()?;
+        let end = parse.next_string_or_null()?;
+        let cursor = parse.next_string_or_null()?;
+        let limit = parse.next_u64()?;
+
+        parse.expect_consumed()?;
+
+        Ok(Scan {
+            prefix,
+            start,
+            end,
+            cursor,
+            limit,
+        })
+    }
+}
+
+impl From<Scan> for MessageFrames {
+    fn from(scan: Scan) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::Scan, 5);
+
+        frames.push_string_or_null(scan.prefix);
+        frames.push_string_or_null(scan.start);
+        frames.push_string_or_null(scan.end);
+        frames.push_string_or_null(scan.cursor);
+        frames.push_u64(scan.limit);
+
+        frames
+    }
+}
+
+// Response of Scan.
+// frames are next_cursor followed by key and value pairs.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ScanPage {
+    pub(crate) entries: Vec<(Key, Value)>,
+    pub(crate) next_cursor: Option<String>,
+}
+
+impl ScanPage {
+    pub(crate) fn new(entries: Vec<(Key, Value)>, next_cursor: Option<String>) -> Self {
+        Self {
+            entries,
+            next_cursor,
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let next_cursor = parse.next_string_or_null()?;
+
+        let mut entries = Vec::new();
+        while parse.has_remaining() {
+            let key = Key::new(parse.next_string()?)?;
+            let value = Value::new(parse.next_bytes()?)?;
+            entries.push((key, value));
+        }
+
+        Ok(ScanPage::new(entries, next_cursor))
+    }
+}
+
+impl From<ScanPage> for MessageFrames {
+    fn from(page: ScanPage) -> Self {
+        let mut frames =
+            MessageFrames::with_capacity(MessageType::ScanPage, 1 + page.entries.len() * 2);
+
+        frames.push_string_or_null(page.next_cursor);
+        for (key, value) in page.entries {
+            frames.push_string(key.into_string());
+            frames.push_bytes(value.into_boxed_bytes());
+        }
+
+        frames
+    }
+}
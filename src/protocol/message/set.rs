@@ -1,32 +1,113 @@
-use crate::common::Result;
+use std::time::Duration;
+
+use crate::common::{ErrorKind, Result};
 use crate::protocol::message::{MessageFrames, MessageType, Parse};
-use crate::protocol::{Key, Value};
+use crate::protocol::{Condition, Key, Value};
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Set {
     pub(crate) key: Key,
     pub(crate) value: Value,
+    pub(crate) ttl: Option<Duration>,
+    pub(crate) condition: Option<Condition>,
 }
 
 impl Set {
+    const ABSENT: &'static str = "ABSENT";
+    const PRESENT: &'static str = "PRESENT";
+    const VALUE: &'static str = "VALUE";
+    const VERSION: &'static str = "VERSION";
+
     pub(crate) fn new(key: Key, value: Value) -> Self {
-        Self { key, value }
+        Self {
+            key,
+            value,
+            ttl: None,
+            condition: None,
+        }
+    }
+
+    pub(crate) fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub(crate) fn with_condition(mut self, condition: Condition) -> Self {
+        self.condition = Some(condition);
+        self
     }
 
     pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
         let key = Key::new(parse.next_string()?)?;
         let value = Value::new(parse.next_bytes()?)?;
+        // ttl milliseconds and condition are optional.
+        let ttl = if parse.has_remaining() {
+            parse.next_u64_or_null()?.map(Duration::from_millis)
+        } else {
+            None
+        };
+        let condition = if parse.has_remaining() {
+            Some(Set::parse_condition(parse)?)
+        } else {
+            None
+        };
 
-        Ok(Set::new(key, value))
+        parse.expect_consumed()?;
+
+        Ok(Set {
+            key,
+            value,
+            ttl,
+            condition,
+        })
+    }
+
+    fn parse_condition(parse: &mut Parse) -> Result<Condition> {
+        let condition = match parse.next_string()?.as_str() {
+            Set::ABSENT => Condition::Absent,
+            Set::PRESENT => Condition::Present,
+            Set::VALUE => Condition::Value(Value::new(parse.next_bytes()?)?),
+            Set::VERSION => Condition::Version(parse.next_u64()?),
+            condition => {
+                return Err(
+                    ErrorKind::NetworkFraming(format!("unknown condition {}", condition)).into(),
+                )
+            }
+        };
+        Ok(condition)
     }
 }
+
 impl From<Set> for MessageFrames {
     fn from(set: Set) -> Self {
-        let mut frames = MessageFrames::with_capacity(MessageType::Set, 2);
+        let mut frames = MessageFrames::with_capacity(MessageType::Set, 5);
 
         frames.push_string(set.key.into_string());
         frames.push_bytes(set.value.into_boxed_bytes());
 
+        match (set.ttl, set.condition) {
+            (None, None) => (),
+            (Some(ttl), None) => frames.push_u64(ttl.as_millis() as u64),
+            (ttl, Some(condition)) => {
+                match ttl {
+                    Some(ttl) => frames.push_u64(ttl.as_millis() as u64),
+                    None => frames.push_null(),
+                }
+                match condition {
+                    Condition::Absent => frames.push_string(Set::ABSENT),
+                    Condition::Present => frames.push_string(Set::PRESENT),
+                    Condition::Value(value) => {
+                        frames.push_string(Set::VALUE);
+                        frames.push_bytes(value.into_boxed_bytes());
+                    }
+                    Condition::Version(version) => {
+                        frames.push_string(Set::VERSION);
+                        frames.push_u64(version);
+                    }
+                }
+            }
+        }
+
         frames
     }
 }
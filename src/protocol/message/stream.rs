@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use crate::common::Result;
+use crate::protocol::message::{MessageFrames, MessageType, Parse};
+use crate::protocol::Key;
+
+// SetStream starts the value upload followed by the Chunk messages.
+// the server responds once with Success containing the version after the last chunk,
+// or with Fail. chunks are read to the last one even if the upload failed.
+// the client sends Fail in place of a chunk to abort the upload.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SetStream {
+    pub(crate) key: Key,
+    pub(crate) ttl: Option<Duration>,
+}
+
+impl SetStream {
+    pub(crate) fn new(key: Key) -> Self {
+        Self { key, ttl: None }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let key = Key::new(parse.next_string()?)?;
+        let ttl = parse.next_u64_or_null()?.map(Duration::from_millis);
+
+        parse.expect_consumed()?;
+
+        Ok(SetStream { key, ttl })
+    }
+}
+
+impl From<SetStream> for MessageFrames {
+    fn from(set: SetStream) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::SetStream, 2);
+
+        frames.push_string(set.key.into_string());
+        match set.ttl {
+            Some(ttl) => frames.push_u64(ttl.as_millis() as u64),
+            None => frames.push_null(),
+        }
+
+        frames
+    }
+}
+
+// GetStream requests the value by chunks.
+// the server responds with Success containing the version followed by the Chunk messages,
+// or Success without version if the key does not exist.
+// Fail in place of a chunk aborts the download.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GetStream {
+    pub(crate) key: Key,
+}
+
+impl GetStream {
+    pub(crate) fn new(key: Key) -> Self {
+        Self { key }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let key = Key::new(parse.next_string()?)?;
+
+        parse.expect_consumed()?;
+
+        Ok(GetStream::new(key))
+    }
+}
+
+impl From<GetStream> for MessageFrames {
+    fn from(get: GetStream) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::GetStream, 1);
+
+        frames.push_string(get.key.into_string());
+
+        frames
+    }
+}
+
+// Part of the value transferred by SetStream and GetStream.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Chunk {
+    pub(crate) data: Vec<u8>,
+    // whether the chunk is the end of the value.
+    pub(crate) last: bool,
+}
+
+impl Chunk {
+    pub(crate) fn new(data: Vec<u8>, last: bool) -> Self {
+        Self { data, last }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let data = parse.next_bytes()?;
+        let last = parse.next_u64()? != 0;
+
+        parse.expect_consumed()?;
+
+        Ok(Chunk::new(data, last))
+    }
+}
+
+impl From<Chunk> for MessageFrames {
+    fn from(chunk: Chunk) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::Chunk, 2);
+
+        frames.push_bytes(chunk.data);
+        frames.push_u64(chunk.last as u64);
+
+        frames
+    }
+}
@@ -1,40 +1,74 @@
 use crate::common::Result;
 use crate::protocol::message::{MessageFrames, MessageType, Parse};
-use crate::protocol::Value;
+use crate::protocol::{Previous, Value};
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Success {
-    value: Option<Value>,
+    // value read, or previous value overwritten or deleted.
+    value: Option<Previous>,
+    // version of the key which is read or written.
+    version: Option<u64>,
 }
 
 impl Success {
     pub(crate) fn new() -> Self {
-        Self { value: None }
+        Self {
+            value: None,
+            version: None,
+        }
     }
 
     pub(crate) fn with_value(value: Value) -> Self {
-        Self { value: Some(value) }
+        Success::with_previous(Previous::Value(value))
+    }
+
+    pub(crate) fn with_previous(previous: Previous) -> Self {
+        Self {
+            value: Some(previous),
+            version: None,
+        }
+    }
+
+    pub(crate) fn with_version(mut self, version: u64) -> Self {
+        self.version = Some(version);
+        self
     }
 
     pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
-        let value = parse.next_bytes_or_null()?.and_then(|v| Value::new(v).ok());
+        let value = parse.next_previous_or_null()?;
+        let version = if parse.has_remaining() {
+            Some(parse.next_u64()?)
+        } else {
+            None
+        };
 
         parse.expect_consumed()?;
 
-        Ok(Self { value })
+        Ok(Self { value, version })
+    }
+
+    pub(crate) fn version(&self) -> Option<u64> {
+        self.version
     }
 
     pub(crate) fn value(self) -> Option<Value> {
+        match self.value {
+            Some(Previous::Value(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn previous(self) -> Option<Previous> {
         self.value
     }
 }
 impl From<Success> for MessageFrames {
     fn from(success: Success) -> Self {
-        let mut frames = MessageFrames::with_capacity(MessageType::Success, 1);
+        let mut frames = MessageFrames::with_capacity(MessageType::Success, 2);
 
-        match success.value {
-            Some(value) => frames.push_bytes(value.into_boxed_bytes()),
-            None => frames.push_null(),
+        frames.push_previous_or_null(success.value);
+        if let Some(version) = success.version {
+            frames.push_u64(version);
         }
 
         frames
@@ -0,0 +1,166 @@
+use crate::common::Result;
+use crate::protocol::message::{MessageFrames, MessageType, Parse};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CreateTable {
+    pub(crate) namespace: String,
+    pub(crate) table: String,
+}
+
+impl CreateTable {
+    pub(crate) fn new(namespace: impl Into<String>, table: impl Into<String>) -> Self {
+        Self {
+            namespace: namespace.into(),
+            table: table.into(),
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let namespace = parse.next_string()?;
+        let table = parse.next_string()?;
+
+        parse.expect_consumed()?;
+
+        Ok(CreateTable::new(namespace, table))
+    }
+}
+
+impl From<CreateTable> for MessageFrames {
+    fn from(create: CreateTable) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::CreateTable, 2);
+
+        frames.push_string(create.namespace);
+        frames.push_string(create.table);
+
+        frames
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DropTable {
+    pub(crate) namespace: String,
+    pub(crate) table: String,
+}
+
+impl DropTable {
+    pub(crate) fn new(namespace: impl Into<String>, table: impl Into<String>) -> Self {
+        Self {
+            namespace: namespace.into(),
+            table: table.into(),
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let namespace = parse.next_string()?;
+        let table = parse.next_string()?;
+
+        parse.expect_consumed()?;
+
+        Ok(DropTable::new(namespace, table))
+    }
+}
+
+impl From<DropTable> for MessageFrames {
+    fn from(drop: DropTable) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::DropTable, 2);
+
+        frames.push_string(drop.namespace);
+        frames.push_string(drop.table);
+
+        frames
+    }
+}
+
+// Compact the table file on the running server.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CompactTable {
+    pub(crate) namespace: String,
+    pub(crate) table: String,
+}
+
+impl CompactTable {
+    pub(crate) fn new(namespace: impl Into<String>, table: impl Into<String>) -> Self {
+        Self {
+            namespace: namespace.into(),
+            table: table.into(),
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let namespace = parse.next_string()?;
+        let table = parse.next_string()?;
+
+        parse.expect_consumed()?;
+
+        Ok(CompactTable::new(namespace, table))
+    }
+}
+
+impl From<CompactTable> for MessageFrames {
+    fn from(compact: CompactTable) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::CompactTable, 2);
+
+        frames.push_string(compact.namespace);
+        frames.push_string(compact.table);
+
+        frames
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ListTables {}
+
+impl ListTables {
+    pub(crate) fn new() -> Self {
+        Self {}
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        parse.expect_consumed()?;
+
+        Ok(ListTables::new())
+    }
+}
+
+impl From<ListTables> for MessageFrames {
+    fn from(_: ListTables) -> Self {
+        MessageFrames::with_capacity(MessageType::ListTables, 0)
+    }
+}
+
+// Response of ListTables.
+// frames are namespace and table name pairs.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Tables {
+    pub(crate) tables: Vec<(String, String)>,
+}
+
+impl Tables {
+    pub(crate) fn new(tables: Vec<(String, String)>) -> Self {
+        Self { tables }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let mut tables = Vec::new();
+        while parse.has_remaining() {
+            let namespace = parse.next_string()?;
+            let table = parse.next_string()?;
+            tables.push((namespace, table));
+        }
+
+        Ok(Tables::new(tables))
+    }
+}
+
+impl From<Tables> for MessageFrames {
+    fn from(tables: Tables) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::Tables, tables.tables.len() * 2);
+
+        for (namespace, table) in tables.tables {
+            frames.push_string(namespace);
+            frames.push_string(table);
+        }
+
+        frames
+    }
+}
@@ -0,0 +1,69 @@
+use crate::common::Result;
+use crate::protocol::message::{MessageFrames, MessageType, Parse};
+
+// Start buffering the reads and writes of the connection as a transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Begin {}
+
+impl Begin {
+    pub(crate) fn new() -> Self {
+        Self {}
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        parse.expect_consumed()?;
+
+        Ok(Begin::new())
+    }
+}
+
+impl From<Begin> for MessageFrames {
+    fn from(_: Begin) -> Self {
+        MessageFrames::with_capacity(MessageType::Begin, 0)
+    }
+}
+
+// Apply the buffered writes atomically.
+// fails with conflict if the keys the transaction read have been changed.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Commit {}
+
+impl Commit {
+    pub(crate) fn new() -> Self {
+        Self {}
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        parse.expect_consumed()?;
+
+        Ok(Commit::new())
+    }
+}
+
+impl From<Commit> for MessageFrames {
+    fn from(_: Commit) -> Self {
+        MessageFrames::with_capacity(MessageType::Commit, 0)
+    }
+}
+
+// Discard the buffered writes.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Abort {}
+
+impl Abort {
+    pub(crate) fn new() -> Self {
+        Self {}
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        parse.expect_consumed()?;
+
+        Ok(Abort::new())
+    }
+}
+
+impl From<Abort> for MessageFrames {
+    fn from(_: Abort) -> Self {
+        MessageFrames::with_capacity(MessageType::Abort, 0)
+    }
+}
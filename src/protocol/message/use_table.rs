@@ -0,0 +1,39 @@
+use crate::common::Result;
+use crate::protocol::message::{MessageFrames, MessageType, Parse};
+
+// Bind the connection to the table.
+// subsequent key value requests are applied to the table.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct UseTable {
+    pub(crate) namespace: String,
+    pub(crate) table: String,
+}
+
+impl UseTable {
+    pub(crate) fn new(namespace: impl Into<String>, table: impl Into<String>) -> Self {
+        Self {
+            namespace: namespace.into(),
+            table: table.into(),
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Self> {
+        let namespace = parse.next_string()?;
+        let table = parse.next_string()?;
+
+        parse.expect_consumed()?;
+
+        Ok(UseTable::new(namespace, table))
+    }
+}
+
+impl From<UseTable> for MessageFrames {
+    fn from(use_table: UseTable) -> Self {
+        let mut frames = MessageFrames::with_capacity(MessageType::Use, 2);
+
+        frames.push_string(use_table.namespace);
+        frames.push_string(use_table.table);
+
+        frames
+    }
+}
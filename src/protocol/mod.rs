@@ -1,12 +1,43 @@
 //! protocol module specifies communication between the server and client.
 
+pub(crate) mod compression;
+
 pub(crate) mod connection;
 
 pub(crate) mod message;
 
+/// Entry points of the fuzz targets under `fuzz/`. not a part of the api.
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing {
+    use std::io::Cursor;
+
+    use crate::protocol::message::{FrameLimits, Message, MessageFrames};
+
+    /// Parse the data as the connection reads the message frames from the peer.
+    pub fn parse_message_frames(data: &[u8]) {
+        for limits in [FrameLimits::UNAUTHENTICATED, FrameLimits::AUTHENTICATED] {
+            let mut buf = Cursor::new(data);
+            if MessageFrames::check_parse(&mut buf, limits).is_ok() {
+                let checked = buf.position();
+                buf.set_position(0);
+                if let Ok(frames) = MessageFrames::parse(&mut buf) {
+                    // The connection advances the buffer by the checked length.
+                    assert_eq!(buf.position(), checked);
+                    let _ = Message::from_frames(frames);
+                }
+            }
+        }
+
+        // Parse does not panic on the unchecked data either.
+        let _ = MessageFrames::parse(&mut Cursor::new(data));
+    }
+}
+
 use std::convert::TryFrom;
 use std::fmt;
 use std::ops::Deref;
+use std::time::Duration;
 
 use crate::common::{KvsdError, Result};
 
@@ -17,9 +48,13 @@ pub const MAX_KYE_BYTES: usize = 1024;
 /// Maximum number of bytes in Value.
 pub const MAX_VALUE_BYTES: usize = 1024 * 1024 * 10;
 
+/// Maximum number of bytes in a chunk of the value transferred by stream.
+/// values larger than [`MAX_VALUE_BYTES`] can be stored by stream.
+pub const MAX_CHUNK_BYTES: usize = 1024 * 64;
+
 /// Key represents a string that meets the specifications of the kvsd protocol.
 /// other components can handle Key without checking the length.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Key(String);
 
 impl Deref for Key {
@@ -108,6 +143,39 @@ impl fmt::Debug for Value {
     }
 }
 
+/// Value overwritten or deleted by the write.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Previous {
+    /// The previous value.
+    Value(Value),
+    /// The previous value exceeds [`MAX_VALUE_BYTES`], so it is not returned inline.
+    /// holds the number of bytes of the value.
+    TooLarge(u64),
+}
+
+/// Expiration of the key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiry {
+    /// The key does not expire.
+    Never,
+    /// The key expires after the duration.
+    In(Duration),
+}
+
+/// Condition of the conditional write.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// The key does not exist.
+    Absent,
+    /// The key exists.
+    Present,
+    /// The current value equals to the value.
+    Value(Value),
+    /// The current version equals to the version.
+    /// absent key has version 0.
+    Version(u64),
+}
+
 pub(crate) struct KeyValue {
     pub(crate) key: Key,
     pub(crate) value: Value,
@@ -0,0 +1,578 @@
+//! http module serves REST endpoints, so that clients which can not use the kvsd protocol
+//! (shell scripts, browsers) can read and write keys.
+//!
+//! * `GET    /v1/{namespace}/{table}/{key}` responds the value of the key.
+//! * `PUT    /v1/{namespace}/{table}/{key}` sets the request body to the key. `ttl` query sets the expiration in seconds.
+//! * `DELETE /v1/{namespace}/{table}/{key}` deletes the key.
+//! * `GET    /v1/{namespace}/{table}` responds the keys of the table. `prefix`, `cursor` and `limit` queries page the keys.
+//!
+//! Requests are authenticated by basic or bearer authorization header.
+//! Errors are responded as json with the same code as the kvsd protocol.
+
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::Engine;
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{self, HeaderMap, HeaderValue};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::json;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, Semaphore};
+
+use crate::common::{error, info, Error, ErrorKind, Result};
+use crate::core::uow::{Delete, Get, Scan, Set, SetResult, Versioned};
+use crate::core::{Credential, CredentialProvider, Password, Principal, UnitOfWork};
+use crate::protocol::message::{Fail, FailCode};
+use crate::protocol::{Key, Value, MAX_VALUE_BYTES};
+use crate::server::tcp::ShutdownSubscriber;
+
+type Body = Full<Bytes>;
+
+pub(super) struct Handler {
+    remote_addr: Option<std::net::SocketAddr>,
+    request_sender: mpsc::Sender<UnitOfWork>,
+    shutdown: ShutdownSubscriber,
+    max_connections: Arc<Semaphore>,
+}
+
+impl Handler {
+    pub(super) fn new(
+        remote_addr: Option<std::net::SocketAddr>,
+        request_sender: mpsc::Sender<UnitOfWork>,
+        shutdown: ShutdownSubscriber,
+        max_connections: Arc<Semaphore>,
+    ) -> Self {
+        Self {
+            remote_addr,
+            request_sender,
+            shutdown,
+            max_connections,
+        }
+    }
+
+    pub(super) async fn run(mut self, stream: TcpStream) {
+        let gateway = Gateway {
+            remote_addr: self.remote_addr,
+            request_sender: self.request_sender.clone(),
+        };
+        let service = service_fn(move |request| {
+            let gateway = gateway.clone();
+            async move { Ok::<_, Infallible>(gateway.respond(request).await) }
+        });
+
+        let conn = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+        tokio::pin!(conn);
+
+        // Finish the request in progress on shutdown.
+        loop {
+            tokio::select! {
+                result = conn.as_mut() => {
+                    if let Err(err) = result {
+                        error!("http: {}", err);
+                    }
+                    break;
+                }
+                _ = self.shutdown.recv(), if !self.shutdown.is_shutdown() => {
+                    conn.as_mut().graceful_shutdown();
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Handler {
+    fn drop(&mut self) {
+        self.max_connections.add_permits(1);
+    }
+}
+
+// Handle each http request independently.
+#[derive(Clone)]
+struct Gateway {
+    remote_addr: Option<std::net::SocketAddr>,
+    request_sender: mpsc::Sender<UnitOfWork>,
+}
+
+impl Gateway {
+    const API_PREFIX: &'static str = "/v1/";
+    const DEFAULT_SCAN_LIMIT: usize = 100;
+    // Maximum keys of a scan page.
+    const MAX_SCAN_LIMIT: usize = 1000;
+    // Maximum bytes of the keys and values read for a scan page.
+    const MAX_SCAN_BYTES: usize = 1024 * 1024 * 4;
+
+    async fn respond(self, request: Request<Incoming>) -> Response<Body> {
+        match self.handle(request).await {
+            Ok(response) => response,
+            Err(err) => {
+                error!("http: {}", err);
+                fail_response(StatusCode::INTERNAL_SERVER_ERROR, FailCode::Undefined, "")
+            }
+        }
+    }
+
+    async fn handle(self, request: Request<Incoming>) -> Result<Response<Body>> {
+        let authorization = match Authorization::from_headers(request.headers()) {
+            Some(authorization) => authorization,
+            None => return Ok(unauthenticated()),
+        };
+        let (work, rx) =
+            UnitOfWork::new_authenticate(Arc::new(Principal::AnonymousUser), authorization);
+        self.request_sender.send(work).await?;
+        let principal = match rx.await?? {
+            Some(principal) => Arc::new(principal),
+            None => {
+                info!(addr=?self.remote_addr, "unauthenticated http request");
+                return Ok(unauthenticated());
+            }
+        };
+
+        let route = match Route::parse(request.uri().path()) {
+            Ok(route) => route,
+            Err(response) => return Ok(response),
+        };
+        let query = match Query::parse(request.uri().query()) {
+            Ok(query) => query,
+            Err(response) => return Ok(response),
+        };
+
+        let response = match (request.method().clone(), route) {
+            (
+                Method::GET,
+                Route::Key {
+                    namespace,
+                    table,
+                    key,
+                },
+            ) => {
+                let get = Get {
+                    namespace,
+                    table,
+                    key,
+                };
+                let (work, rx) = UnitOfWork::new_get(principal, get);
+                self.request_sender.send(work).await?;
+
+                match rx.await? {
+                    Ok(Some(Versioned { value, version })) => Response::builder()
+                        .header(header::CONTENT_TYPE, "application/octet-stream")
+                        .header("kvsd-version", version)
+                        .body(Full::new(Bytes::from(value.into_boxed_bytes())))
+                        .unwrap(),
+                    Ok(None) => key_not_found(),
+                    Err(err) => error_response(&err),
+                }
+            }
+            (
+                Method::PUT,
+                Route::Key {
+                    namespace,
+                    table,
+                    key,
+                },
+            ) => {
+                let ttl = match query.get("ttl").map(|ttl| ttl.parse::<u64>()) {
+                    Some(Ok(0)) | Some(Err(_)) => {
+                        return Ok(invalid_argument("ttl must be a positive integer"))
+                    }
+                    Some(Ok(ttl)) => Some(Duration::from_secs(ttl)),
+                    None => None,
+                };
+                let value = match Limited::new(request.into_body(), MAX_VALUE_BYTES)
+                    .collect()
+                    .await
+                {
+                    Ok(body) => Value::new_unchecked(body.to_bytes().to_vec()),
+                    Err(err) if err.is::<LengthLimitError>() => {
+                        return Ok(fail_response(
+                            StatusCode::PAYLOAD_TOO_LARGE,
+                            FailCode::ValueTooLarge,
+                            &err.to_string(),
+                        ))
+                    }
+                    Err(err) => {
+                        return Err(ErrorKind::Internal(format!("read body {}", err)).into())
+                    }
+                };
+
+                let set = Set {
+                    namespace,
+                    table,
+                    key,
+                    value,
+                    ttl,
+                    condition: None,
+                };
+                let (work, rx) = UnitOfWork::new_set(principal, set);
+                self.request_sender.send(work).await?;
+
+                match rx.await? {
+                    Ok(SetResult::Applied { version, .. }) => {
+                        json_response(StatusCode::OK, json!({ "version": version }))
+                    }
+                    Ok(SetResult::ConditionFailed) => fail_response(
+                        StatusCode::PRECONDITION_FAILED,
+                        FailCode::ConditionFailed,
+                        "",
+                    ),
+                    Err(err) => error_response(&err),
+                }
+            }
+            (
+                Method::DELETE,
+                Route::Key {
+                    namespace,
+                    table,
+                    key,
+                },
+            ) => {
+                let delete = Delete {
+                    namespace,
+                    table,
+                    key,
+                };
+                let (work, rx) = UnitOfWork::new_delete(principal, delete);
+                self.request_sender.send(work).await?;
+
+                match rx.await? {
+                    Ok(deleted) => {
+                        json_response(StatusCode::OK, json!({ "deleted": deleted.is_some() }))
+                    }
+                    Err(err) => error_response(&err),
+                }
+            }
+            (Method::GET, Route::Table { namespace, table }) => {
+                let limit = match query.get("limit").map(|limit| limit.parse::<usize>()) {
+                    Some(Ok(limit)) if limit > 0 => limit.min(Gateway::MAX_SCAN_LIMIT),
+                    Some(_) => return Ok(invalid_argument("limit must be a positive integer")),
+                    None => Gateway::DEFAULT_SCAN_LIMIT,
+                };
+                let scan = Scan {
+                    namespace,
+                    table,
+                    prefix: query.get("prefix").map(ToOwned::to_owned),
+                    start: None,
+                    end: None,
+                    cursor: query.get("cursor").map(ToOwned::to_owned),
+                    limit,
+                    max_bytes: Gateway::MAX_SCAN_BYTES,
+                };
+                let (work, rx) = UnitOfWork::new_scan(principal, scan);
+                self.request_sender.send(work).await?;
+
+                match rx.await? {
+                    Ok(page) => {
+                        let keys = page
+                            .entries
+                            .into_iter()
+                            .map(|(key, _)| key.into_string())
+                            .collect::<Vec<_>>();
+                        json_response(
+                            StatusCode::OK,
+                            json!({ "keys": keys, "next_cursor": page.next_cursor }),
+                        )
+                    }
+                    Err(err) => error_response(&err),
+                }
+            }
+            (_, Route::Key { .. }) => method_not_allowed("GET, PUT, DELETE"),
+            (_, Route::Table { .. }) => method_not_allowed("GET"),
+        };
+
+        Ok(response)
+    }
+}
+
+// Resource specified by the request path.
+#[derive(Debug, PartialEq)]
+enum Route {
+    // /v1/{namespace}/{table}
+    Table {
+        namespace: String,
+        table: String,
+    },
+    // /v1/{namespace}/{table}/{key}. key may contain '/'.
+    Key {
+        namespace: String,
+        table: String,
+        key: Key,
+    },
+}
+
+impl Route {
+    fn parse(path: &str) -> std::result::Result<Route, Response<Body>> {
+        let not_found = || fail_response(StatusCode::NOT_FOUND, FailCode::InvalidArgument, path);
+
+        let path = path
+            .strip_prefix(Gateway::API_PREFIX)
+            .ok_or_else(not_found)?;
+        let mut segments = path.splitn(3, '/');
+        let namespace = segments
+            .next()
+            .filter(|s| !s.is_empty())
+            .ok_or_else(not_found)?;
+        let table = segments
+            .next()
+            .filter(|s| !s.is_empty())
+            .ok_or_else(not_found)?;
+
+        let namespace = percent_decode(namespace)?;
+        let table = percent_decode(table)?;
+
+        match segments.next().filter(|s| !s.is_empty()) {
+            Some(key) => {
+                let key = Key::new(percent_decode(key)?).map_err(|err| {
+                    fail_response(
+                        StatusCode::URI_TOO_LONG,
+                        FailCode::KeyTooLarge,
+                        &err.to_string(),
+                    )
+                })?;
+                Ok(Route::Key {
+                    namespace,
+                    table,
+                    key,
+                })
+            }
+            None => Ok(Route::Table { namespace, table }),
+        }
+    }
+}
+
+// Decoded query parameters.
+struct Query(Vec<(String, String)>);
+
+impl Query {
+    fn parse(query: Option<&str>) -> std::result::Result<Query, Response<Body>> {
+        let mut params = Vec::new();
+        for param in query
+            .unwrap_or_default()
+            .split('&')
+            .filter(|p| !p.is_empty())
+        {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            params.push((
+                percent_decode(&name.replace('+', " "))?,
+                percent_decode(&value.replace('+', " "))?,
+            ));
+        }
+        Ok(Query(params))
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn percent_decode(s: &str) -> std::result::Result<String, Response<Body>> {
+    let invalid = || invalid_argument(&format!("invalid percent encoding {}", s));
+
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut src = s.bytes();
+    while let Some(b) = src.next() {
+        if b == b'%' {
+            let hex = [
+                src.next().ok_or_else(invalid)?,
+                src.next().ok_or_else(invalid)?,
+            ];
+            let hex = std::str::from_utf8(&hex).map_err(|_| invalid())?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+        } else {
+            bytes.push(b);
+        }
+    }
+
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+// Credential given by the authorization header.
+#[derive(Debug, PartialEq)]
+enum Authorization {
+    Basic { username: String, password: String },
+    Bearer(String),
+}
+
+impl Authorization {
+    fn from_headers(headers: &HeaderMap) -> Option<Authorization> {
+        let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+        let (scheme, credential) = value.split_once(' ')?;
+        let credential = credential.trim();
+
+        if scheme.eq_ignore_ascii_case("basic") {
+            let decoded = base64::engine::general_purpose::STANDARD
+                .decode(credential)
+                .ok()?;
+            let decoded = String::from_utf8(decoded).ok()?;
+            let (username, password) = decoded.split_once(':')?;
+            Some(Authorization::Basic {
+                username: username.to_owned(),
+                password: password.to_owned(),
+            })
+        } else if scheme.eq_ignore_ascii_case("bearer") {
+            Some(Authorization::Bearer(credential.to_owned()))
+        } else {
+            None
+        }
+    }
+}
+
+impl CredentialProvider for Authorization {
+    fn credential(&self) -> Credential {
+        match self {
+            Authorization::Basic { username, password } => Credential::Password(Password {
+                username: Cow::Borrowed(username),
+                password: Cow::Borrowed(password),
+            }),
+            Authorization::Bearer(token) => Credential::Token(Cow::Borrowed(token)),
+        }
+    }
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body.to_string())))
+        .unwrap()
+}
+
+fn fail_response(status: StatusCode, code: FailCode, message: &str) -> Response<Body> {
+    json_response(
+        status,
+        json!({ "code": code.to_string(), "message": message }),
+    )
+}
+
+// Response corresponding to the error of unit of work.
+fn error_response(err: &Error) -> Response<Body> {
+    let code = Fail::from(err).code();
+    let status = match code {
+        FailCode::TableNotFound | FailCode::NamespaceNotFound => StatusCode::NOT_FOUND,
+        FailCode::InvalidArgument | FailCode::IncompatibleProtocol | FailCode::InvalidFrame => {
+            StatusCode::BAD_REQUEST
+        }
+        FailCode::AlreadyExists | FailCode::Conflict => StatusCode::CONFLICT,
+        FailCode::ConditionFailed => StatusCode::PRECONDITION_FAILED,
+        FailCode::Unauthenticated => StatusCode::UNAUTHORIZED,
+        FailCode::Unauthorized => StatusCode::FORBIDDEN,
+        FailCode::KeyTooLarge => StatusCode::URI_TOO_LONG,
+        FailCode::ValueTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        FailCode::ReadOnly => StatusCode::SERVICE_UNAVAILABLE,
+        FailCode::Undefined
+        | FailCode::UnexpectedMessage
+        | FailCode::Corruption
+        | FailCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    fail_response(status, code, &err.to_string())
+}
+
+fn unauthenticated() -> Response<Body> {
+    let mut response = fail_response(StatusCode::UNAUTHORIZED, FailCode::Unauthenticated, "");
+    response.headers_mut().insert(
+        header::WWW_AUTHENTICATE,
+        HeaderValue::from_static("Basic realm=\"kvsd\""),
+    );
+    response
+}
+
+fn invalid_argument(message: &str) -> Response<Body> {
+    fail_response(StatusCode::BAD_REQUEST, FailCode::InvalidArgument, message)
+}
+
+fn key_not_found() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .body(Full::default())
+        .unwrap()
+}
+
+fn method_not_allowed(allow: &'static str) -> Response<Body> {
+    let mut response = fail_response(
+        StatusCode::METHOD_NOT_ALLOWED,
+        FailCode::UnexpectedMessage,
+        "",
+    );
+    response
+        .headers_mut()
+        .insert(header::ALLOW, HeaderValue::from_static(allow));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route() {
+        assert_eq!(
+            Route::parse("/v1/default/users").unwrap(),
+            Route::Table {
+                namespace: "default".into(),
+                table: "users".into(),
+            }
+        );
+        assert_eq!(
+            Route::parse("/v1/default/users/user%3A1/name").unwrap(),
+            Route::Key {
+                namespace: "default".into(),
+                table: "users".into(),
+                key: Key::new("user:1/name").unwrap(),
+            }
+        );
+        assert_eq!(
+            Route::parse("/v2/default/users").unwrap_err().status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            Route::parse("/v1/default").unwrap_err().status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            Route::parse("/v1/default/users/%zz").unwrap_err().status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn query() {
+        let query = Query::parse(Some("prefix=user%3A&limit=10&empty")).unwrap();
+        assert_eq!(query.get("prefix"), Some("user:"));
+        assert_eq!(query.get("limit"), Some("10"));
+        assert_eq!(query.get("empty"), Some(""));
+        assert_eq!(query.get("cursor"), None);
+    }
+
+    #[test]
+    fn authorization() {
+        let headers = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, HeaderValue::from_static(value));
+            headers
+        };
+
+        // "user:pa:ss"
+        assert_eq!(
+            Authorization::from_headers(&headers("Basic dXNlcjpwYTpzcw==")),
+            Some(Authorization::Basic {
+                username: "user".into(),
+                password: "pa:ss".into(),
+            })
+        );
+        assert_eq!(
+            Authorization::from_headers(&headers("Bearer token1")),
+            Some(Authorization::Bearer("token1".into()))
+        );
+        assert_eq!(Authorization::from_headers(&headers("Digest xxx")), None);
+        assert_eq!(Authorization::from_headers(&HeaderMap::new()), None);
+    }
+}
@@ -1,3 +1,7 @@
+#[cfg(feature = "http")]
+pub(crate) mod http;
+pub(crate) mod resp;
 pub(crate) mod tcp;
+mod tls;
 
 pub const DEFAULT_PORT: &str = "7379";
@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use crate::protocol::{Condition, Key, Value};
+use crate::server::resp::frame::RespValue;
+
+// Command supported by the RESP listener.
+#[derive(Debug, PartialEq)]
+pub(super) enum Command {
+    Ping(Option<Vec<u8>>),
+    Auth {
+        username: String,
+        password: String,
+    },
+    Get(Key),
+    Set {
+        key: Key,
+        value: Value,
+        ttl: Option<Duration>,
+        condition: Option<Condition>,
+    },
+    Del(Vec<Key>),
+    Exists(Vec<Key>),
+    Expire {
+        key: Key,
+        seconds: i64,
+    },
+    Ttl(Key),
+    Scan {
+        cursor: u64,
+        pattern: Option<Vec<u8>>,
+        count: usize,
+    },
+    Quit,
+}
+
+impl Command {
+    // Username used by AUTH without username.
+    const DEFAULT_USERNAME: &'static str = "default";
+    const DEFAULT_SCAN_COUNT: usize = 10;
+
+    // Parse the command, or the error replied to the client.
+    pub(super) fn parse(command: Vec<Vec<u8>>) -> Result<Command, RespValue> {
+        let mut args = command.into_iter();
+        let name = String::from_utf8_lossy(&args.next().unwrap_or_default()).to_ascii_uppercase();
+        let args: Vec<Vec<u8>> = args.collect();
+
+        let arity = |ok: bool| {
+            if ok {
+                Ok(())
+            } else {
+                Err(RespValue::err(format!(
+                    "wrong number of arguments for '{}' command",
+                    name.to_ascii_lowercase()
+                )))
+            }
+        };
+
+        let command = match name.as_str() {
+            "PING" => {
+                arity(args.len() <= 1)?;
+                Command::Ping(args.into_iter().next())
+            }
+            "AUTH" => {
+                arity(args.len() == 1 || args.len() == 2)?;
+                let mut args = args
+                    .into_iter()
+                    .map(string)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let password = args.pop().unwrap();
+                let username = args
+                    .pop()
+                    .unwrap_or_else(|| Command::DEFAULT_USERNAME.to_owned());
+                Command::Auth { username, password }
+            }
+            "GET" => {
+                arity(args.len() == 1)?;
+                Command::Get(key(&args[0])?)
+            }
+            "SET" => {
+                arity(args.len() >= 2)?;
+                Command::parse_set(args)?
+            }
+            "DEL" => {
+                arity(!args.is_empty())?;
+                Command::Del(args.iter().map(|arg| key(arg)).collect::<Result<_, _>>()?)
+            }
+            "EXISTS" => {
+                arity(!args.is_empty())?;
+                Command::Exists(args.iter().map(|arg| key(arg)).collect::<Result<_, _>>()?)
+            }
+            "EXPIRE" => {
+                arity(args.len() == 2)?;
+                Command::Expire {
+                    key: key(&args[0])?,
+                    seconds: integer(&args[1])?,
+                }
+            }
+            "TTL" => {
+                arity(args.len() == 1)?;
+                Command::Ttl(key(&args[0])?)
+            }
+            "SCAN" => {
+                arity(!args.is_empty())?;
+                Command::parse_scan(args)?
+            }
+            "QUIT" => Command::Quit,
+            _ => return Err(RespValue::err(format!("unknown command '{}'", name))),
+        };
+
+        Ok(command)
+    }
+
+    // SET key value [NX | XX] [EX seconds | PX milliseconds]
+    fn parse_set(args: Vec<Vec<u8>>) -> Result<Command, RespValue> {
+        let mut args = args.into_iter();
+        let key = key(&args.next().unwrap())?;
+        let value = Value::new(args.next().unwrap()).map_err(RespValue::err)?;
+
+        let mut ttl = None;
+        let mut condition = None;
+        while let Some(option) = args.next() {
+            match option.to_ascii_uppercase().as_slice() {
+                b"NX" if condition.is_none() => condition = Some(Condition::Absent),
+                b"XX" if condition.is_none() => condition = Some(Condition::Present),
+                unit @ (b"EX" | b"PX") if ttl.is_none() => {
+                    let n = args.next().ok_or_else(syntax_error)?;
+                    let n = integer(&n)?;
+                    if n <= 0 {
+                        return Err(RespValue::err("invalid expire time in 'set' command"));
+                    }
+                    ttl = Some(if unit == b"EX" {
+                        Duration::from_secs(n as u64)
+                    } else {
+                        Duration::from_millis(n as u64)
+                    });
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        Ok(Command::Set {
+            key,
+            value,
+            ttl,
+            condition,
+        })
+    }
+
+    // SCAN cursor [MATCH pattern] [COUNT count]
+    fn parse_scan(args: Vec<Vec<u8>>) -> Result<Command, RespValue> {
+        let mut args = args.into_iter();
+        let cursor = std::str::from_utf8(&args.next().unwrap())
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .ok_or_else(|| RespValue::err("invalid cursor"))?;
+
+        let mut pattern = None;
+        let mut count = Command::DEFAULT_SCAN_COUNT;
+        while let Some(option) = args.next() {
+            let value = args.next().ok_or_else(syntax_error)?;
+            match option.to_ascii_uppercase().as_slice() {
+                b"MATCH" => pattern = Some(value),
+                b"COUNT" => match integer(&value)? {
+                    n if n >= 1 => count = n as usize,
+                    _ => return Err(syntax_error()),
+                },
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        Ok(Command::Scan {
+            cursor,
+            pattern,
+            count,
+        })
+    }
+}
+
+fn syntax_error() -> RespValue {
+    RespValue::err("syntax error")
+}
+
+fn string(arg: Vec<u8>) -> Result<String, RespValue> {
+    String::from_utf8(arg).map_err(|_| RespValue::err("argument must be valid UTF-8"))
+}
+
+fn key(arg: &[u8]) -> Result<Key, RespValue> {
+    let key = std::str::from_utf8(arg).map_err(|_| RespValue::err("key must be valid UTF-8"))?;
+    Key::new(key).map_err(RespValue::err)
+}
+
+fn integer(arg: &[u8]) -> Result<i64, RespValue> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .ok_or_else(|| RespValue::err("value is not an integer or out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(command: &[&str]) -> Result<Command, RespValue> {
+        Command::parse(command.iter().map(|arg| arg.as_bytes().to_vec()).collect())
+    }
+
+    #[test]
+    fn parse_command() {
+        assert_eq!(parse(&["ping"]), Ok(Command::Ping(None)));
+        assert_eq!(
+            parse(&["AUTH", "secret"]),
+            Ok(Command::Auth {
+                username: "default".into(),
+                password: "secret".into(),
+            })
+        );
+        assert_eq!(
+            parse(&["Set", "key", "value", "nx", "PX", "1500"]),
+            Ok(Command::Set {
+                key: Key::new("key").unwrap(),
+                value: Value::new(b"value".as_ref()).unwrap(),
+                ttl: Some(Duration::from_millis(1500)),
+                condition: Some(Condition::Absent),
+            })
+        );
+        assert_eq!(
+            parse(&["SCAN", "3", "MATCH", "user:*"]),
+            Ok(Command::Scan {
+                cursor: 3,
+                pattern: Some(b"user:*".to_vec()),
+                count: 10,
+            })
+        );
+
+        assert_eq!(
+            parse(&["GET"]),
+            Err(RespValue::err(
+                "wrong number of arguments for 'get' command"
+            ))
+        );
+        assert_eq!(
+            parse(&["SET", "key", "value", "NX", "XX"]),
+            Err(RespValue::err("syntax error"))
+        );
+        assert_eq!(
+            parse(&["SET", "key", "value", "EX", "0"]),
+            Err(RespValue::err("invalid expire time in 'set' command"))
+        );
+        assert_eq!(
+            parse(&["EXPIRE", "key", "soon"]),
+            Err(RespValue::err("value is not an integer or out of range"))
+        );
+        assert_eq!(
+            parse(&["HSET", "key"]),
+            Err(RespValue::err("unknown command 'HSET'"))
+        );
+    }
+}
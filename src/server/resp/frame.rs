@@ -0,0 +1,319 @@
+use std::io::Cursor;
+
+use bytes::{Buf, BufMut, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
+
+use crate::common::{Error, ErrorKind, Result};
+use crate::protocol::message::FrameError;
+use crate::protocol::{MAX_KYE_BYTES, MAX_VALUE_BYTES};
+
+const DELIMITER: &[u8] = b"\r\n";
+
+// Maximum arguments of a command.
+const MAX_ARGUMENTS: usize = 1024 * 1024;
+// Maximum bytes of a bulk string argument.
+const MAX_BULK_BYTES: usize = MAX_VALUE_BYTES;
+// Maximum bytes of an inline command.
+const MAX_INLINE_BYTES: usize = MAX_KYE_BYTES * 64;
+
+// RESP2 value written to the client.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum RespValue {
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Vec<u8>),
+    Null,
+    Array(Vec<RespValue>),
+}
+
+impl RespValue {
+    pub(super) fn ok() -> Self {
+        RespValue::Simple("OK".into())
+    }
+
+    // Error reply with the generic ERR prefix.
+    pub(super) fn err(message: impl std::fmt::Display) -> Self {
+        RespValue::Error(format!("ERR {}", message))
+    }
+
+    // Error reply of the failed unit of work.
+    pub(super) fn error(err: Error) -> Self {
+        match err.kind() {
+            ErrorKind::Unauthorized(message) => RespValue::Error(format!("NOPERM {}", message)),
+            _ => RespValue::err(err),
+        }
+    }
+
+    pub(super) fn encode(&self, buf: &mut BytesMut) {
+        match self {
+            RespValue::Simple(s) => {
+                buf.put_u8(b'+');
+                buf.put_slice(s.as_bytes());
+                buf.put_slice(DELIMITER);
+            }
+            RespValue::Error(s) => {
+                buf.put_u8(b'-');
+                buf.put_slice(s.as_bytes());
+                buf.put_slice(DELIMITER);
+            }
+            RespValue::Integer(n) => {
+                buf.put_u8(b':');
+                buf.put_slice(n.to_string().as_bytes());
+                buf.put_slice(DELIMITER);
+            }
+            RespValue::Bulk(bytes) => {
+                buf.put_u8(b'$');
+                buf.put_slice(bytes.len().to_string().as_bytes());
+                buf.put_slice(DELIMITER);
+                buf.put_slice(bytes);
+                buf.put_slice(DELIMITER);
+            }
+            RespValue::Null => buf.put_slice(b"$-1\r\n"),
+            RespValue::Array(values) => {
+                buf.put_u8(b'*');
+                buf.put_slice(values.len().to_string().as_bytes());
+                buf.put_slice(DELIMITER);
+                for value in values {
+                    value.encode(buf);
+                }
+            }
+        }
+    }
+}
+
+// Parse a command sent by the client.
+// command is an array of bulk strings, or an inline command separated by spaces.
+// *{arguments}\r\n${bytes}\r\n{argument}\r\n...
+pub(super) fn parse_command(src: &mut Cursor<&[u8]>) -> Result<Vec<Vec<u8>>, FrameError> {
+    if !src.has_remaining() {
+        return Err(FrameError::Incomplete);
+    }
+
+    if src.chunk()[0] != b'*' {
+        let line = get_line(src, MAX_INLINE_BYTES)?;
+        return Ok(line
+            .split(|b| b.is_ascii_whitespace())
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| arg.to_vec())
+            .collect());
+    }
+    src.advance(1);
+
+    let arguments = get_decimal(src)?;
+    if arguments > MAX_ARGUMENTS {
+        return Err(FrameError::Invalid(format!(
+            "command exceeds {} arguments",
+            MAX_ARGUMENTS
+        )));
+    }
+
+    let mut command = Vec::with_capacity(arguments.min(16));
+    for _ in 0..arguments {
+        if !src.has_remaining() {
+            return Err(FrameError::Incomplete);
+        }
+        if src.get_u8() != b'$' {
+            return Err(FrameError::Invalid("expected bulk string".into()));
+        }
+
+        let len = get_decimal(src)?;
+        if len > MAX_BULK_BYTES {
+            return Err(FrameError::Invalid(format!(
+                "bulk string exceeds {} bytes",
+                MAX_BULK_BYTES
+            )));
+        }
+        if src.remaining() < len + DELIMITER.len() {
+            return Err(FrameError::Incomplete);
+        }
+        command.push(src.chunk()[..len].to_vec());
+        src.advance(len);
+        if src.chunk()[..DELIMITER.len()] != *DELIMITER {
+            return Err(FrameError::Invalid("bulk string is not terminated".into()));
+        }
+        src.advance(DELIMITER.len());
+    }
+
+    Ok(command)
+}
+
+fn get_line<'a>(src: &mut Cursor<&'a [u8]>, max_bytes: usize) -> Result<&'a [u8], FrameError> {
+    let start = src.position() as usize;
+    let buf = *src.get_ref();
+
+    match buf[start..].iter().position(|b| *b == b'\n') {
+        Some(end) => {
+            src.set_position((start + end + 1) as u64);
+            let line = &buf[start..start + end];
+            Ok(line.strip_suffix(b"\r").unwrap_or(line))
+        }
+        None if buf.len() - start > max_bytes => Err(FrameError::Invalid(format!(
+            "line exceeds {} bytes",
+            max_bytes
+        ))),
+        None => Err(FrameError::Incomplete),
+    }
+}
+
+fn get_decimal(src: &mut Cursor<&[u8]>) -> Result<usize, FrameError> {
+    // usize::MAX has 20 digits.
+    let line = get_line(src, 20)?;
+    std::str::from_utf8(line)
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .ok_or_else(|| FrameError::Invalid(format!("invalid length {:?}", line)))
+}
+
+// Connection speaking RESP2.
+pub(crate) struct RespConnection<T> {
+    stream: BufWriter<T>,
+    // The buffer for reading commands.
+    buffer: BytesMut,
+    // The buffer for encoding replies.
+    write_buffer: BytesMut,
+}
+
+impl<T> RespConnection<T>
+where
+    T: AsyncWrite + AsyncRead + Unpin,
+{
+    pub(crate) fn new(stream: T, buffer_size: Option<usize>) -> Self {
+        let buffer_size = buffer_size.unwrap_or(4 * 1024);
+        Self {
+            stream: BufWriter::new(stream),
+            buffer: BytesMut::with_capacity(buffer_size),
+            write_buffer: BytesMut::with_capacity(buffer_size),
+        }
+    }
+
+    // Read a command. None if the peer closed the socket.
+    pub(super) async fn read_command(&mut self) -> Result<Option<Vec<Vec<u8>>>> {
+        loop {
+            if let Some(command) = self.parse_command()? {
+                // Empty inline commands are ignored.
+                if command.is_empty() {
+                    continue;
+                }
+                return Ok(Some(command));
+            }
+
+            if 0 == self.stream.read_buf(&mut self.buffer).await? {
+                return if self.buffer.is_empty() {
+                    Ok(None)
+                } else {
+                    Err(ErrorKind::ConnectionResetByPeer.into())
+                };
+            }
+        }
+    }
+
+    fn parse_command(&mut self) -> Result<Option<Vec<Vec<u8>>>> {
+        let mut buf = Cursor::new(&self.buffer[..]);
+
+        match parse_command(&mut buf) {
+            Ok(command) => {
+                let len = buf.position() as usize;
+                self.buffer.advance(len);
+                Ok(Some(command))
+            }
+            Err(FrameError::Incomplete) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub(super) async fn write_value(&mut self, value: &RespValue) -> Result<()> {
+        self.write_buffer.clear();
+        value.encode(&mut self.write_buffer);
+
+        self.stream.write_all(&self.write_buffer).await?;
+        self.stream.flush().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(buf: &[u8]) -> Result<Vec<Vec<u8>>, FrameError> {
+        parse_command(&mut Cursor::new(buf))
+    }
+
+    #[test]
+    fn parse_array() {
+        let command = parse(b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\na\r\nb\n\r\n").unwrap();
+        assert_eq!(
+            command,
+            vec![b"SET".to_vec(), b"key".to_vec(), b"a\r\nb\n".to_vec()]
+        );
+
+        assert!(matches!(
+            parse(b"*2\r\n$3\r\nGET\r\n$3\r\nke"),
+            Err(FrameError::Incomplete)
+        ));
+        assert!(matches!(
+            parse(b"*1\r\n+GET\r\n"),
+            Err(FrameError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse(b"*1\r\n$3\r\nGETX\r\n"),
+            Err(FrameError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn parse_inline() {
+        assert_eq!(
+            parse(b"GET  key\r\n").unwrap(),
+            vec![b"GET".to_vec(), b"key".to_vec()]
+        );
+        assert_eq!(parse(b"PING\n").unwrap(), vec![b"PING".to_vec()]);
+        assert!(matches!(parse(b"PING"), Err(FrameError::Incomplete)));
+    }
+
+    #[test]
+    fn encode() {
+        let mut buf = BytesMut::new();
+        RespValue::Array(vec![
+            RespValue::ok(),
+            RespValue::err("unknown"),
+            RespValue::Integer(-2),
+            RespValue::Bulk(b"value".to_vec()),
+            RespValue::Null,
+            RespValue::Array(vec![]),
+        ])
+        .encode(&mut buf);
+
+        assert_eq!(
+            &buf[..],
+            b"*6\r\n+OK\r\n-ERR unknown\r\n:-2\r\n$5\r\nvalue\r\n$-1\r\n*0\r\n".as_ref()
+        );
+    }
+
+    #[tokio::test]
+    async fn read_command() {
+        let (client, server) = tokio::io::duplex(1024);
+        let mut client = RespConnection::new(client, None);
+        let mut server = RespConnection::new(server, None);
+
+        client
+            .stream
+            .write_all(b"\r\nPING\r\n*2\r\n$3\r\nGET\r\n$1\r\nk\r\n")
+            .await
+            .unwrap();
+        client.stream.flush().await.unwrap();
+        drop(client);
+
+        assert_eq!(
+            server.read_command().await.unwrap(),
+            Some(vec![b"PING".to_vec()])
+        );
+        assert_eq!(
+            server.read_command().await.unwrap(),
+            Some(vec![b"GET".to_vec(), b"k".to_vec()])
+        );
+        assert_eq!(server.read_command().await.unwrap(), None);
+    }
+}
@@ -0,0 +1,376 @@
+//! resp module serves the subset of redis commands, so that redis-cli and redis client libraries
+//! can talk to kvsd. commands are translated into the unit of works and applied to the default table.
+
+mod command;
+mod frame;
+
+pub(crate) use frame::RespConnection;
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{mpsc, Semaphore};
+
+use crate::common::glob::{glob_match, literal_prefix};
+use crate::common::{error, info, Result};
+use crate::config::filepath;
+use crate::core::uow::{Delete, Expire, Get, MDelete, MGet, Scan, Set, SetResult, Ttl};
+use crate::core::{Principal, UnitOfWork};
+use crate::protocol::message::Authenticate;
+use crate::protocol::{Condition, Expiry, Key, Value};
+use crate::server::tcp::ShutdownSubscriber;
+
+use command::Command;
+use frame::RespValue;
+
+pub(super) struct Handler {
+    principal: Arc<Principal>,
+    remote_addr: Option<std::net::SocketAddr>,
+    request_sender: mpsc::Sender<UnitOfWork>,
+    shutdown: ShutdownSubscriber,
+    max_connections: Arc<Semaphore>,
+    // scan cursors returned to the client and the last keys of the pages.
+    scan_cursors: VecDeque<(u64, String)>,
+    next_scan_cursor: u64,
+}
+
+impl Handler {
+    const DEFAULT_TABLE: &'static str = "default";
+    // Maximum entries of a scan page.
+    const MAX_SCAN_COUNT: usize = 1000;
+    // Maximum bytes of the keys and values read for a scan page.
+    const MAX_SCAN_BYTES: usize = 1024 * 1024 * 4;
+    // Maximum keys of DEL and EXISTS.
+    const MAX_BATCH_KEYS: usize = 1000;
+    // Maximum scan cursors kept per connection. older cursors are forgotten.
+    const MAX_SCAN_CURSORS: usize = 64;
+
+    pub(super) fn new(
+        remote_addr: Option<std::net::SocketAddr>,
+        request_sender: mpsc::Sender<UnitOfWork>,
+        shutdown: ShutdownSubscriber,
+        max_connections: Arc<Semaphore>,
+    ) -> Self {
+        Self {
+            principal: Arc::new(Principal::AnonymousUser),
+            remote_addr,
+            request_sender,
+            shutdown,
+            max_connections,
+            scan_cursors: VecDeque::new(),
+            next_scan_cursor: 1,
+        }
+    }
+
+    pub(super) async fn run<T>(mut self, conn: RespConnection<T>)
+    where
+        T: AsyncWrite + AsyncRead + Unpin,
+    {
+        if let Err(err) = self.handle(conn).await {
+            error!("{}", err);
+        }
+    }
+
+    async fn handle<T>(&mut self, mut conn: RespConnection<T>) -> Result<()>
+    where
+        T: AsyncWrite + AsyncRead + Unpin,
+    {
+        // select! can't detect shutdown reliably, so explicitly check shutdown before tcp read.
+        while !self.shutdown.is_shutdown() {
+            let maybe_command = tokio::select! {
+                command = conn.read_command() => command?,
+                _ = self.shutdown.recv() => break,
+            };
+
+            let command = match maybe_command {
+                Some(command) => command,
+                // peer closed the socket.
+                None => break,
+            };
+
+            let command = match Command::parse(command) {
+                Ok(command) => command,
+                Err(reply) => {
+                    conn.write_value(&reply).await?;
+                    continue;
+                }
+            };
+
+            if matches!(command, Command::Quit) {
+                conn.write_value(&RespValue::ok()).await?;
+                break;
+            }
+
+            let reply = self.execute(command).await?;
+            conn.write_value(&reply).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn execute(&mut self, command: Command) -> Result<RespValue> {
+        if !self.principal.is_authenticated() && !matches!(command, Command::Auth { .. }) {
+            return Ok(RespValue::Error("NOAUTH Authentication required.".into()));
+        }
+
+        let reply = match command {
+            Command::Ping(message) => {
+                let (work, rx) = UnitOfWork::new_ping(self.principal.clone());
+                self.request_sender.send(work).await?;
+
+                match rx.await? {
+                    Ok(_) => match message {
+                        Some(message) => RespValue::Bulk(message),
+                        None => RespValue::Simple("PONG".into()),
+                    },
+                    Err(err) => RespValue::error(err),
+                }
+            }
+            Command::Auth { username, password } => {
+                let auth = Authenticate::new(username, password);
+                let (work, rx) = UnitOfWork::new_authenticate(self.principal.clone(), auth);
+                self.request_sender.send(work).await?;
+
+                match rx.await? {
+                    Ok(Some(principal)) => {
+                        self.principal = Arc::new(principal);
+                        RespValue::ok()
+                    }
+                    Ok(None) => {
+                        info!(addr=?self.remote_addr, "unauthenticated resp connection");
+                        RespValue::Error(
+                            "WRONGPASS invalid username-password pair or user is disabled.".into(),
+                        )
+                    }
+                    Err(err) => RespValue::error(err),
+                }
+            }
+            Command::Get(key) => {
+                let get = Get {
+                    namespace: filepath::NS_DEFAULT.to_owned(),
+                    table: Handler::DEFAULT_TABLE.to_owned(),
+                    key,
+                };
+                let (work, rx) = UnitOfWork::new_get(self.principal.clone(), get);
+                self.request_sender.send(work).await?;
+
+                match rx.await? {
+                    Ok(Some(versioned)) => RespValue::Bulk(versioned.value.to_vec()),
+                    Ok(None) => RespValue::Null,
+                    Err(err) => RespValue::error(err),
+                }
+            }
+            Command::Set {
+                key,
+                value,
+                ttl,
+                condition,
+            } => self.set(key, value, ttl, condition).await?,
+            Command::Del(keys) if keys.len() > Handler::MAX_BATCH_KEYS => Handler::too_many_keys(),
+            Command::Del(keys) => {
+                let mdelete = MDelete {
+                    namespace: filepath::NS_DEFAULT.to_owned(),
+                    table: Handler::DEFAULT_TABLE.to_owned(),
+                    keys,
+                };
+                let (work, rx) = UnitOfWork::new_mdelete(self.principal.clone(), mdelete);
+                self.request_sender.send(work).await?;
+
+                Handler::count_reply(rx.await?)
+            }
+            Command::Exists(keys) if keys.len() > Handler::MAX_BATCH_KEYS => {
+                Handler::too_many_keys()
+            }
+            Command::Exists(keys) => {
+                let mget = MGet {
+                    namespace: filepath::NS_DEFAULT.to_owned(),
+                    table: Handler::DEFAULT_TABLE.to_owned(),
+                    keys,
+                };
+                let (work, rx) = UnitOfWork::new_mget(self.principal.clone(), mget);
+                self.request_sender.send(work).await?;
+
+                Handler::count_reply(rx.await?)
+            }
+            Command::Expire { key, seconds } => self.expire(key, seconds).await?,
+            Command::Ttl(key) => {
+                let ttl = Ttl {
+                    namespace: filepath::NS_DEFAULT.to_owned(),
+                    table: Handler::DEFAULT_TABLE.to_owned(),
+                    key,
+                };
+                let (work, rx) = UnitOfWork::new_ttl(self.principal.clone(), ttl);
+                self.request_sender.send(work).await?;
+
+                match rx.await? {
+                    Ok(None) => RespValue::Integer(-2),
+                    Ok(Some(Expiry::Never)) => RespValue::Integer(-1),
+                    // Round to the nearest second as redis does.
+                    Ok(Some(Expiry::In(ttl))) => {
+                        RespValue::Integer(((ttl.as_millis() + 500) / 1000) as i64)
+                    }
+                    Err(err) => RespValue::error(err),
+                }
+            }
+            Command::Scan {
+                cursor,
+                pattern,
+                count,
+            } => self.scan(cursor, pattern, count).await?,
+            // Handled by the connection loop.
+            Command::Quit => RespValue::ok(),
+        };
+
+        Ok(reply)
+    }
+
+    async fn set(
+        &mut self,
+        key: Key,
+        value: Value,
+        ttl: Option<Duration>,
+        condition: Option<Condition>,
+    ) -> Result<RespValue> {
+        let set = Set {
+            namespace: filepath::NS_DEFAULT.to_owned(),
+            table: Handler::DEFAULT_TABLE.to_owned(),
+            key,
+            value,
+            ttl,
+            condition,
+        };
+        let (work, rx) = UnitOfWork::new_set(self.principal.clone(), set);
+        self.request_sender.send(work).await?;
+
+        Ok(match rx.await? {
+            Ok(SetResult::Applied { .. }) => RespValue::ok(),
+            Ok(SetResult::ConditionFailed) => RespValue::Null,
+            Err(err) => RespValue::error(err),
+        })
+    }
+
+    // Set the ttl of the key. non positive seconds delete the key as redis does.
+    async fn expire(&mut self, key: Key, seconds: i64) -> Result<RespValue> {
+        let namespace = filepath::NS_DEFAULT.to_owned();
+        let table = Handler::DEFAULT_TABLE.to_owned();
+
+        if seconds <= 0 {
+            let delete = Delete {
+                namespace,
+                table,
+                key,
+            };
+            let (work, rx) = UnitOfWork::new_delete(self.principal.clone(), delete);
+            self.request_sender.send(work).await?;
+
+            return Ok(match rx.await? {
+                Ok(deleted) => RespValue::Integer(deleted.is_some() as i64),
+                Err(err) => RespValue::error(err),
+            });
+        }
+
+        let expire = Expire {
+            namespace,
+            table,
+            key,
+            ttl: Some(Duration::from_secs(seconds as u64)),
+        };
+        let (work, rx) = UnitOfWork::new_expire(self.principal.clone(), expire);
+        self.request_sender.send(work).await?;
+
+        Ok(match rx.await? {
+            Ok(expiry) => RespValue::Integer(expiry.is_some() as i64),
+            Err(err) => RespValue::error(err),
+        })
+    }
+
+    // Scan a page of keys.
+    // redis cursor is a number, so the last key of the page is kept per connection
+    // and the number associated with it is returned. cursor 0 starts and ends the iteration.
+    async fn scan(
+        &mut self,
+        cursor: u64,
+        pattern: Option<Vec<u8>>,
+        count: usize,
+    ) -> Result<RespValue> {
+        let cursor = match cursor {
+            0 => None,
+            cursor => match self.scan_cursors.iter().find(|(id, _)| *id == cursor) {
+                Some((_, last_key)) => Some(last_key.clone()),
+                None => return Ok(RespValue::err("invalid cursor")),
+            },
+        };
+        // Narrow the scan with the literal prefix of the pattern.
+        let prefix = pattern
+            .as_deref()
+            .map(literal_prefix)
+            .filter(|prefix| !prefix.is_empty())
+            .and_then(|prefix| String::from_utf8(prefix.to_vec()).ok());
+
+        let scan = Scan {
+            namespace: filepath::NS_DEFAULT.to_owned(),
+            table: Handler::DEFAULT_TABLE.to_owned(),
+            prefix,
+            start: None,
+            end: None,
+            cursor,
+            limit: count.min(Handler::MAX_SCAN_COUNT),
+            max_bytes: Handler::MAX_SCAN_BYTES,
+        };
+        let (work, rx) = UnitOfWork::new_scan(self.principal.clone(), scan);
+        self.request_sender.send(work).await?;
+
+        let page = match rx.await? {
+            Ok(page) => page,
+            Err(err) => return Ok(RespValue::error(err)),
+        };
+
+        let next_cursor = match page.next_cursor {
+            Some(last_key) => {
+                let id = self.next_scan_cursor;
+                self.next_scan_cursor += 1;
+                self.scan_cursors.push_back((id, last_key));
+                if self.scan_cursors.len() > Handler::MAX_SCAN_CURSORS {
+                    self.scan_cursors.pop_front();
+                }
+                id
+            }
+            None => 0,
+        };
+        let keys = page
+            .entries
+            .into_iter()
+            .map(|(key, _)| key.into_string().into_bytes())
+            .filter(|key| match &pattern {
+                Some(pattern) => glob_match(pattern, key),
+                None => true,
+            })
+            .map(RespValue::Bulk)
+            .collect();
+
+        Ok(RespValue::Array(vec![
+            RespValue::Bulk(next_cursor.to_string().into_bytes()),
+            RespValue::Array(keys),
+        ]))
+    }
+
+    // Number of the keys found by the batch unit of work.
+    fn count_reply<T>(result: Result<Vec<Option<T>>>) -> RespValue {
+        match result {
+            Ok(values) => RespValue::Integer(values.iter().filter(|v| v.is_some()).count() as i64),
+            Err(err) => RespValue::error(err),
+        }
+    }
+
+    fn too_many_keys() -> RespValue {
+        RespValue::err(format!("command exceeds {} keys", Handler::MAX_BATCH_KEYS))
+    }
+}
+
+impl Drop for Handler {
+    fn drop(&mut self) {
+        self.max_connections.add_permits(1);
+    }
+}
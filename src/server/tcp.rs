@@ -1,27 +1,42 @@
 use std::future::Future;
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicU32, Ordering},
         Arc,
     },
 };
 
-use rustls_pemfile::rsa_private_keys;
 use serde::Deserialize;
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
-use tokio::net::{TcpListener, TcpStream};
+use tokio::net::{TcpListener, TcpStream, UnixListener};
 use tokio::sync::{broadcast, mpsc, Semaphore};
+use tokio::task::{JoinError, JoinSet};
 use tokio::time::{timeout, Duration};
 use tokio_rustls::rustls::{self, pki_types};
 use tokio_rustls::server::TlsStream;
 use tokio_rustls::TlsAcceptor;
 
-use crate::common::{error, info, trace, warn, Result};
-use crate::core::uow::{Delete, Get, Set};
+use crate::common::{error, info, trace, warn, ErrorKind, Result};
+use crate::config::filepath;
+use crate::core::uow::{
+    Commit, CompactTable, CreateNamespace, CreateTable, Delete, DropNamespace, DropTable, Expire,
+    Get, LookupTable, MDelete, MGet, MSet, ReadChunk, Scan, Set, SetResult, TableKey, Ttl,
+    Versioned, WriteChunk,
+};
 use crate::core::{Principal, UnitOfWork};
+use crate::protocol::compression::Compression;
 use crate::protocol::connection::Connection;
-use crate::protocol::message::{Fail, FailCode, Message, Success};
+use crate::protocol::message::{
+    Authenticate, Chunk, Fail, FailCode, FrameLimits, GetStream, Hello, Message, MessageFrames,
+    RequestId, ScanPage, SetStream, Success, Tables, TtlResult, UseTable, Values,
+    MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
+use crate::protocol::{Expiry, Key, Value, MAX_CHUNK_BYTES};
+#[cfg(feature = "http")]
+use crate::server::http;
+use crate::server::resp::{self, RespConnection};
+use crate::server::tls;
 
 // Server configuration.
 #[derive(Debug, Deserialize, Default)]
@@ -42,6 +57,25 @@ pub struct Config {
     tls_certificate: Option<PathBuf>,
     // tls server private key file path
     tls_key: Option<PathBuf>,
+    // CA bundle file path to verify the tls client certificates.
+    // client certificate authentication is disabled if not set.
+    tls_client_ca: Option<PathBuf>,
+    // reject the tls clients without certificate.
+    tls_client_cert_required: Option<bool>,
+    // resp listen port. resp listener is disabled if not set.
+    resp_listen_port: Option<String>,
+    // http listen port. http listener is disabled if not set.
+    // serving http requires the http feature.
+    http_listen_port: Option<String>,
+    // unix domain socket path. unix socket listener is disabled if not set.
+    unix_socket_path: Option<PathBuf>,
+    // permission bits of the unix socket file.
+    unix_socket_mode: Option<u32>,
+    // connections through the unix socket are authenticated as the user without password.
+    // access is controlled by the permission of the socket file instead.
+    unix_socket_user: Option<String>,
+    // do not negotiate the compression of the bytes frames with the clients.
+    disable_compression: Option<bool>,
 }
 
 impl Config {
@@ -50,6 +84,7 @@ impl Config {
     const DEFAULT_AUTHENTICATE_TIMEOUT_MILLISECONDS: u64 = 300;
     const DEFAULT_LISTEN_HOST: &'static str = "127.0.0.1";
     const DEFAULT_LISTEN_PORT: &'static str = crate::server::DEFAULT_PORT;
+    const DEFAULT_UNIX_SOCKET_MODE: u32 = 0o600;
 
     pub fn set_max_tcp_connections(&mut self, val: Option<u32>) {
         if let Some(val) = val {
@@ -91,6 +126,46 @@ impl Config {
             self.tls_key = Some(val);
         }
     }
+    pub fn set_tls_client_ca(&mut self, val: &mut Option<PathBuf>) {
+        if let Some(val) = val.take() {
+            self.tls_client_ca = Some(val);
+        }
+    }
+    pub fn set_tls_client_cert_required(&mut self, val: &mut Option<bool>) {
+        if let Some(val) = val.take() {
+            self.tls_client_cert_required = Some(val);
+        }
+    }
+    pub fn set_resp_listen_port(&mut self, val: &mut Option<String>) {
+        if let Some(val) = val.take() {
+            self.resp_listen_port = Some(val)
+        }
+    }
+    pub fn set_http_listen_port(&mut self, val: &mut Option<String>) {
+        if let Some(val) = val.take() {
+            self.http_listen_port = Some(val)
+        }
+    }
+    pub fn set_unix_socket_path(&mut self, val: &mut Option<PathBuf>) {
+        if let Some(val) = val.take() {
+            self.unix_socket_path = Some(val)
+        }
+    }
+    pub fn set_unix_socket_mode(&mut self, val: Option<u32>) {
+        if let Some(val) = val {
+            self.unix_socket_mode = Some(val & 0o777)
+        }
+    }
+    pub fn set_unix_socket_user(&mut self, val: &mut Option<String>) {
+        if let Some(val) = val.take() {
+            self.unix_socket_user = Some(val)
+        }
+    }
+    pub fn set_disable_compression(&mut self, val: &mut Option<bool>) {
+        if let Some(val) = val.take() {
+            self.disable_compression = Some(val)
+        }
+    }
     pub(crate) fn override_merge(&mut self, other: &mut Config) {
         self.set_max_tcp_connections(other.max_tcp_connections);
         self.set_connection_tcp_buffer_bytes(other.connection_tcp_buffer_bytes);
@@ -100,6 +175,14 @@ impl Config {
         self.set_disable_tls(&mut other.disable_tls);
         self.set_tls_certificate(&mut other.tls_certificate);
         self.set_tls_key(&mut other.tls_key);
+        self.set_tls_client_ca(&mut other.tls_client_ca);
+        self.set_tls_client_cert_required(&mut other.tls_client_cert_required);
+        self.set_resp_listen_port(&mut other.resp_listen_port);
+        self.set_http_listen_port(&mut other.http_listen_port);
+        self.set_unix_socket_path(&mut other.unix_socket_path);
+        self.set_unix_socket_mode(other.unix_socket_mode);
+        self.set_unix_socket_user(&mut other.unix_socket_user);
+        self.set_disable_compression(&mut other.disable_compression);
     }
 
     fn max_tcp_connections(&self) -> u32 {
@@ -126,19 +209,59 @@ impl Config {
     pub(crate) fn listen_addr(&self) -> String {
         format!(
             "{}:{}",
-            self.listen_host
-                .as_deref()
-                .unwrap_or(Config::DEFAULT_LISTEN_HOST),
+            self.listen_host(),
             self.listen_port
                 .as_deref()
                 .unwrap_or(Config::DEFAULT_LISTEN_PORT),
         )
     }
 
+    // resp listener shares the host with the kvsd listener.
+    pub(crate) fn resp_listen_addr(&self) -> Option<String> {
+        self.resp_listen_port
+            .as_deref()
+            .map(|port| format!("{}:{}", self.listen_host(), port))
+    }
+
+    // http listener shares the host with the kvsd listener.
+    pub(crate) fn http_listen_addr(&self) -> Option<String> {
+        self.http_listen_port
+            .as_deref()
+            .map(|port| format!("{}:{}", self.listen_host(), port))
+    }
+
+    pub(crate) fn unix_socket_path(&self) -> Option<&Path> {
+        self.unix_socket_path.as_deref()
+    }
+
+    pub(crate) fn unix_socket_mode(&self) -> u32 {
+        self.unix_socket_mode
+            .unwrap_or(Config::DEFAULT_UNIX_SOCKET_MODE)
+    }
+
+    fn listen_host(&self) -> &str {
+        self.listen_host
+            .as_deref()
+            .unwrap_or(Config::DEFAULT_LISTEN_HOST)
+    }
+
     fn disable_tls(&self) -> bool {
         self.disable_tls.unwrap_or(false)
     }
 
+    // Hello the server advertises to the clients.
+    fn hello(&self) -> Hello {
+        if self.disable_compression.unwrap_or(false) {
+            Hello::new().without_compression()
+        } else {
+            Hello::new()
+        }
+    }
+
+    fn tls_client_cert_required(&self) -> bool {
+        self.tls_client_cert_required.unwrap_or(false)
+    }
+
     fn load_certs(&self) -> std::io::Result<Vec<pki_types::CertificateDer<'static>>> {
         rustls_pemfile::certs(&mut std::io::BufReader::new(
             std::fs::File::open(self.tls_certificate.clone().unwrap()).unwrap(),
@@ -147,12 +270,11 @@ impl Config {
     }
 
     fn load_keys(&self) -> std::io::Result<pki_types::PrivateKeyDer<'static>> {
-        rsa_private_keys(&mut std::io::BufReader::new(
+        // Accept pkcs1, pkcs8 and sec1 keys.
+        rustls_pemfile::private_key(&mut std::io::BufReader::new(
             std::fs::File::open(self.tls_key.clone().unwrap()).unwrap(),
-        ))
-        .next()
-        .unwrap()
-        .map(Into::into)
+        ))?
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "no private key"))
     }
 }
 
@@ -189,6 +311,13 @@ impl GracefulShutdown {
         // Wait for all handler to finish.
         let _ = self.shutdown_complete_rx.recv().await;
     }
+
+    fn subscribe(&self) -> ShutdownSubscriber {
+        ShutdownSubscriber::new(
+            self.notify_shutdown.subscribe(),
+            self.shutdown_complete_tx.clone(),
+        )
+    }
 }
 
 pub(crate) struct Server {
@@ -206,17 +335,55 @@ impl Server {
     }
 
     pub(crate) async fn run(
-        mut self,
+        self,
         request_sender: mpsc::Sender<UnitOfWork>,
         listener: TcpListener,
+        resp_listener: Option<TcpListener>,
+        http_listener: Option<TcpListener>,
+        unix_listener: Option<UnixListener>,
         shutdown: impl Future,
     ) -> Result<()> {
+        let serve_resp = async {
+            match resp_listener {
+                Some(resp_listener) => self.serve_resp(resp_listener, request_sender.clone()).await,
+                None => std::future::pending().await,
+            }
+        };
+        let serve_http = async {
+            match http_listener {
+                #[cfg(feature = "http")]
+                Some(http_listener) => self.serve_http(http_listener, request_sender.clone()).await,
+                _ => std::future::pending::<Result<()>>().await,
+            }
+        };
+        let serve_unix = async {
+            match unix_listener {
+                Some(unix_listener) => self.serve_unix(unix_listener, request_sender.clone()).await,
+                None => std::future::pending().await,
+            }
+        };
+
         tokio::select! {
-            result = self.serve(listener, request_sender) => {
+            result = self.serve(listener, request_sender.clone()) => {
                 if let Err(err) = result {
                     error!(cause = %err, "Failed to accept");
                 }
             }
+            result = serve_resp => {
+                if let Err(err) = result {
+                    error!(cause = %err, "Failed to accept resp connection");
+                }
+            }
+            result = serve_http => {
+                if let Err(err) = result {
+                    error!(cause = %err, "Failed to accept http connection");
+                }
+            }
+            result = serve_unix => {
+                if let Err(err) = result {
+                    error!(cause = %err, "Failed to accept unix socket connection");
+                }
+            }
             _ = shutdown => {
                 info!("Shutdown signal received");
             }
@@ -226,13 +393,19 @@ impl Server {
 
         self.graceful_shutdown.shutdown().await;
 
+        if let Some(path) = self.config.unix_socket_path() {
+            if let Err(err) = std::fs::remove_file(path) {
+                warn!(path = %path.display(), "Remove unix socket {}", err);
+            }
+        }
+
         info!("Shutdown successfully completed");
 
         Ok(())
     }
 
     pub(crate) async fn serve(
-        &mut self,
+        &self,
         listener: TcpListener,
         request_sender: mpsc::Sender<UnitOfWork>,
     ) -> Result<()> {
@@ -257,19 +430,26 @@ impl Server {
             let certs = self.config.load_certs().unwrap();
             let key = self.config.load_keys().unwrap();
 
-            let tls_config = rustls::ServerConfig::builder()
-                .with_no_client_auth()
-                .with_single_cert(certs, key)
-                .unwrap();
+            let tls_config = rustls::ServerConfig::builder();
+            let tls_config = match self.config.tls_client_ca.as_deref() {
+                Some(ca_bundle) => tls_config.with_client_cert_verifier(tls::client_verifier(
+                    ca_bundle,
+                    self.config.tls_client_cert_required(),
+                )?),
+                None => tls_config.with_no_client_auth(),
+            }
+            .with_single_cert(certs, key)
+            .unwrap();
 
             let tls_acceptor = TlsAcceptor::from(Arc::new(tls_config));
 
             loop {
-                let (socket, handler) = self.accept(&mut listener, request_sender.clone()).await?;
+                let (socket, mut handler) =
+                    self.accept(&mut listener, request_sender.clone()).await?;
                 let acceptor = tls_acceptor.clone();
 
                 tokio::spawn(async move {
-                    let connection = match Server::handshake(
+                    let (connection, certificate_names) = match Server::handshake(
                         acceptor,
                         socket,
                         connection_tcp_buffer_bytes,
@@ -283,6 +463,7 @@ impl Server {
                         }
                     };
                     trace!("TLS Handshake success");
+                    handler.certificate_names = certificate_names;
                     handler.run(connection).await;
                 });
             }
@@ -290,7 +471,7 @@ impl Server {
     }
 
     async fn accept(
-        &mut self,
+        &self,
         listener: &mut SemaphoreListener,
         request_sender: mpsc::Sender<UnitOfWork>,
     ) -> Result<(TcpStream, Handler)> {
@@ -300,69 +481,340 @@ impl Server {
             "Connection accepted"
         );
 
-        let handler = Handler {
-            principal: Arc::new(Principal::AnonymousUser),
-            remote_addr: Some(peer_addr),
+        let handler = self.handler(
+            Principal::AnonymousUser,
+            Some(peer_addr),
             request_sender,
-            shutdown: ShutdownSubscriber::new(
-                self.graceful_shutdown.notify_shutdown.subscribe(),
-                self.graceful_shutdown.shutdown_complete_tx.clone(),
-            ),
-            max_connections: listener.max_connections.clone(),
-            authenticate_timeout: self.config.authenticate_timeout(),
-        };
+            listener.max_connections.clone(),
+        );
 
         Ok((socket, handler))
     }
 
+    fn handler(
+        &self,
+        principal: Principal,
+        remote_addr: Option<std::net::SocketAddr>,
+        request_sender: mpsc::Sender<UnitOfWork>,
+        max_connections: Arc<Semaphore>,
+    ) -> Handler {
+        Handler {
+            principal: Arc::new(principal),
+            namespace: filepath::NS_DEFAULT.to_owned(),
+            table: Handler::DEFAULT_TABLE.to_owned(),
+            remote_addr,
+            request_sender,
+            shutdown: self.graceful_shutdown.subscribe(),
+            max_connections,
+            authenticate_timeout: self.config.authenticate_timeout(),
+            certificate_names: Vec::new(),
+            protocol: self.config.hello(),
+            transaction: None,
+            chunk_bytes: self
+                .config
+                .connection_tcp_buffer_bytes()
+                .min(MAX_CHUNK_BYTES),
+        }
+    }
+
+    // Serve the connections through the unix domain socket.
+    // they skip the authentication if the unix socket user is configured.
+    async fn serve_unix(
+        &self,
+        listener: UnixListener,
+        request_sender: mpsc::Sender<UnitOfWork>,
+    ) -> Result<()> {
+        info!(
+            user = ?self.config.unix_socket_user,
+            "Unix socket server running"
+        );
+
+        let max_connections = Arc::new(Semaphore::new(self.config.max_tcp_connections() as usize));
+        let connection_tcp_buffer_bytes = self.config.connection_tcp_buffer_bytes();
+
+        loop {
+            max_connections.acquire().await?.forget();
+            let (socket, _) = listener.accept().await?;
+            info!(
+                available = max_connections.available_permits(),
+                "Unix socket connection accepted"
+            );
+
+            let principal = match &self.config.unix_socket_user {
+                Some(user) => Principal::user(user),
+                None => Principal::AnonymousUser,
+            };
+            let handler = self.handler(
+                principal,
+                None,
+                request_sender.clone(),
+                max_connections.clone(),
+            );
+            let connection = Connection::new(socket, Some(connection_tcp_buffer_bytes));
+
+            tokio::spawn(handler.run(connection));
+        }
+    }
+
+    // Serve the redis compatible connections.
+    async fn serve_resp(
+        &self,
+        listener: TcpListener,
+        request_sender: mpsc::Sender<UnitOfWork>,
+    ) -> Result<()> {
+        info!("Resp server running");
+
+        let mut listener = SemaphoreListener::new(listener, self.config.max_tcp_connections());
+        let connection_tcp_buffer_bytes = self.config.connection_tcp_buffer_bytes();
+
+        loop {
+            let (socket, peer_addr) = listener.accept().await?;
+            info!(
+                available = listener.max_connections.available_permits(),
+                "Resp connection accepted"
+            );
+
+            let handler = resp::Handler::new(
+                Some(peer_addr),
+                request_sender.clone(),
+                self.graceful_shutdown.subscribe(),
+                listener.max_connections.clone(),
+            );
+            let connection = RespConnection::new(socket, Some(connection_tcp_buffer_bytes));
+
+            tokio::spawn(handler.run(connection));
+        }
+    }
+
+    // Serve the http gateway connections.
+    #[cfg(feature = "http")]
+    async fn serve_http(
+        &self,
+        listener: TcpListener,
+        request_sender: mpsc::Sender<UnitOfWork>,
+    ) -> Result<()> {
+        info!("Http server running");
+
+        let mut listener = SemaphoreListener::new(listener, self.config.max_tcp_connections());
+
+        loop {
+            let (socket, peer_addr) = listener.accept().await?;
+            info!(
+                available = listener.max_connections.available_permits(),
+                "Http connection accepted"
+            );
+
+            let handler = http::Handler::new(
+                Some(peer_addr),
+                request_sender.clone(),
+                self.graceful_shutdown.subscribe(),
+                listener.max_connections.clone(),
+            );
+
+            tokio::spawn(handler.run(socket));
+        }
+    }
+
+    // Establish the tls connection.
+    // returns the names of the client certificate too if the client presented it.
     async fn handshake(
         acceptor: TlsAcceptor,
         stream: TcpStream,
         buffer_bytes: usize,
-    ) -> Result<Connection<TlsStream<TcpStream>>> {
+    ) -> Result<(Connection<TlsStream<TcpStream>>, Vec<String>)> {
         let tls_stream = acceptor.accept(stream).await?;
-        Ok(Connection::new(tls_stream, Some(buffer_bytes)))
+        let certificate_names = tls_stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .and_then(|certs| certs.first())
+            .map(tls::certificate_names)
+            .unwrap_or_default();
+
+        Ok((
+            Connection::new(tls_stream, Some(buffer_bytes)),
+            certificate_names,
+        ))
     }
 }
 
 struct Handler {
     principal: Arc<Principal>,
+    // namespace and table to which key value requests are applied.
+    namespace: String,
+    table: String,
     remote_addr: Option<std::net::SocketAddr>,
     request_sender: mpsc::Sender<UnitOfWork>,
     shutdown: ShutdownSubscriber,
     max_connections: Arc<Semaphore>,
     authenticate_timeout: Duration,
+    // names of the client certificate verified by the tls handshake.
+    certificate_names: Vec<String>,
+    // protocol advertised by the server, and negotiated with the client by Hello.
+    protocol: Hello,
+    // reads and writes buffered since Begin.
+    transaction: Option<Commit>,
+    // maximum bytes of the chunk sent by stream.
+    // bounded by the connection buffer so that streaming large values does not grow the memory.
+    chunk_bytes: usize,
+}
+
+// How the client started the connection.
+enum Greeting {
+    // Protocol is negotiated by Hello.
+    Hello,
+    // Client built before Hello sent the message without it.
+    Legacy(Message),
+    // Client is incompatible or closed the connection.
+    Closed,
+}
+
+// Context to handle the request independent of the connection state.
+// cloned per pipelined request so that they are handled concurrently.
+#[derive(Clone)]
+struct RequestContext {
+    principal: Arc<Principal>,
+    namespace: String,
+    table: String,
+    request_sender: mpsc::Sender<UnitOfWork>,
 }
 
 impl Handler {
-    async fn run<T>(mut self, conn: Connection<T>)
+    const DEFAULT_TABLE: &'static str = "default";
+    // Maximum entries of a scan page.
+    const MAX_SCAN_LIMIT: u64 = 1000;
+    // Maximum bytes of the keys and values of a scan page.
+    const MAX_SCAN_BYTES: usize = 1024 * 1024 * 4;
+    // Maximum keys of a batch.
+    const MAX_BATCH_KEYS: usize = 1000;
+    // Maximum pipelined requests handled concurrently per connection.
+    const MAX_PIPELINED_REQUESTS: usize = 128;
+
+    async fn run<T>(mut self, mut conn: Connection<T>)
     where
         T: AsyncWrite + AsyncRead + Unpin,
     {
-        if let Err(err) = self.handle(conn).await {
+        if let Err(err) = self.handle(&mut conn).await {
+            // The client sending the invalid frames is told the cause before the disconnection.
+            if matches!(
+                err.kind(),
+                ErrorKind::NetworkFraming(_) | ErrorKind::UnknownMessageType { .. }
+            ) {
+                conn.write_message(Fail::from(&err)).await.ok();
+            }
             error!("{}", err);
         }
     }
 
-    async fn handle<T>(&mut self, mut conn: Connection<T>) -> Result<()>
+    async fn handle<T>(&mut self, conn: &mut Connection<T>) -> Result<()>
     where
         T: AsyncWrite + AsyncRead + Unpin,
     {
-        if self.authenticate(&mut conn).await? {
-            self.handle_message(&mut conn).await
+        // The peer is not trusted to send large frames until authenticated.
+        conn.set_frame_limits(FrameLimits::UNAUTHENTICATED);
+
+        let mut first = match self.hello(conn).await? {
+            Greeting::Hello => None,
+            Greeting::Legacy(message) => Some(message),
+            Greeting::Closed => return Ok(()),
+        };
+
+        // Connection may be authenticated by the listener or the client certificate.
+        if self.principal.is_authenticated()
+            || self.authenticate_by_certificate().await?
+            || self.authenticate(conn, first.take()).await?
+        {
+            conn.set_frame_limits(FrameLimits::AUTHENTICATED);
+            // Message of the legacy client is left if the connection was authenticated without it.
+            if let Some(message) = first {
+                let response = self.respond(message).await?;
+                conn.write_message(response).await?;
+            }
+            self.handle_message(conn).await
         } else {
             Ok(())
         }
     }
 
-    async fn authenticate<T>(&mut self, connection: &mut Connection<T>) -> Result<bool>
+    // Negotiate the protocol with the Hello sent first by the client.
+    // the client sending the other message first is built before Hello, so it is treated as
+    // speaking the protocol version 1 without any features.
+    // incompatible clients are responded with Fail and disconnected.
+    async fn hello<T>(&mut self, connection: &mut Connection<T>) -> Result<Greeting>
     where
         T: AsyncWrite + AsyncRead + Unpin,
     {
-        match connection
+        let message = match connection
             .read_message_with_timeout(self.authenticate_timeout)
             .await?
         {
+            Some(Message::Hello(hello)) if hello.is_compatible() => {
+                self.protocol = self.protocol.negotiate(&hello);
+                connection.write_message(self.protocol.clone()).await?;
+                connection.set_compression(Compression::negotiated(&self.protocol));
+                return Ok(Greeting::Hello);
+            }
+            Some(Message::Hello(hello)) => format!(
+                "protocol version {} is not supported. supported versions are {}..={}",
+                hello.version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+            ),
+            Some(message) => {
+                self.protocol = Hello::legacy();
+                return Ok(Greeting::Legacy(message));
+            }
+            None => return Ok(Greeting::Closed),
+        };
+
+        info!(addr=?self.remote_addr, "incompatible client: {}", message);
+        connection
+            .write_message(Fail::new(FailCode::IncompatibleProtocol).with_message(message))
+            .await?;
+        Ok(Greeting::Closed)
+    }
+
+    // Authenticate as the user the client certificate is mapped to.
+    // the client falls back to the password authentication if no user is mapped.
+    async fn authenticate_by_certificate(&mut self) -> Result<bool> {
+        if self.certificate_names.is_empty() {
+            return Ok(false);
+        }
+
+        let certificate = tls::ClientCertificate {
+            names: std::mem::take(&mut self.certificate_names),
+        };
+        let (work, rx) = UnitOfWork::new_authenticate(self.principal.clone(), certificate);
+        self.request_sender.send(work).await?;
+
+        match rx.await?? {
+            Some(principal) => {
+                self.principal = Arc::new(principal);
+                Ok(true)
+            }
+            None => {
+                info!(addr=?self.remote_addr, "client certificate is not mapped to any user");
+                Ok(false)
+            }
+        }
+    }
+
+    // Authenticate by the message the legacy client sent first, or the next one.
+    async fn authenticate<T>(
+        &mut self,
+        connection: &mut Connection<T>,
+        first: Option<Message>,
+    ) -> Result<bool>
+    where
+        T: AsyncWrite + AsyncRead + Unpin,
+    {
+        let message = match first {
+            Some(message) => Some(message),
+            None => {
+                connection
+                    .read_message_with_timeout(self.authenticate_timeout)
+                    .await?
+            }
+        };
+        match message {
             Some(Message::Authenticate(auth)) => {
                 let (work, rx) = UnitOfWork::new_authenticate(self.principal.clone(), auth.clone());
 
@@ -396,99 +848,688 @@ impl Handler {
     where
         T: AsyncWrite + AsyncRead + Unpin,
     {
+        // Pipelined requests in progress.
+        let mut pipelined = JoinSet::new();
+
         // select! can't detect shutdown reliably, so explicitly check shutdown before tcp read.
         while !self.shutdown.is_shutdown() {
             let maybe_message = tokio::select! {
-                msg = connection.read_message() => msg?,
-                _ = self.shutdown.recv() => {
-                    return Ok(())
+                msg = connection.read_message_with_id(),
+                    if pipelined.len() < Handler::MAX_PIPELINED_REQUESTS => msg?,
+                Some(responded) = pipelined.join_next() => {
+                    let (request_id, response) = Handler::pipelined_response(responded)?;
+                    connection.write_message_with_id(Some(request_id), response).await?;
+                    continue;
                 }
+                _ = self.shutdown.recv() => break,
             };
 
-            let message = match maybe_message {
+            let (request_id, message) = match maybe_message {
                 Some(message) => message,
                 // peer closed the socket.
                 None => return Ok(()),
             };
 
-            match message {
-                Message::Ping(mut ping) => {
-                    let (work, rx) = UnitOfWork::new_ping(self.principal.clone());
-
-                    self.request_sender.send(work).await?;
+            match request_id {
+                // Requests with id are independent of each other, so they are handled concurrently
+                // and may be responded out of order.
+                Some(request_id)
+                    if self.protocol.supports(Hello::PIPELINING)
+                        && self.transaction.is_none()
+                        && RequestContext::is_independent(&message) =>
+                {
+                    let context = self.context();
+                    pipelined.spawn(async move {
+                        let response = context.respond(message).await?;
+                        Ok((request_id, response))
+                    });
+                }
+                _ => {
+                    // Wait for the pipelined requests so that this request observes their effects.
+                    while let Some(responded) = pipelined.join_next().await {
+                        let (request_id, response) = Handler::pipelined_response(responded)?;
+                        connection
+                            .write_message_with_id(Some(request_id), response)
+                            .await?;
+                    }
 
-                    let ping_result = rx.await?;
-                    match ping_result {
-                        Ok(time) => {
-                            ping.record_server_time(time);
-                            connection.write_message(ping).await?;
+                    match message {
+                        // Streams read and write the connection until the value is transferred.
+                        Message::SetStream(set) => {
+                            self.set_stream(connection, request_id, set).await?
+                        }
+                        Message::GetStream(get) => {
+                            self.get_stream(connection, request_id, get).await?
                         }
-                        Err(err) if err.is_unauthorized() => {
+                        message => {
+                            let response = self.respond(message).await?;
                             connection
-                                .write_message(Fail::new(FailCode::Unauthenticated))
+                                .write_message_with_id(request_id, response)
                                 .await?;
                         }
-                        _ => unreachable!(),
                     }
                 }
-                Message::Set(set) => {
-                    // TODO: store namespace as state.
-                    let set = Set {
-                        namespace: "default".into(),
-                        table: "default".into(),
-                        key: set.key,
-                        value: set.value,
-                    };
-                    let (work, rx) = UnitOfWork::new_set(self.principal.clone(), set);
-                    self.request_sender.send(work).await?;
+            }
+        }
 
-                    match rx.await? {
-                        // TODO: write back previous value.
-                        Ok(_) => connection.write_message(Success::new()).await?,
-                        _ => todo!(),
-                    }
+        // Respond the pipelined requests in progress before closing.
+        while let Some(responded) = pipelined.join_next().await {
+            let (request_id, response) = Handler::pipelined_response(responded)?;
+            connection
+                .write_message_with_id(Some(request_id), response)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // Write the value uploaded by the chunks following SetStream.
+    // chunks are read to the last one even if the upload failed, so that the next message is read
+    // from the message boundary.
+    async fn set_stream<T>(
+        &mut self,
+        connection: &mut Connection<T>,
+        request_id: Option<RequestId>,
+        set: SetStream,
+    ) -> Result<()>
+    where
+        T: AsyncWrite + AsyncRead + Unpin,
+    {
+        let mut failure = self.transaction.as_ref().map(|_| {
+            Fail::new(FailCode::InvalidArgument)
+                .with_message("stream is not supported in transaction")
+        });
+        let mut upload = None;
+        let mut version = None;
+
+        loop {
+            let chunk = match connection.read_message().await? {
+                Some(Message::Chunk(chunk)) => chunk,
+                // Client aborted the upload. the table aborts it after a while.
+                Some(Message::Fail(_)) => {
+                    let fail =
+                        Fail::new(FailCode::InvalidArgument).with_message("upload is aborted");
+                    return connection.write_message_with_id(request_id, fail).await;
                 }
-                Message::Get(get) => {
-                    let get = Get {
-                        namespace: "default".into(),
-                        table: "default".into(),
-                        key: get.key,
-                    };
-                    let (work, rx) = UnitOfWork::new_get(self.principal.clone(), get);
-                    self.request_sender.send(work).await?;
+                Some(_) => {
+                    return Err(ErrorKind::NetworkFraming("chunk is expected".to_owned()).into())
+                }
+                // Upload is aborted by the table after a while.
+                None => return Ok(()),
+            };
+            let last = chunk.last;
+
+            if failure.is_none() {
+                let write = WriteChunk {
+                    namespace: self.namespace.clone(),
+                    table: self.table.clone(),
+                    key: set.key.clone(),
+                    upload,
+                    data: chunk.data,
+                    last,
+                    ttl: set.ttl,
+                };
+                let (work, rx) = UnitOfWork::new_write_chunk(self.principal.clone(), write);
+                self.request_sender.send(work).await?;
 
-                    match rx.await? {
-                        Ok(Some(value)) => {
-                            connection.write_message(Success::with_value(value)).await?
-                        }
-                        Ok(None) => connection.write_message(Success::new()).await?,
-                        _ => unreachable!(),
+                match rx.await? {
+                    Ok(written) => {
+                        upload = Some(written.upload);
+                        version = written.version;
                     }
+                    Err(err) => failure = Some(Fail::from(&err)),
                 }
-                Message::Delete(delete) => {
-                    let delete = Delete {
-                        namespace: "default".into(),
-                        table: "default".into(),
-                        key: delete.key,
-                    };
-                    let (work, rx) = UnitOfWork::new_delete(self.principal.clone(), delete);
-                    self.request_sender.send(work).await?;
+            }
+            if last {
+                break;
+            }
+        }
+
+        let response: MessageFrames = match (failure, version) {
+            (Some(fail), _) => fail.into(),
+            (None, Some(version)) => Success::new().with_version(version).into(),
+            (None, None) => Fail::new(FailCode::Internal).into(),
+        };
+        connection.write_message_with_id(request_id, response).await
+    }
+
+    // Send the value by chunks following the Success containing the version.
+    // the value read by the chunks is the same version, otherwise the download is aborted by Fail.
+    async fn get_stream<T>(
+        &mut self,
+        connection: &mut Connection<T>,
+        request_id: Option<RequestId>,
+        get: GetStream,
+    ) -> Result<()>
+    where
+        T: AsyncWrite + AsyncRead + Unpin,
+    {
+        if self.transaction.is_some() {
+            let fail = Fail::new(FailCode::InvalidArgument)
+                .with_message("stream is not supported in transaction");
+            return connection.write_message_with_id(request_id, fail).await;
+        }
+
+        let mut offset = 0;
+        let mut version = None;
+        loop {
+            let read = ReadChunk {
+                namespace: self.namespace.clone(),
+                table: self.table.clone(),
+                key: get.key.clone(),
+                offset,
+                len: self.chunk_bytes,
+                version,
+            };
+            let (work, rx) = UnitOfWork::new_read_chunk(self.principal.clone(), read);
+            self.request_sender.send(work).await?;
+
+            let chunk = match rx.await? {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => {
+                    return connection
+                        .write_message_with_id(request_id, Success::new())
+                        .await
+                }
+                Err(err) => {
+                    return connection
+                        .write_message_with_id(request_id, Fail::from(&err))
+                        .await
+                }
+            };
+
+            if version.is_none() {
+                version = Some(chunk.version);
+                connection
+                    .feed_message(request_id, Success::new().with_version(chunk.version))
+                    .await?;
+            }
+            offset += chunk.data.len() as u64;
+            let last = offset >= chunk.total_bytes || chunk.data.is_empty();
+            connection
+                .write_message_with_id(request_id, Chunk::new(chunk.data, last))
+                .await?;
+            if last {
+                return Ok(());
+            }
+        }
+    }
+
+    fn pipelined_response(
+        responded: std::result::Result<Result<(RequestId, MessageFrames)>, JoinError>,
+    ) -> Result<(RequestId, MessageFrames)> {
+        responded.map_err(|err| ErrorKind::Internal(format!("pipelined request {}", err)))?
+    }
+
+    fn context(&self) -> RequestContext {
+        RequestContext {
+            principal: self.principal.clone(),
+            namespace: self.namespace.clone(),
+            table: self.table.clone(),
+            request_sender: self.request_sender.clone(),
+        }
+    }
+
+    // Respond to the request which may depend on the connection state.
+    async fn respond(&mut self, message: Message) -> Result<MessageFrames> {
+        if let Message::Authenticate(auth) = message {
+            return self.reauthenticate(auth).await;
+        }
+        if self.transaction.is_some() {
+            return self.respond_in_transaction(message).await;
+        }
+
+        match message {
+            Message::Use(use_table) => self.use_table(use_table).await,
+            Message::Begin(_) => {
+                self.transaction = Some(Commit::default());
+                Ok(Success::new().into())
+            }
+            Message::Commit(_) | Message::Abort(_) => Ok(Fail::new(FailCode::InvalidArgument)
+                .with_message("no transaction in progress")
+                .into()),
+            message => self.context().respond(message).await,
+        }
+    }
+
+    // Authenticate the authenticated connection again, so that the connection authenticated by
+    // the listener can switch the user.
+    async fn reauthenticate(&mut self, auth: Authenticate) -> Result<MessageFrames> {
+        let (work, rx) = UnitOfWork::new_authenticate(self.principal.clone(), auth);
+        self.request_sender.send(work).await?;
+
+        match rx.await?? {
+            Some(principal) => {
+                self.principal = Arc::new(principal);
+                Ok(Success::new().into())
+            }
+            None => Ok(Fail::from(FailCode::Unauthenticated).into()),
+        }
+    }
+
+    async fn use_table(&mut self, use_table: UseTable) -> Result<MessageFrames> {
+        let (work, rx) = UnitOfWork::new_lookup_table(
+            self.principal.clone(),
+            LookupTable {
+                namespace: use_table.namespace.clone(),
+                table: use_table.table.clone(),
+            },
+        );
+        self.request_sender.send(work).await?;
 
-                    match rx.await? {
-                        Ok(Some(value)) => {
-                            connection.write_message(Success::with_value(value)).await?
+        let result = rx.await?;
+        if result.is_ok() {
+            self.namespace = use_table.namespace;
+            self.table = use_table.table;
+        }
+
+        Ok(Handler::result_response(result))
+    }
+
+    // Apply the message to the transaction in progress.
+    async fn respond_in_transaction(&mut self, message: Message) -> Result<MessageFrames> {
+        let response = match message {
+            Message::Get(get) => {
+                let key = self.table_key(get.key);
+                match self.transaction_read(key).await {
+                    Ok(Some(value)) => Success::with_value(value).into(),
+                    Ok(None) => Success::new().into(),
+                    Err(err) => Fail::from(&err).into(),
+                }
+            }
+            Message::Set(set) if set.ttl.is_some() || set.condition.is_some() => {
+                Fail::new(FailCode::InvalidArgument)
+                    .with_message("ttl and condition are not supported in transaction")
+                    .into()
+            }
+            Message::Set(set) => {
+                let key = self.table_key(set.key);
+                self.transaction
+                    .as_mut()
+                    .unwrap()
+                    .writes
+                    .insert(key, Some(set.value));
+                Success::new().into()
+            }
+            Message::Delete(delete) => {
+                let key = self.table_key(delete.key);
+                match self.transaction_read(key.clone()).await {
+                    Ok(previous) => {
+                        self.transaction.as_mut().unwrap().writes.insert(key, None);
+                        match previous {
+                            Some(previous) => Success::with_value(previous).into(),
+                            None => Success::new().into(),
                         }
-                        Ok(None) => connection.write_message(Success::new()).await?,
-                        _ => unreachable!(),
                     }
+                    Err(err) => Fail::from(&err).into(),
                 }
-                Message::Authenticate(_) => unreachable!(),
-                Message::Success(_) => unreachable!(),
-                Message::Fail(_) => unreachable!(),
             }
+            Message::Commit(_) => {
+                let commit = self.transaction.take().unwrap();
+                let (work, rx) = UnitOfWork::new_commit(self.principal.clone(), commit);
+                self.request_sender.send(work).await?;
+
+                Handler::result_response(rx.await?)
+            }
+            Message::Abort(_) => {
+                self.transaction = None;
+                Success::new().into()
+            }
+            Message::Begin(_) => Fail::new(FailCode::InvalidArgument)
+                .with_message("transaction already in progress")
+                .into(),
+            Message::Scan(_)
+            | Message::MSet(_)
+            | Message::MGet(_)
+            | Message::MDelete(_)
+            | Message::Expire(_)
+            | Message::Persist(_)
+            | Message::Ttl(_) => Fail::new(FailCode::InvalidArgument)
+                .with_message("message is not supported in transaction")
+                .into(),
+            Message::Use(use_table) => return self.use_table(use_table).await,
+            message => return self.context().respond(message).await,
+        };
+
+        Ok(response)
+    }
+
+    // Read the key in the transaction. buffered write is returned if the key was written,
+    // otherwise the version read first is recorded to detect the conflict on commit.
+    async fn transaction_read(&mut self, key: TableKey) -> Result<Option<Value>> {
+        let transaction = self.transaction.as_mut().unwrap();
+        if let Some(value) = transaction.writes.get(&key) {
+            return Ok(value.clone());
         }
 
-        Ok(())
+        let get = Get {
+            namespace: key.namespace.clone(),
+            table: key.table.clone(),
+            key: key.key.clone(),
+        };
+        let (work, rx) = UnitOfWork::new_get(self.principal.clone(), get);
+        self.request_sender.send(work).await?;
+
+        let versioned = rx.await??;
+        let version = versioned.as_ref().map_or(0, |versioned| versioned.version);
+        transaction.reads.entry(key).or_insert(version);
+
+        Ok(versioned.map(|versioned| versioned.value))
+    }
+
+    fn table_key(&self, key: Key) -> TableKey {
+        TableKey {
+            namespace: self.namespace.clone(),
+            table: self.table.clone(),
+            key,
+        }
+    }
+
+    // Success or Fail corresponding to the result of unit of work.
+    fn result_response(result: Result<()>) -> MessageFrames {
+        match result {
+            Ok(()) => Success::new().into(),
+            Err(err) => Fail::from(&err).into(),
+        }
+    }
+
+    // Values or Fail corresponding to the result of batch unit of work.
+    fn values_response(result: Result<Values>) -> MessageFrames {
+        match result {
+            Ok(values) => values.into(),
+            Err(err) => Fail::from(&err).into(),
+        }
+    }
+
+    fn batch_too_large() -> MessageFrames {
+        let message = format!("batch exceeds {} keys", Handler::MAX_BATCH_KEYS);
+        Fail::new(FailCode::InvalidArgument)
+            .with_message(message)
+            .into()
+    }
+
+    // TtlResult or Fail corresponding to the result of expiry unit of work.
+    fn ttl_response(result: Result<Option<Expiry>>) -> MessageFrames {
+        match result {
+            Ok(expiry) => TtlResult::new(expiry).into(),
+            Err(err) => Fail::from(&err).into(),
+        }
+    }
+}
+
+impl RequestContext {
+    // Whether the request can be handled regardless of the other requests in progress.
+    // only the data operations on the table in use are. namespace and table operations wait for
+    // the requests before them, so that those requests are not run against the dropped table and
+    // the listing reflects the tables created before.
+    fn is_independent(message: &Message) -> bool {
+        matches!(
+            message,
+            Message::Ping(_)
+                | Message::Set(_)
+                | Message::Get(_)
+                | Message::Delete(_)
+                | Message::Scan(_)
+                | Message::MSet(_)
+                | Message::MGet(_)
+                | Message::MDelete(_)
+                | Message::Expire(_)
+                | Message::Persist(_)
+                | Message::Ttl(_)
+        )
+    }
+
+    async fn respond(self, message: Message) -> Result<MessageFrames> {
+        let response = match message {
+            Message::Ping(mut ping) => {
+                let (work, rx) = UnitOfWork::new_ping(self.principal.clone());
+
+                self.request_sender.send(work).await?;
+
+                let ping_result = rx.await?;
+                match ping_result {
+                    Ok(time) => {
+                        ping.record_server_time(time);
+                        ping.into()
+                    }
+                    Err(err) => Fail::from(&err).into(),
+                }
+            }
+            Message::Set(set) => {
+                let set = Set {
+                    namespace: self.namespace,
+                    table: self.table,
+                    key: set.key,
+                    value: set.value,
+                    ttl: set.ttl,
+                    condition: set.condition,
+                };
+                let (work, rx) = UnitOfWork::new_set(self.principal.clone(), set);
+                self.request_sender.send(work).await?;
+
+                match rx.await? {
+                    Ok(SetResult::Applied { previous, version }) => {
+                        let success = match previous {
+                            Some(previous) => Success::with_previous(previous),
+                            None => Success::new(),
+                        };
+                        success.with_version(version).into()
+                    }
+                    Ok(SetResult::ConditionFailed) => Fail::new(FailCode::ConditionFailed).into(),
+                    Err(err) => Fail::from(&err).into(),
+                }
+            }
+            Message::Get(get) => {
+                let get = Get {
+                    namespace: self.namespace,
+                    table: self.table,
+                    key: get.key,
+                };
+                let (work, rx) = UnitOfWork::new_get(self.principal.clone(), get);
+                self.request_sender.send(work).await?;
+
+                match rx.await? {
+                    Ok(Some(Versioned { value, version })) => {
+                        Success::with_value(value).with_version(version).into()
+                    }
+                    Ok(None) => Success::new().into(),
+                    Err(err) => Fail::from(&err).into(),
+                }
+            }
+            Message::Delete(delete) => {
+                let delete = Delete {
+                    namespace: self.namespace,
+                    table: self.table,
+                    key: delete.key,
+                };
+                let (work, rx) = UnitOfWork::new_delete(self.principal.clone(), delete);
+                self.request_sender.send(work).await?;
+
+                match rx.await? {
+                    Ok(Some(previous)) => Success::with_previous(previous).into(),
+                    Ok(None) => Success::new().into(),
+                    Err(err) => Fail::from(&err).into(),
+                }
+            }
+            Message::Scan(scan) => {
+                let scan = Scan {
+                    namespace: self.namespace,
+                    table: self.table,
+                    prefix: scan.prefix,
+                    start: scan.start,
+                    end: scan.end,
+                    cursor: scan.cursor,
+                    limit: scan.limit.clamp(1, Handler::MAX_SCAN_LIMIT) as usize,
+                    max_bytes: Handler::MAX_SCAN_BYTES,
+                };
+                let (work, rx) = UnitOfWork::new_scan(self.principal.clone(), scan);
+                self.request_sender.send(work).await?;
+
+                match rx.await? {
+                    Ok(page) => ScanPage::new(page.entries, page.next_cursor).into(),
+                    Err(err) => Fail::from(&err).into(),
+                }
+            }
+            Message::MSet(mset) if mset.entries.len() > Handler::MAX_BATCH_KEYS => {
+                Handler::batch_too_large()
+            }
+            Message::MSet(mset) => {
+                let mset = MSet {
+                    namespace: self.namespace,
+                    table: self.table,
+                    entries: mset.entries,
+                };
+                let (work, rx) = UnitOfWork::new_mset(self.principal.clone(), mset);
+                self.request_sender.send(work).await?;
+
+                Handler::result_response(rx.await?)
+            }
+            Message::MGet(mget) if mget.keys.len() > Handler::MAX_BATCH_KEYS => {
+                Handler::batch_too_large()
+            }
+            Message::MGet(mget) => {
+                let mget = MGet {
+                    namespace: self.namespace,
+                    table: self.table,
+                    keys: mget.keys,
+                };
+                let (work, rx) = UnitOfWork::new_mget(self.principal.clone(), mget);
+                self.request_sender.send(work).await?;
+
+                Handler::values_response(rx.await?.map(Values::new))
+            }
+            Message::MDelete(mdelete) if mdelete.keys.len() > Handler::MAX_BATCH_KEYS => {
+                Handler::batch_too_large()
+            }
+            Message::MDelete(mdelete) => {
+                let mdelete = MDelete {
+                    namespace: self.namespace,
+                    table: self.table,
+                    keys: mdelete.keys,
+                };
+                let (work, rx) = UnitOfWork::new_mdelete(self.principal.clone(), mdelete);
+                self.request_sender.send(work).await?;
+
+                Handler::values_response(rx.await?.map(Values::with_previous))
+            }
+            Message::Expire(expire) => {
+                let expire = Expire {
+                    namespace: self.namespace,
+                    table: self.table,
+                    key: expire.key,
+                    ttl: Some(expire.ttl),
+                };
+                let (work, rx) = UnitOfWork::new_expire(self.principal.clone(), expire);
+                self.request_sender.send(work).await?;
+
+                Handler::ttl_response(rx.await?)
+            }
+            Message::Persist(persist) => {
+                let expire = Expire {
+                    namespace: self.namespace,
+                    table: self.table,
+                    key: persist.key,
+                    ttl: None,
+                };
+                let (work, rx) = UnitOfWork::new_expire(self.principal.clone(), expire);
+                self.request_sender.send(work).await?;
+
+                Handler::ttl_response(rx.await?)
+            }
+            Message::Ttl(ttl) => {
+                let ttl = Ttl {
+                    namespace: self.namespace,
+                    table: self.table,
+                    key: ttl.key,
+                };
+                let (work, rx) = UnitOfWork::new_ttl(self.principal.clone(), ttl);
+                self.request_sender.send(work).await?;
+
+                Handler::ttl_response(rx.await?)
+            }
+            Message::CreateNamespace(create) => {
+                let create = CreateNamespace {
+                    namespace: create.namespace,
+                };
+                let (work, rx) = UnitOfWork::new_create_namespace(self.principal.clone(), create);
+                self.request_sender.send(work).await?;
+
+                Handler::result_response(rx.await?)
+            }
+            Message::DropNamespace(drop) => {
+                let drop = DropNamespace {
+                    namespace: drop.namespace,
+                };
+                let (work, rx) = UnitOfWork::new_drop_namespace(self.principal.clone(), drop);
+                self.request_sender.send(work).await?;
+
+                Handler::result_response(rx.await?)
+            }
+            Message::CreateTable(create) => {
+                let create = CreateTable {
+                    namespace: create.namespace,
+                    table: create.table,
+                };
+                let (work, rx) = UnitOfWork::new_create_table(self.principal.clone(), create);
+                self.request_sender.send(work).await?;
+
+                Handler::result_response(rx.await?)
+            }
+            Message::DropTable(drop) => {
+                let drop = DropTable {
+                    namespace: drop.namespace,
+                    table: drop.table,
+                };
+                let (work, rx) = UnitOfWork::new_drop_table(self.principal.clone(), drop);
+                self.request_sender.send(work).await?;
+
+                Handler::result_response(rx.await?)
+            }
+            Message::CompactTable(compact) => {
+                let compact = CompactTable {
+                    namespace: compact.namespace,
+                    table: compact.table,
+                };
+                let (work, rx) = UnitOfWork::new_compact_table(self.principal.clone(), compact);
+                self.request_sender.send(work).await?;
+
+                Handler::result_response(rx.await?)
+            }
+            Message::ListTables(_) => {
+                let (work, rx) = UnitOfWork::new_list_tables(self.principal.clone());
+                self.request_sender.send(work).await?;
+
+                match rx.await? {
+                    Ok(tables) => Tables::new(tables).into(),
+                    Err(err) => Fail::from(&err).into(),
+                }
+            }
+            // Handled by Handler as they depend on the connection state.
+            Message::Use(_)
+            | Message::Begin(_)
+            | Message::Commit(_)
+            | Message::Abort(_)
+            | Message::Authenticate(_)
+            | Message::SetStream(_)
+            | Message::GetStream(_) => unreachable!(),
+            Message::Chunk(_) => Fail::new(FailCode::UnexpectedMessage)
+                .with_message("chunk is sent without stream")
+                .into(),
+            Message::Hello(_) => Fail::new(FailCode::UnexpectedMessage)
+                .with_message("hello is exchanged once at connection start")
+                .into(),
+            // Responses are not expected from the client.
+            Message::Success(_)
+            | Message::Fail(_)
+            | Message::Tables(_)
+            | Message::ScanPage(_)
+            | Message::TtlResult(_)
+            | Message::Values(_) => Fail::new(FailCode::UnexpectedMessage)
+                .with_message("response message is sent as request")
+                .into(),
+        };
+
+        Ok(response)
     }
 }
 
@@ -585,7 +1626,7 @@ impl MaxConnAwareListener {
     }
 }
 
-struct ShutdownSubscriber {
+pub(super) struct ShutdownSubscriber {
     shutdown: bool,
     notify: broadcast::Receiver<ShutdownSignal>,
     // Notify completing shutdown process by dropping.
@@ -604,11 +1645,11 @@ impl ShutdownSubscriber {
         }
     }
 
-    fn is_shutdown(&self) -> bool {
+    pub(super) fn is_shutdown(&self) -> bool {
         self.shutdown
     }
 
-    async fn recv(&mut self) {
+    pub(super) async fn recv(&mut self) {
         if self.shutdown {
             return;
         }
@@ -0,0 +1,122 @@
+//! Client certificate authentication of the tls connections.
+
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio_rustls::rustls::server::danger::ClientCertVerifier;
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{pki_types, RootCertStore};
+use x509_parser::extensions::GeneralName;
+
+use crate::common::{ErrorKind, Result};
+use crate::core::{Credential, CredentialProvider};
+
+// Build the verifier of the client certificates issued by the CAs in the bundle.
+// clients without certificate are still accepted unless required,
+// so that they can authenticate by password.
+pub(super) fn client_verifier(
+    ca_bundle: &Path,
+    required: bool,
+) -> Result<Arc<dyn ClientCertVerifier>> {
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut std::io::BufReader::new(std::fs::File::open(
+        ca_bundle,
+    )?)) {
+        roots.add(cert?).map_err(|err| {
+            ErrorKind::InvalidArgument(format!("tls client ca {}: {}", ca_bundle.display(), err))
+        })?;
+    }
+
+    let builder = WebPkiClientVerifier::builder(Arc::new(roots));
+    let builder = if required {
+        builder
+    } else {
+        builder.allow_unauthenticated()
+    };
+
+    builder.build().map_err(|err| {
+        ErrorKind::InvalidArgument(format!("tls client ca {}: {}", ca_bundle.display(), err)).into()
+    })
+}
+
+// Names identifying the verified client certificate.
+// the subject common names and the dns, email and uri subject alternative names.
+pub(super) fn certificate_names(cert: &pki_types::CertificateDer) -> Vec<String> {
+    let cert = match x509_parser::parse_x509_certificate(cert.as_ref()) {
+        Ok((_, cert)) => cert,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut names: Vec<String> = cert
+        .subject()
+        .iter_common_name()
+        .filter_map(|cn| cn.as_str().ok())
+        .map(str::to_owned)
+        .collect();
+
+    if let Ok(Some(san)) = cert.subject_alternative_name() {
+        names.extend(
+            san.value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name)
+                    | GeneralName::RFC822Name(name)
+                    | GeneralName::URI(name) => Some((*name).to_owned()),
+                    _ => None,
+                }),
+        );
+    }
+
+    names
+}
+
+// Credential of the connection presenting the verified client certificate.
+pub(super) struct ClientCertificate {
+    pub(super) names: Vec<String>,
+}
+
+impl CredentialProvider for ClientCertificate {
+    fn credential(&self) -> Credential {
+        Credential::Certificate(Cow::Borrowed(&self.names))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(path: &str) -> pki_types::CertificateDer<'static> {
+        let pem = std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join(path)).unwrap();
+        let cert = rustls_pemfile::certs(&mut pem.as_slice())
+            .next()
+            .unwrap()
+            .unwrap();
+        cert
+    }
+
+    #[test]
+    fn names_of_certificate() {
+        assert_eq!(
+            certificate_names(&load("files/client.pem")),
+            vec![
+                "kvsduser".to_owned(),
+                "kvsduser.client.local".to_owned(),
+                "spiffe://kvsd.local/kvsduser".to_owned(),
+            ]
+        );
+        assert_eq!(
+            certificate_names(&load("files/localhost.pem")),
+            vec!["localhost".to_owned(), "localhost".to_owned()]
+        );
+        assert!(certificate_names(&pki_types::CertificateDer::from(vec![0u8; 8])).is_empty());
+    }
+
+    #[test]
+    fn verifier() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        assert!(client_verifier(&manifest_dir.join("files/ca.pem"), true).is_ok());
+        assert!(client_verifier(&manifest_dir.join("files/none.pem"), false).is_err());
+    }
+}
@@ -3,6 +3,7 @@ use std::{sync::Arc, time::Duration};
 use tokio::net::TcpListener;
 
 use kvsd::client::Api;
+use tokio_stream::StreamExt;
 
 mod common;
 
@@ -19,9 +20,34 @@ fn key_value_crud() {
         // Setup user credential.
         config.kvsd.users = vec![kvsd::core::UserEntry {
             username: "test".into(),
-            password: "test".into(),
+            // kvsadmin user hash-password test
+            password_hash: Some("$argon2id$v=19$m=19456,t=2,p=1$b9Zx0LIWM89svz5k0i21cw$uOwKUhkLSLPiWb5I90TZ9rBa3GGlKnI9BL9rqz9/sL0".into()),
+            password: None,
+            tokens: vec!["test-token".into()],
+            certificate_names: vec![],
+            roles: vec!["admin".into()],
+        }, kvsd::core::UserEntry {
+            username: "reader".into(),
+            password_hash: None,
+            password: Some("reader".into()),
+            tokens: vec![],
+            certificate_names: vec![],
+            roles: vec!["reader".into()],
         }];
+        config.kvsd.allow_plaintext_passwords = true;
+        config.kvsd.roles = serde_yaml::from_str(
+            r#"
+admin:
+  - scope: "*"
+    permissions: [admin]
+reader:
+  - scope: "default/*"
+    permissions: [read]
+"#,
+        )
+        .unwrap();
         config.server.set_disable_tls(&mut Some(true));
+        config.server.set_unix_socket_user(&mut Some("test".into()));
 
         // Test Server listen addr
         let addr = ("localhost", 47379);
@@ -30,6 +56,14 @@ fn key_value_crud() {
 
         initializer.set_root_dir(root_dir.path());
         initializer.set_listener(TcpListener::bind(addr).await.unwrap());
+        let resp_addr = ("localhost", 46379);
+        initializer.set_resp_listener(TcpListener::bind(resp_addr).await.unwrap());
+        let unix_socket = root_dir.path().join("kvsd.sock");
+        initializer.set_unix_listener(tokio::net::UnixListener::bind(&unix_socket).unwrap());
+        #[cfg(feature = "http")]
+        let http_addr = ("localhost", 48080);
+        #[cfg(feature = "http")]
+        initializer.set_http_listener(TcpListener::bind(http_addr).await.unwrap());
 
         initializer.init_dir().await.unwrap();
 
@@ -78,11 +112,727 @@ fn key_value_crud() {
         assert_eq!(Some(value.clone()), got);
 
         let got = client.delete(key.clone()).await.unwrap();
-        assert_eq!(Some(value.clone()), got);
+        assert_eq!(Some(kvsd::Previous::Value(value.clone())), got);
 
         let got = client.get(key.clone()).await.unwrap();
         assert!(got.is_none());
 
+        // Scan
+        for i in 0..5 {
+            let key = kvsd::Key::new(format!("scan:{}", i)).unwrap();
+            client.set(key, value.clone()).await.unwrap();
+        }
+        let scanned = client
+            .scan(kvsd::client::ScanOptions {
+                limit: Some(2),
+                ..kvsd::client::ScanOptions::with_prefix("scan:")
+            })
+            .map(|entry| entry.unwrap().0.into_string())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            scanned,
+            vec!["scan:0", "scan:1", "scan:2", "scan:3", "scan:4"]
+        );
+
+        // Batch
+        let batch_keys = (0..3)
+            .map(|i| kvsd::Key::new(format!("batch:{}", i)).unwrap())
+            .collect::<Vec<_>>();
+        client
+            .mset(
+                batch_keys
+                    .iter()
+                    .map(|key| (key.clone(), value.clone()))
+                    .collect(),
+            )
+            .await
+            .unwrap();
+        let mut keys = batch_keys.clone();
+        keys.push(kvsd::Key::new("batch:missing").unwrap());
+        assert_eq!(
+            client.mget(keys.clone()).await.unwrap(),
+            vec![
+                Some(value.clone()),
+                Some(value.clone()),
+                Some(value.clone()),
+                None
+            ]
+        );
+        assert_eq!(
+            client.mdelete(keys.clone()).await.unwrap(),
+            vec![
+                Some(kvsd::Previous::Value(value.clone())),
+                Some(kvsd::Previous::Value(value.clone())),
+                Some(kvsd::Previous::Value(value.clone())),
+                None
+            ]
+        );
+        assert_eq!(client.mget(keys).await.unwrap(), vec![None; 4]);
+
+        // Pipeline
+        use kvsd::client::{PipelineMode, Request};
+        let pipeline_key = |i: usize| kvsd::Key::new(format!("pipeline:{}", i)).unwrap();
+        let responses = client
+            .pipeline(
+                (0..10)
+                    .map(|i| Request::Set(pipeline_key(i), value.clone()))
+                    .collect(),
+                PipelineMode::Concurrent,
+            )
+            .await
+            .unwrap();
+        assert!(responses
+            .iter()
+            .all(|response| matches!(response, Ok(None))));
+        let responses = client
+            .pipeline(
+                (0..10).map(|i| Request::Get(pipeline_key(i))).collect(),
+                PipelineMode::Concurrent,
+            )
+            .await
+            .unwrap();
+        assert!(responses
+            .iter()
+            .all(|response| matches!(response, Ok(Some(got)) if *got == value)));
+        let value2 = kvsd::Value::new(b"value2".as_ref()).unwrap();
+        let responses = client
+            .pipeline(
+                vec![
+                    Request::Set(pipeline_key(0), value2.clone()),
+                    Request::Get(pipeline_key(0)),
+                    Request::Delete(pipeline_key(0)),
+                    Request::Get(pipeline_key(0)),
+                ],
+                PipelineMode::Ordered,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            responses
+                .into_iter()
+                .map(Result::unwrap)
+                .collect::<Vec<_>>(),
+            vec![
+                Some(value.clone()),
+                Some(value2.clone()),
+                Some(value2),
+                None
+            ]
+        );
+
+        // Stream value larger than the maximum value bytes.
+        let large = (0..kvsd::protocol::MAX_VALUE_BYTES + 1)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let stream_key = kvsd::Key::new("stream").unwrap();
+        // New key is versioned after the greatest version the table has assigned.
+        assert!(
+            client
+                .set_stream(stream_key.clone(), &mut large.as_slice())
+                .await
+                .unwrap()
+                > 0
+        );
+        let mut got = Vec::new();
+        assert_eq!(
+            client
+                .get_stream(stream_key.clone(), &mut got)
+                .await
+                .unwrap(),
+            Some(large.len() as u64)
+        );
+        assert!(got == large);
+        assert!(matches!(
+            client.get(stream_key.clone()).await,
+            Err(kvsd::KvsdError::MaxValueBytes { .. })
+        ));
+        assert_eq!(
+            client.delete(stream_key.clone()).await.unwrap(),
+            Some(kvsd::Previous::TooLarge(large.len() as u64))
+        );
+        assert_eq!(
+            client
+                .get_stream(stream_key, &mut Vec::new())
+                .await
+                .unwrap(),
+            None
+        );
+
+        // Conditional set
+        let cas_key = kvsd::Key::new("cas").unwrap();
+        let cas_value = |v: &str| kvsd::Value::new(v.as_bytes()).unwrap();
+        let created = client
+            .set_if(cas_key.clone(), cas_value("v1"), kvsd::Condition::Absent)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            None,
+            client
+                .set_if(cas_key.clone(), cas_value("v2"), kvsd::Condition::Absent)
+                .await
+                .unwrap()
+        );
+        let (got, version) = client
+            .get_versioned(cas_key.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(got, cas_value("v1"));
+        assert_eq!(version, created);
+        assert_eq!(
+            Some(version + 1),
+            client
+                .set_if(
+                    cas_key.clone(),
+                    cas_value("v2"),
+                    kvsd::Condition::Version(version)
+                )
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            client
+                .set_if(
+                    cas_key.clone(),
+                    cas_value("v3"),
+                    kvsd::Condition::Version(version)
+                )
+                .await
+                .unwrap()
+        );
+
+        // Expiration
+        let ttl_key = kvsd::Key::new("ttl").unwrap();
+        client
+            .set_with_ttl(ttl_key.clone(), value.clone(), Duration::from_millis(50))
+            .await
+            .unwrap();
+        assert!(matches!(
+            client.ttl(ttl_key.clone()).await.unwrap(),
+            Some(kvsd::Expiry::In(_))
+        ));
+        assert!(client.persist(ttl_key.clone()).await.unwrap());
+        assert_eq!(
+            Some(kvsd::Expiry::Never),
+            client.ttl(ttl_key.clone()).await.unwrap()
+        );
+        assert!(client
+            .expire(ttl_key.clone(), Duration::from_millis(50))
+            .await
+            .unwrap());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(client.get(ttl_key.clone()).await.unwrap().is_none());
+        assert_eq!(None, client.ttl(ttl_key.clone()).await.unwrap());
+        assert!(!client.persist(ttl_key).await.unwrap());
+
+        // Table management
+        client.create_namespace("ns1".into()).await.unwrap();
+        client
+            .create_table("ns1".into(), "table1".into())
+            .await
+            .unwrap();
+        assert!(matches!(
+            client.create_table("ns1".into(), "table1".into()).await,
+            Err(kvsd::KvsdError::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            client.create_table("ns2".into(), "table1".into()).await,
+            Err(kvsd::KvsdError::NamespaceNotFound(_))
+        ));
+        assert!(matches!(
+            client.create_table("ns1".into(), "../table1".into()).await,
+            Err(kvsd::KvsdError::InvalidArgument(_))
+        ));
+        assert_eq!(
+            client.list_tables().await.unwrap(),
+            vec![
+                ("default".to_owned(), "default".to_owned()),
+                ("ns1".to_owned(), "table1".to_owned()),
+            ]
+        );
+        assert!(root_dir
+            .path()
+            .join("namespaces/ns1/table1/table1.kvsd")
+            .exists());
+
+        // Data is isolated per table.
+        client
+            .use_table("ns1".into(), "table1".into())
+            .await
+            .unwrap();
+        client.set(key.clone(), value.clone()).await.unwrap();
+        assert_eq!(Some(value.clone()), client.get(key.clone()).await.unwrap());
+        client
+            .use_table("default".into(), "default".into())
+            .await
+            .unwrap();
+        assert!(client.get(key.clone()).await.unwrap().is_none());
+        assert!(matches!(
+            client.use_table("ns1".into(), "table2".into()).await,
+            Err(kvsd::KvsdError::TableNotFound(_))
+        ));
+
+        // Compaction runs on the server, and keeps the data.
+        client
+            .compact_table("ns1".into(), "table1".into())
+            .await
+            .unwrap();
+        assert!(matches!(
+            client.compact_table("ns1".into(), "table2".into()).await,
+            Err(kvsd::KvsdError::TableNotFound(_))
+        ));
+        client
+            .use_table("ns1".into(), "table1".into())
+            .await
+            .unwrap();
+        assert_eq!(Some(value.clone()), client.get(key.clone()).await.unwrap());
+        client
+            .use_table("default".into(), "default".into())
+            .await
+            .unwrap();
+
+        // Transaction across tables.
+        let tx_key = kvsd::Key::new("tx").unwrap();
+        let tx_value = |v: &str| kvsd::Value::new(v.as_bytes()).unwrap();
+        client.begin().await.unwrap();
+        client
+            .set(tx_key.clone(), tx_value("default"))
+            .await
+            .unwrap();
+        assert_eq!(
+            Some(tx_value("default")),
+            client.get(tx_key.clone()).await.unwrap()
+        );
+        client
+            .use_table("ns1".into(), "table1".into())
+            .await
+            .unwrap();
+        client
+            .set(tx_key.clone(), tx_value("table1"))
+            .await
+            .unwrap();
+        assert_eq!(
+            Some(kvsd::Previous::Value(value.clone())),
+            client.delete(key.clone()).await.unwrap()
+        );
+        client.commit().await.unwrap();
+        assert!(client.get(key.clone()).await.unwrap().is_none());
+        assert_eq!(
+            Some(tx_value("table1")),
+            client.get(tx_key.clone()).await.unwrap()
+        );
+
+        // Aborted writes are discarded.
+        client.begin().await.unwrap();
+        client
+            .set(tx_key.clone(), tx_value("aborted"))
+            .await
+            .unwrap();
+        client.abort().await.unwrap();
+        assert_eq!(
+            Some(tx_value("table1")),
+            client.get(tx_key.clone()).await.unwrap()
+        );
+
+        // Commit conflicts with the write made after the transaction read the key.
+        let mut other =
+            kvsd::client::tcp::UnauthenticatedClient::insecure_from_addr(addr.0, addr.1)
+                .await
+                .unwrap()
+                .authenticate("test", "test")
+                .await
+                .unwrap();
+        other
+            .use_table("ns1".into(), "table1".into())
+            .await
+            .unwrap();
+        client.begin().await.unwrap();
+        client.get(tx_key.clone()).await.unwrap();
+        client.set(tx_key.clone(), tx_value("lost")).await.unwrap();
+        other.set(tx_key.clone(), tx_value("other")).await.unwrap();
+        assert!(matches!(
+            client.commit().await,
+            Err(kvsd::KvsdError::Conflict(_))
+        ));
+        assert_eq!(
+            Some(tx_value("other")),
+            client.get(tx_key.clone()).await.unwrap()
+        );
+        client
+            .use_table("default".into(), "default".into())
+            .await
+            .unwrap();
+        assert_eq!(
+            Some(tx_value("default")),
+            client.get(tx_key.clone()).await.unwrap()
+        );
+        assert!(root_dir.path().join("transaction.log").exists());
+
+        // Namespace with tables can not be dropped.
+        assert!(matches!(
+            client.drop_namespace("ns1".into()).await,
+            Err(kvsd::KvsdError::InvalidArgument(_))
+        ));
+        client
+            .drop_table("ns1".into(), "table1".into())
+            .await
+            .unwrap();
+        // Requests to the dropped table fail without closing the connection.
+        assert!(matches!(
+            other.get(tx_key.clone()).await,
+            Err(kvsd::KvsdError::TableNotFound(_))
+        ));
+        assert!(matches!(
+            other.set(tx_key.clone(), tx_value("dropped")).await,
+            Err(kvsd::KvsdError::TableNotFound(_))
+        ));
+        assert!(matches!(
+            other.delete(tx_key.clone()).await,
+            Err(kvsd::KvsdError::TableNotFound(_))
+        ));
+        other.ping().await.unwrap();
+        client.drop_namespace("ns1".into()).await.unwrap();
+        assert!(!root_dir.path().join("namespaces/ns1").exists());
+        assert_eq!(
+            client.list_tables().await.unwrap(),
+            vec![("default".to_owned(), "default".to_owned())]
+        );
+
+        // Invalid frames
+        {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+            // Unauthenticated client claims the bytes larger than the limit.
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            stream.write_all(b"*2\r\n$1073741824\r\n").await.unwrap();
+
+            let mut buf = Vec::new();
+            stream.read_to_end(&mut buf).await.unwrap();
+            let reply = String::from_utf8_lossy(&buf);
+            assert!(reply.contains("INVALID_FRAME"), "{}", reply);
+            assert!(reply.contains("message exceeds"), "{}", reply);
+        }
+
+        // Client built before Hello
+        {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+            // Read until the reply contains the expected bytes.
+            async fn read_until(stream: &mut tokio::net::TcpStream, expected: &[u8]) {
+                let mut buf = Vec::new();
+                while !buf.windows(expected.len()).any(|w| w == expected) {
+                    let mut chunk = [0; 1024];
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    assert!(n > 0, "{}", String::from_utf8_lossy(&buf));
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+            }
+
+            client
+                .set(
+                    kvsd::Key::new("legacy").unwrap(),
+                    kvsd::Value::new(b"legacy_value".as_ref()).unwrap(),
+                )
+                .await
+                .unwrap();
+
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            // Authenticate without Hello.
+            stream
+                .write_all(b"*3\r\n#\x02+test\r\n+test\r\n")
+                .await
+                .unwrap();
+            read_until(&mut stream, b"#\x03").await;
+            // Get
+            stream.write_all(b"*2\r\n#\x06+legacy\r\n").await.unwrap();
+            read_until(&mut stream, b"legacy_value").await;
+        }
+
+        // Resp
+        {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+            let mut resp = tokio::net::TcpStream::connect(resp_addr).await.unwrap();
+            // Send the command and assert the reply.
+            async fn call(resp: &mut tokio::net::TcpStream, command: &[&str], reply: &[u8]) {
+                let mut request = format!("*{}\r\n", command.len());
+                for arg in command {
+                    request.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+                }
+                resp.write_all(request.as_bytes()).await.unwrap();
+
+                let mut buf = vec![0; reply.len()];
+                resp.read_exact(&mut buf).await.unwrap();
+                assert_eq!(
+                    String::from_utf8_lossy(&buf),
+                    String::from_utf8_lossy(reply),
+                    "{:?}",
+                    command
+                );
+            }
+
+            call(
+                &mut resp,
+                &["GET", "resp1"],
+                b"-NOAUTH Authentication required.\r\n",
+            )
+            .await;
+            call(
+                &mut resp,
+                &["AUTH", "test", "wrong"],
+                b"-WRONGPASS invalid username-password pair or user is disabled.\r\n",
+            )
+            .await;
+            call(&mut resp, &["AUTH", "test", "test"], b"+OK\r\n").await;
+            call(&mut resp, &["PING"], b"+PONG\r\n").await;
+            call(&mut resp, &["SET", "resp1", "v1"], b"+OK\r\n").await;
+            call(&mut resp, &["SET", "resp1", "v2", "NX"], b"$-1\r\n").await;
+            call(&mut resp, &["GET", "resp1"], b"$2\r\nv1\r\n").await;
+            call(&mut resp, &["SET", "resp2", "v2", "EX", "100"], b"+OK\r\n").await;
+            call(&mut resp, &["TTL", "resp2"], b":100\r\n").await;
+            call(&mut resp, &["TTL", "resp1"], b":-1\r\n").await;
+            call(&mut resp, &["EXPIRE", "resp1", "50"], b":1\r\n").await;
+            call(&mut resp, &["EXISTS", "resp1", "resp2", "resp3"], b":2\r\n").await;
+            call(
+                &mut resp,
+                &["SCAN", "0", "MATCH", "resp*", "COUNT", "1"],
+                b"*2\r\n$1\r\n1\r\n*1\r\n$5\r\nresp1\r\n",
+            )
+            .await;
+            call(
+                &mut resp,
+                &["SCAN", "1", "MATCH", "resp*", "COUNT", "1"],
+                b"*2\r\n$1\r\n0\r\n*1\r\n$5\r\nresp2\r\n",
+            )
+            .await;
+            call(&mut resp, &["DEL", "resp1", "resp2", "resp3"], b":2\r\n").await;
+            call(&mut resp, &["TTL", "resp1"], b":-2\r\n").await;
+            call(
+                &mut resp,
+                &["HGET", "resp1"],
+                b"-ERR unknown command 'HGET'\r\n",
+            )
+            .await;
+            call(&mut resp, &["QUIT"], b"+OK\r\n").await;
+        }
+
+        // Unix socket authenticates the connection without password.
+        {
+            let mut unix_client =
+                kvsd::client::tcp::UnauthenticatedClient::from_unix_socket(&unix_socket)
+                    .await
+                    .unwrap()
+                    .authenticated_by_peer();
+            let unix_key = kvsd::Key::new("unix1").unwrap();
+            unix_client
+                .set(unix_key.clone(), value.clone())
+                .await
+                .unwrap();
+            assert_eq!(
+                Some(value.clone()),
+                client.get(unix_key.clone()).await.unwrap()
+            );
+
+            // Authenticate again as another user.
+            let mut unix_client =
+                kvsd::client::tcp::UnauthenticatedClient::from_unix_socket(&unix_socket)
+                    .await
+                    .unwrap()
+                    .authenticate("test", "test")
+                    .await
+                    .unwrap();
+            assert_eq!(
+                Some(kvsd::Previous::Value(value.clone())),
+                unix_client.delete(unix_key.clone()).await.unwrap()
+            );
+            assert!(matches!(
+                kvsd::client::tcp::UnauthenticatedClient::from_unix_socket(&unix_socket)
+                    .await
+                    .unwrap()
+                    .authenticate("test", "wrong")
+                    .await,
+                Err(kvsd::KvsdError::Unauthenticated)
+            ));
+        }
+
+        // Roles restrict the operations.
+        {
+            let mut reader =
+                kvsd::client::tcp::UnauthenticatedClient::insecure_from_addr(addr.0, addr.1)
+                    .await
+                    .unwrap()
+                    .authenticate("reader", "reader")
+                    .await
+                    .unwrap();
+            let reader_key = kvsd::Key::new("reader1").unwrap();
+            client.set(reader_key.clone(), value.clone()).await.unwrap();
+            assert_eq!(
+                Some(value.clone()),
+                reader.get(reader_key.clone()).await.unwrap()
+            );
+            assert!(matches!(
+                reader.delete(reader_key.clone()).await,
+                Err(kvsd::KvsdError::Unauthorized(_))
+            ));
+            assert!(matches!(
+                reader.create_table("default".into(), "reader".into()).await,
+                Err(kvsd::KvsdError::Unauthorized(_))
+            ));
+            assert!(matches!(
+                reader
+                    .compact_table("default".into(), "default".into())
+                    .await,
+                Err(kvsd::KvsdError::Unauthorized(_))
+            ));
+            // Using the table requires only the read permission on it.
+            reader
+                .use_table("default".into(), "default".into())
+                .await
+                .unwrap();
+            assert_eq!(
+                Some(value.clone()),
+                reader.get(reader_key.clone()).await.unwrap()
+            );
+            assert!(matches!(
+                reader.use_table("ns1".into(), "table1".into()).await,
+                Err(kvsd::KvsdError::Unauthorized(_))
+            ));
+            assert_eq!(
+                Some(kvsd::Previous::Value(value.clone())),
+                client.delete(reader_key).await.unwrap()
+            );
+        }
+
+        // Responses are bounded by the client limits.
+        {
+            let large_key = kvsd::Key::new("large").unwrap();
+            let large_value = kvsd::Value::new(vec![b'x'; 1024 * 1024]).unwrap();
+            client
+                .set(large_key.clone(), large_value.clone())
+                .await
+                .unwrap();
+
+            let mut limited =
+                kvsd::client::tcp::UnauthenticatedClient::insecure_from_addr(addr.0, addr.1)
+                    .await
+                    .unwrap()
+                    .authenticate("test", "test")
+                    .await
+                    .unwrap();
+            limited.set_max_response_bytes(1024);
+            assert!(limited.get(large_key.clone()).await.is_err());
+
+            let mut raised =
+                kvsd::client::tcp::UnauthenticatedClient::insecure_from_addr(addr.0, addr.1)
+                    .await
+                    .unwrap()
+                    .authenticate("test", "test")
+                    .await
+                    .unwrap();
+            raised.set_max_response_bytes(2 * 1024 * 1024);
+            assert_eq!(
+                Some(large_value),
+                raised.get(large_key.clone()).await.unwrap()
+            );
+            client.delete(large_key).await.unwrap();
+        }
+
+        // Http
+        #[cfg(feature = "http")]
+        {
+            let basic = Some("Basic dGVzdDp0ZXN0"); // test:test
+            let bearer = Some("Bearer test-token");
+
+            let (status, _) = http(http_addr, "GET", "/v1/default/default/http1", None, b"").await;
+            assert_eq!(status, 401);
+            let (status, _) = http(
+                http_addr,
+                "GET",
+                "/v1/default/default/http1",
+                Some("Bearer wrong"),
+                b"",
+            )
+            .await;
+            assert_eq!(status, 401);
+
+            let (status, body) = http(
+                http_addr,
+                "PUT",
+                "/v1/default/default/http%2F1",
+                basic,
+                b"value\r\n1",
+            )
+            .await;
+            assert_eq!(status, 200);
+            assert!(body.starts_with(br#"{"version":"#));
+            let (status, body) =
+                http(http_addr, "GET", "/v1/default/default/http/1", bearer, b"").await;
+            assert_eq!((status, body.as_slice()), (200, b"value\r\n1".as_ref()));
+            let (status, _) = http(
+                http_addr,
+                "PUT",
+                "/v1/default/default/http/2?ttl=100",
+                bearer,
+                b"value2",
+            )
+            .await;
+            assert_eq!(status, 200);
+
+            let (status, body) = http(
+                http_addr,
+                "GET",
+                "/v1/default/default?prefix=http%2F&limit=1",
+                bearer,
+                b"",
+            )
+            .await;
+            assert_eq!(
+                (status, String::from_utf8(body).unwrap()),
+                (
+                    200,
+                    r#"{"keys":["http/1"],"next_cursor":"http/1"}"#.to_owned()
+                )
+            );
+            let (status, body) = http(
+                http_addr,
+                "GET",
+                "/v1/default/default?prefix=http%2F&cursor=http%2F1",
+                bearer,
+                b"",
+            )
+            .await;
+            assert_eq!(
+                (status, String::from_utf8(body).unwrap()),
+                (200, r#"{"keys":["http/2"],"next_cursor":null}"#.to_owned())
+            );
+
+            let (status, body) = http(
+                http_addr,
+                "DELETE",
+                "/v1/default/default/http/1",
+                bearer,
+                b"",
+            )
+            .await;
+            assert_eq!(
+                (status, body.as_slice()),
+                (200, br#"{"deleted":true}"#.as_ref())
+            );
+            let (status, _) =
+                http(http_addr, "GET", "/v1/default/default/http/1", bearer, b"").await;
+            assert_eq!(status, 404);
+            let (status, body) =
+                http(http_addr, "GET", "/v1/default/none/http/1", bearer, b"").await;
+            assert_eq!(status, 404);
+            assert!(String::from_utf8(body).unwrap().contains("TABLE_NOT_FOUND"));
+            let (status, _) =
+                http(http_addr, "POST", "/v1/default/default/http/1", bearer, b"").await;
+            assert_eq!(status, 405);
+        }
+
         // Notify shutdown
         shutdown.notify_one();
 
@@ -90,3 +840,40 @@ fn key_value_crud() {
         server_handler.await.unwrap().unwrap();
     });
 }
+
+// Send the http request and return the status and the body.
+#[cfg(feature = "http")]
+async fn http(
+    addr: (&str, u16),
+    method: &str,
+    path: &str,
+    authorization: Option<&str>,
+    body: &[u8],
+) -> (u16, Vec<u8>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+        method,
+        path,
+        addr.0,
+        body.len()
+    );
+    if let Some(authorization) = authorization {
+        request.push_str(&format!("Authorization: {}\r\n", authorization));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await.unwrap();
+    stream.write_all(body).await.unwrap();
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.unwrap();
+
+    let header_end = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let status = std::str::from_utf8(&response[9..12])
+        .unwrap()
+        .parse()
+        .unwrap();
+    (status, response[header_end + 4..].to_vec())
+}